@@ -1,11 +1,32 @@
-use nalgebra::{Point3, Vector3, Unit, vector, point};
-use rand::Rng;
-use rand_distr::StandardNormal;
-use tracing::debug;
+pub mod aperture;
+pub mod exposure;
+pub mod framing;
+pub mod path;
+pub mod projection;
+pub mod stereo;
 
-use crate::{Scalar, image::AspectRatio, geometry::Ray};
+pub use aperture::{Aperture, ApertureMask};
+pub use exposure::Exposure;
+pub use framing::Framing;
+pub use path::CameraPath;
+pub use projection::{FisheyeMapping, Projection};
+pub use stereo::{Eye, StereoRig};
 
-#[derive(Debug)]
+use std::{cmp::Ordering, fmt};
+
+use nalgebra::{Point2, Point3, Vector3, Unit};
+
+use crate::{Scalar, fingerprint::{Fingerprint, Fingerprinter}, image::AspectRatio, geometry::Ray, sampler::{IndependentSampler, Sampler}};
+use stereo::StereoEye;
+
+/// height of a full frame 35mm sensor in scene units (metres), used to turn a field
+/// of view into a physical focal length so that f-numbers behave like a real lens
+pub const SENSOR_HEIGHT: f64 = 0.024;
+
+/// lengths and angles (as sines) below this are treated as zero when setting up the view
+const DEGENERATE_EPSILON: f64 = 1e-9;
+
+#[derive(Debug, Clone)]
 struct Viewport<T>
 where
     T: Scalar
@@ -22,13 +43,6 @@ impl<T> Viewport<T>
 where
     T: Scalar
 {
-    
-    pub fn new_from_scalar(origin: Point3<T>, width: T, height: T) -> Self {
-        let horizontal = vector![width, T::zero(), T::zero()]; 
-        let vertical = vector![T::zero(), height, T::zero()];
-        Self { origin, horizontal, vertical }
-    }
-
     #[tracing::instrument]
     pub fn new_from_basis(origin: Point3<T>, horizontal: Vector3<T>, vertical: Vector3<T>) -> Self {
         Self { origin, horizontal, vertical }
@@ -46,7 +60,37 @@ where
     }
 }
 
-#[derive(Debug)]
+/// a camera set up so that it can't work out which way it's facing, or can't be exposed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CameraError {
+    /// the camera is looking at its own position
+    CoincidentTarget,
+    /// the up vector has no length
+    ZeroUp,
+    /// the camera is looking straight along its up vector, so any roll would fit
+    UpParallelToView,
+    /// the exposure asked for would let no light through, from a pinhole, a zero exposure
+    /// time or a zero iso
+    NoLight,
+}
+
+impl fmt::Display for CameraError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CameraError::CoincidentTarget => write!(f, "camera target is at the camera position"),
+            CameraError::ZeroUp => write!(f, "camera up vector has zero length"),
+            CameraError::UpParallelToView => write!(f, "camera is looking along its up vector, give a different up"),
+            CameraError::NoLight => write!(f, "camera exposure needs a positive iso, f-number and exposure time"),
+        }
+    }
+}
+
+impl std::error::Error for CameraError {}
+
+/// a thin lens camera, rays leave from a point on the lens and pass through the
+/// viewport, which always sits on the plane of perfect focus. the projection decides
+/// whether the lens sits at a single point or is swept across the whole view
+#[derive(Debug, Clone)]
 pub struct Camera<T>
 where
     T: Scalar
@@ -55,139 +99,480 @@ where
     u: Unit<Vector3<T>>,
     v: Unit<Vector3<T>>,
     w: Unit<Vector3<T>>,
-    orientation: Unit<Vector3<T>>,
-    focal_length: T,
+    projection: Projection<T>,
+    aspect_ratio: AspectRatio,
+    /// distance from the lens to the plane in perfect focus
+    focus_distance: T,
     viewport: Viewport<T>,
-    // defocus disk size
+    /// radius of the lens, zero gives a pinhole with everything in focus
     aperture: T,
-    // corner: Point3<T>,
-    // horizontal: Vector3<T>,
-    // vertical: Vector3<T>,
+    /// shape of the lens opening
+    aperture_shape: Aperture<T>,
+    /// rays are spread uniformly over the time the shutter is open for motion blur
+    shutter_open: T,
+    shutter_close: T,
+    /// set when this is one eye of a [`StereoRig`]
+    stereo: Option<StereoEye<T>>,
+    /// None leaves radiance as it is
+    exposure: Option<Exposure<T>>,
 }
 
 impl<T> Camera<T>
 where
      T: Scalar
 {
-    // pub fn new_with_focus(origin: Point3<T>, look_at: Point3<T>, up: Vector3<T>) -> Self {
-    //     let focus = look_at - origin;
-
-    //     todo!();
+    /// a pinhole camera at `origin` with the given basis, `w` points backwards out of the camera
+    fn from_basis(origin: Point3<T>, u: Unit<Vector3<T>>, v: Unit<Vector3<T>>, w: Unit<Vector3<T>>, projection: Projection<T>, aspect_ratio: AspectRatio) -> Self {
+        Self {
+            origin, u, v, w,
+            projection,
+            aspect_ratio,
+            focus_distance: T::one(),
+            viewport: Viewport::new_from_basis(origin, Vector3::zeros(), Vector3::zeros()),
+            aperture: T::zero(),
+            aperture_shape: Aperture::Circle,
+            shutter_open: T::zero(),
+            shutter_close: T::zero(),
+            stereo: None,
+            exposure: None,
+        }.with_viewport()
+    }
 
-    //     // Self {
-    //     //     origin: origin,
-    //     //     orientation: 
-    //     // }
-    // }
+    /// recalculates the viewport to match the current basis, field of view and focus
+    fn with_viewport(self) -> Self {
+        let Some(viewport_height) = self.projection.height_at(self.focus_distance) else {
+            // panoramas don't go through a viewport at all
+            return self;
+        };
+        let viewport_width = viewport_height * self.aspect();
+        let horizontal = self.u.into_inner() * viewport_width;
+        let vertical = self.v.into_inner() * viewport_height;
+        let viewport_origin = self.origin - horizontal / T::TWO - vertical / T::TWO - self.w.into_inner() * self.focus_distance;
+        Self { viewport: Viewport::new_from_basis(viewport_origin, horizontal, vertical), ..self }
+    }
 
+    /// a uniformly distributed point within the lens opening centred on `centre`
+    fn ray_source(&self, centre: Point3<T>, lens: Point2<T>) -> Point3<T> {
+        if self.aperture <= T::zero() {
+            return centre;
+        }
+        let lens = self.aperture_shape.sample(lens.x, lens.y) * self.aperture;
+        centre + self.u.into_inner() * lens.x + self.v.into_inner() * lens.y
+    }
 
-    fn ray_source(&self) -> Point3<T> {
-        let mut rng = rand::thread_rng();
-        let (delta_u, delta_v) = (T::from_float(rng.sample(StandardNormal)), T::from_float(rng.sample(StandardNormal)));
-        let defocus = (self.u.into_inner() * delta_u + self.v.into_inner() * delta_v) * self.aperture;
-        self.origin + defocus
+    /// the moment `t` of the way between the shutter opening and closing
+    fn ray_time(&self, t: T) -> T {
+        if self.shutter_close <= self.shutter_open {
+            return self.shutter_open;
+        }
+        self.shutter_open + (self.shutter_close - self.shutter_open) * t
     }
 
+    /// the ray through image coordinates (u, v) in 0..1, or None if nothing is seen there.
+    /// the lens and shutter are sampled with fresh random numbers from the thread's rng, so
+    /// with an aperture or an open shutter it isn't reproducible, and two calls with the same
+    /// (u, v) give different rays. renders should use [`Camera::sample_ray`] so they come from
+    /// the render's seeded sampler instead
     pub fn ray(&self, u: T, v: T) -> Option<Ray<T>> {
-        Some(Ray::from_focus_point(self.ray_source(), self.viewport.point(u, v)?))
+        self.sample_ray(u, v, &mut IndependentSampler::new(rand::random()))
+    }
+
+    /// the ray through image coordinates (u, v) in 0..1, or None if nothing is seen there,
+    /// taking the point on the lens and the moment in the shutter from `sampler`. the same
+    /// dimensions are always drawn so the dimensions of later decisions don't shift about
+    pub fn sample_ray<S>(&self, u: T, v: T, sampler: &mut S) -> Option<Ray<T>>
+    where
+        S: Sampler<T> + ?Sized
+    {
+        let lens = sampler.next_2d();
+        let time = self.ray_time(sampler.next_1d());
+        let (source, target) = match self.projection {
+            Projection::Perspective { .. } => {
+                let target = self.viewport.point(u, v)?;
+                (self.ray_source(self.origin, lens), target)
+            },
+            Projection::Orthographic { .. } => {
+                // every pixel gets its own lens directly behind it, so the rays run parallel
+                let target = self.viewport.point(u, v)?;
+                (self.ray_source(target + self.w.into_inner() * self.focus_distance, lens), target)
+            },
+            Projection::Equirectangular | Projection::Fisheye { .. } => {
+                let clamp = T::zero()..=T::one();
+                if !(clamp.contains(&u) && clamp.contains(&v)) {
+                    return None;
+                }
+                let local = self.projection.panoramic_direction(u, v, self.aspect())?;
+                (self.origin, self.origin + self.to_world(local))
+            },
+        };
+        let (source, target) = match self.stereo {
+            Some(eye) => self.eye_ray(eye, source, target),
+            None => (source, target),
+        };
+        Some(Ray::from_focus_point(source, target).with_time(time))
+    }
+
+    /// moves a ray from the centre of a stereo rig across to one eye
+    fn eye_ray(&self, eye: StereoEye<T>, source: Point3<T>, target: Point3<T>) -> (Point3<T>, Point3<T>) {
+        let converges = eye.convergence < T::INF;
+        let offset = self.u.into_inner() * eye.offset;
+        match self.projection {
+            Projection::Perspective { .. } => {
+                // shift the part of the viewport that lands on the convergence plane back in
+                // front of the centre, giving an off-axis frustum rather than toeing the eyes in
+                let shift = if converges { T::one() - self.focus_distance / eye.convergence } else { T::one() };
+                (source + offset, target + offset * shift)
+            },
+            Projection::Orthographic { .. } => (source + offset, target + offset),
+            Projection::Equirectangular | Projection::Fisheye { .. } => {
+                let direction = target - self.origin;
+                let offset = if let Projection::Equirectangular = self.projection {
+                    // omni-directional stereo, each direction is seen by eyes on a circle either side
+                    // of it. the horizontal right vector shrinks towards the poles, so the eyes meet there
+                    direction.cross(&self.v) * eye.offset
+                } else {
+                    offset
+                };
+                let target = if converges { self.origin + direction * eye.convergence } else { target + offset };
+                (source + offset, target)
+            },
+        }
     }
 
+    /// turns this into one eye of a stereo pair
+    pub(crate) fn with_stereo_eye(self, eye: StereoEye<T>) -> Camera<T> {
+        Self { stereo: Some(eye), ..self }
+    }
+
+    /// converts a direction from camera space into the world
+    fn to_world(&self, local: Vector3<T>) -> Vector3<T> {
+        self.u.into_inner() * local.x + self.v.into_inner() * local.y + self.w.into_inner() * local.z
+    }
+
+    /// switches projection, keeping the position, orientation and focus. panoramic projections
+    /// will usually want a different aspect ratio as well
+    pub fn with_projection(self, projection: Projection<T>) -> Camera<T> {
+        Self { projection, ..self }.with_viewport()
+    }
+
+    /// sets the radius of the lens directly, in scene units
     pub fn with_aperture(self, aperture: T) -> Camera<T> {
         Self { aperture, ..self }
     }
 
-    // pub fn from_vfov(vertical_fov: T, aspect_ratio: AspectRatio) -> Camera<T> {
-    //     let origin = Point3::origin();
-    //     let theta = degrees_to_radians(vertical_fov);
-    //     let h = (theta / T::TWO).tan();
+    /// changes the shape of the lens opening, the radius still comes from the aperture or f-number
+    pub fn with_aperture_shape(self, aperture_shape: Aperture<T>) -> Camera<T> {
+        Self { aperture_shape, ..self }
+    }
 
-    //     let viewport_height = T::TWO * h;
-    //     let viewport_width = viewport_height * T::from_float(aspect_ratio.as_f64());
+    /// sets the lens radius from an f-number, the ratio of focal length to lens diameter.
+    /// panoramic projections are always a pinhole and are left as they are
+    pub fn with_f_number(self, f_number: T) -> Camera<T> {
+        match self.focal_length() {
+            Some(focal_length) => Self { aperture: focal_length / (T::TWO * f_number), ..self },
+            None => self,
+        }
+    }
 
-    //     let focus = point![T::zero(), T::zero(), -T::one()];
-    //     let focal_length = (focus - origin).norm();
+    /// moves the plane of perfect focus to `focus_distance` in front of the lens
+    pub fn with_focus_distance(self, focus_distance: T) -> Camera<T> {
+        Self { focus_distance, ..self }.with_viewport()
+    }
 
-    //     let horizontal = vector![viewport_width, T::zero(), T::zero()];
-    //     let vertical = vector![T::zero(), viewport_height, T::zero()];
-        
-    //     let depth = vector![T::zero(), T::zero(), focal_length];
-        
-    //     let viewport_origin = origin - horizontal / T::TWO - vertical / T::TWO - depth;
-    
-    //     Self {
-    //         origin: origin,
-    //         orientation: Unit::new_normalize(depth),
-    //         focal_length: focal_length,
-    //         viewport: Viewport::new_from_basis(viewport_origin, horizontal, vertical),
-    //         aperture: 0
-    //     }
+    /// focuses on the plane through `point` facing the camera
+    pub fn with_focus_point(self, point: Point3<T>) -> Camera<T> {
+        let focus_distance = (point - self.origin).dot(&-self.w.into_inner());
+        self.with_focus_distance(focus_distance)
+    }
 
+    /// opens the shutter over [open, close], anything animated over that interval will blur
+    pub fn with_shutter(self, open: T, close: T) -> Camera<T> {
+        Self { shutter_open: open, shutter_close: close, ..self }
+    }
 
-    // }
+    /// scales radiance as a physical camera with these settings would, see [`Exposure`]
+    pub fn with_exposure(self, exposure: Exposure<T>) -> Camera<T> {
+        Self { exposure: Some(exposure), ..self }
+    }
 
-    pub fn look_at(origin: Point3<T>, focus: Point3<T>, vertical_fov: T, aspect_ratio: AspectRatio) -> Camera<T> {
-        let theta = degrees_to_radians(vertical_fov);
-        let h = (theta / 2.0.into()).tan();
-        let viewport_height = T::TWO * h;
-        let viewport_width = viewport_height * T::from_float(aspect_ratio.as_f64());
+    pub fn exposure(&self) -> Option<Exposure<T>> {
+        self.exposure
+    }
+
+    /// exposes as a real camera at this iso would with the sensor exposed for `exposure_time`
+    /// seconds behind this camera's lens, see [`Exposure::from_iso`]. the exposure is worked
+    /// out now, so changing the aperture afterwards doesn't change it. fails for a pinhole,
+    /// including the default camera and every panoramic projection, which has no f-number
+    pub fn with_iso(self, iso: T, exposure_time: T) -> Result<Camera<T>, CameraError> {
+        Exposure::from_iso(iso, self.f_number(), exposure_time).map(|exposure| self.with_exposure(exposure))
+    }
+
+    /// the factor the renderer multiplies radiance by before it's stored in the image
+    pub fn exposure_scale(&self) -> T {
+        self.exposure.map_or(T::one(), |exposure| exposure.scale())
+    }
+
+    pub fn shutter(&self) -> (T, T) {
+        (self.shutter_open, self.shutter_close)
+    }
+
+    pub fn origin(&self) -> Point3<T> {
+        self.origin
+    }
+
+    /// the direction the camera is looking
+    pub fn orientation(&self) -> Unit<Vector3<T>> {
+        -self.w
+    }
+
+    pub fn focus_distance(&self) -> T {
+        self.focus_distance
+    }
+
+    pub fn aperture(&self) -> T {
+        self.aperture
+    }
+
+    pub fn aperture_shape(&self) -> &Aperture<T> {
+        &self.aperture_shape
+    }
+
+    pub fn projection(&self) -> Projection<T> {
+        self.projection
+    }
+
+    /// changes the shape of the image, which should match the buffer being rendered into
+    pub fn with_aspect_ratio(self, aspect_ratio: AspectRatio) -> Camera<T> {
+        Self { aspect_ratio, ..self }.with_viewport()
+    }
+
+    pub fn aspect_ratio(&self) -> AspectRatio {
+        self.aspect_ratio
+    }
+
+    /// width over height
+    fn aspect(&self) -> T {
+        T::from_float(self.aspect_ratio.as_f64())
+    }
+
+    /// the physical focal length of a lens giving this field of view on a full frame sensor,
+    /// orthographic views use the lens that frames their plane of focus the same way. None for
+    /// panoramic projections, which don't map the view through a lens onto a flat sensor
+    pub fn focal_length(&self) -> Option<T> {
+        self.projection.is_planar().then(|| {
+            let vertical_fov = self.projection.vertical_fov_at(self.focus_distance);
+            T::from_float(SENSOR_HEIGHT) / (T::TWO * (vertical_fov / T::TWO).tan())
+        })
+    }
+
+    /// the f-number matching the current aperture, infinite for a pinhole, which panoramic
+    /// projections always are
+    pub fn f_number(&self) -> T {
+        match self.focal_length() {
+            Some(focal_length) if self.aperture > T::zero() => focal_length / (T::TWO * self.aperture),
+            _ => T::INF,
+        }
+    }
+
+    /// a camera at `origin` looking towards and focused on `focus`, with +y up
+    pub fn look_at(origin: Point3<T>, focus: Point3<T>, vertical_fov: T, aspect_ratio: AspectRatio) -> Result<Camera<T>, CameraError> {
+        Self::look_at_with_up(origin, focus, vup(), vertical_fov, aspect_ratio)
+    }
+
+    /// a camera at `origin` looking towards and focused on `focus`, rolled so that `up` points
+    /// up the image as far as it can. fails if the view direction can't be found or runs along `up`
+    pub fn look_at_with_up(origin: Point3<T>, focus: Point3<T>, up: Vector3<T>, vertical_fov: T, aspect_ratio: AspectRatio) -> Result<Camera<T>, CameraError> {
+        Self::default()
+            .with_projection(Projection::perspective(vertical_fov))
+            .with_aspect_ratio(aspect_ratio)
+            .with_look_at(origin, focus, up)
+    }
+
+    /// moves the camera to `origin` and turns it to look towards and focus on `focus`, like
+    /// [`Camera::look_at_with_up`], keeping its projection, lens, shutter and exposure
+    pub fn with_look_at(self, origin: Point3<T>, focus: Point3<T>, up: Vector3<T>) -> Result<Camera<T>, CameraError> {
+        let distance = (focus - origin).norm();
+        if degenerate(distance) {
+            return Err(CameraError::CoincidentTarget);
+        }
+        let up_length = up.norm();
+        if degenerate(up_length) {
+            return Err(CameraError::ZeroUp);
+        }
 
         // positive w is behind the camera
-        let w = Unit::new_normalize(origin - focus);
-        let u = Unit::new_normalize(vup().cross(&w));
+        let w = Unit::new_unchecked((origin - focus) / distance);
+        let right = (up / up_length).cross(&w);
+        let sin_angle = right.norm();
+        if degenerate(sin_angle) {
+            return Err(CameraError::UpParallelToView);
+        }
+        let u = Unit::new_unchecked(right / sin_angle);
         let v = Unit::new_normalize(w.cross(&u));
 
-        let horizontal = u.into_inner() * viewport_width;
-        let vertical = v.into_inner() * viewport_height;
-        let viewport_origin = origin - horizontal / T::TWO - vertical / T::TWO - w.into_inner();
-    
-        Self {
-            origin, u, v, w,
-            orientation: -w,
-            focal_length: w.norm(),
-            viewport: Viewport::new_from_basis(viewport_origin, horizontal, vertical),
-            aperture: T::zero(),
-        }
+        Ok(Self { origin, u, v, w, ..self }.with_focus_distance(distance))
+    }
+
+    /// rolls the camera anticlockwise about its view direction by `degrees`
+    pub fn with_roll(self, degrees: T) -> Camera<T> {
+        let (sin, cos) = (degrees * T::pi() / T::from_float(180.0)).sin_cos();
+        let (u, v) = (self.u.into_inner(), self.v.into_inner());
+        let u = Unit::new_normalize(u * cos + v * sin);
+        let v = Unit::new_normalize(self.w.cross(&u));
+        Self { u, v, ..self }.with_viewport()
+    }
+
+    /// the camera's up vector, the direction of increasing v in the image
+    pub fn up(&self) -> Unit<Vector3<T>> {
+        self.v
+    }
+
+    /// the camera's right vector, the direction of increasing u in the image
+    pub fn right(&self) -> Unit<Vector3<T>> {
+        self.u
     }
 }
 
-fn degrees_to_radians<T: Scalar>(n: T) -> T {
-    n * T::pi() / T::from_float(180.0)
+/// true for lengths too small to divide by, and for NaN so that bad positions are caught too
+fn degenerate<T: Scalar>(length: T) -> bool {
+    length.partial_cmp(&T::from_float(DEGENERATE_EPSILON)) != Some(Ordering::Greater)
 }
 
-/// return the "view-up" vector, +y in the universal reference frame
-fn vup<T: Scalar>() -> Unit<Vector3<T>> {
-    Vector3::y_axis()
-    // Unit::new_normalize(vector![T::zero(), T::one(), T::zero()])
+/// the default "view-up" vector, +y in the universal reference frame
+fn vup<T: Scalar>() -> Vector3<T> {
+    Vector3::y()
 }
 
 impl<T> Default for Camera<T>
 where
     T: Scalar
 {
+    /// looks down -z with a 90 degree vertical field of view and a pinhole lens
     fn default() -> Self {
-        let viewport_height = T::from_float(2.0);
-        let viewport_width = viewport_height * T::from_float(AspectRatio::default().as_f64());
-        let focal_length = T::from_float(1.0);
-
-        let camera_origin = Point3::origin();
-        let horizontal = vector![viewport_width, T::zero(), T::zero()];
-        let vertical = vector![T::zero(), viewport_height, T::zero()];
-        let depth = vector![T::zero(), T::zero(), focal_length];
-        let viewport_origin = camera_origin - horizontal / T::from_float(2.0) - vertical / T::from_float(2.0) - depth;
-        
-        let u = Unit::new_normalize(horizontal);
-        let v = Unit::new_normalize(vertical);
-        let w = Unit::new_normalize(depth);
+        let projection = Projection::Perspective { vertical_fov: T::frac_pi_2() };
+        Self::from_basis(Point3::origin(), Vector3::x_axis(), Vector3::y_axis(), Vector3::z_axis(), projection, AspectRatio::default())
+    }
+}
 
-        Self {
-            origin: camera_origin,
-            u, v, w,
-            orientation: -w,
-            focal_length,
-            viewport: Viewport::new_from_basis(viewport_origin, horizontal, vertical),
-            aperture: T::zero()
-        }
+impl<T> Fingerprint for Camera<T>
+where
+    T: Scalar
+{
+    /// the viewport is worked out from everything else, so it's left out
+    fn fingerprint(&self, state: &mut Fingerprinter) {
+        state.add(&self.origin).add(&self.u).add(&self.v).add(&self.w);
+        state.add(&self.projection).add(&self.aspect_ratio);
+        state.scalar(self.focus_distance);
+        state.scalar(self.aperture);
+        state.add(&self.aperture_shape);
+        state.scalar(self.shutter_open);
+        state.scalar(self.shutter_close);
+        state.add(&self.stereo).add(&self.exposure);
     }
 }
 
+#[cfg(test)]
+mod test {
+    use nalgebra::point;
+
+    use super::*;
+
+    #[test]
+    pub fn test_thin_lens_rays_converge_on_the_focal_plane() {
+        let focus = point![0.3, -0.2, -4.0];
+        let camera = Camera::<f64>::look_at(point![0.0, 0.0, 0.0], point![0.0, 0.0, -4.0], 40.0, AspectRatio::default())
+            .unwrap()
+            .with_f_number(1.4)
+            .with_focus_point(focus);
+        assert!((camera.focus_distance() - 4.0).abs() < 1e-12);
+        assert!((camera.f_number() - 1.4).abs() < 1e-12);
+
+        // every ray through the same viewport coordinate should pass through the same in-focus point
+        let target = camera.viewport.point(0.6, 0.45).unwrap();
+        for _ in 0..32 {
+            let ray = camera.ray(0.6, 0.45).unwrap();
+            let depth = (target - ray.origin()).norm();
+            assert!((ray.project(depth) - target).norm() < 1e-9);
+            // and start somewhere on the lens
+            assert!((ray.origin() - camera.origin()).norm() <= camera.aperture() + 1e-12);
+        }
+    }
+
+    #[test]
+    pub fn test_iso_exposure_follows_the_lens() {
+        let camera = Camera::<f64>::default().with_f_number(4.0).with_iso(100.0, 0.01).unwrap();
+        let expected = Exposure::from_ev100(1600.0_f64.log2()).scale();
+        assert!((camera.exposure_scale() / expected - 1.0).abs() < 1e-12);
+        // a stop down on the lens halves the light, twice the exposure time doubles it again
+        let stopped_down = Camera::<f64>::default().with_f_number(4.0 * 2.0_f64.sqrt()).with_iso(100.0, 0.02).unwrap();
+        assert!((stopped_down.exposure_scale() / camera.exposure_scale() - 1.0).abs() < 1e-12);
+        // the motion blur shutter is in scene time and has nothing to do with it
+        let blurred = Camera::<f64>::default().with_f_number(4.0).with_shutter(0.0, 10.0).with_iso(100.0, 0.01).unwrap();
+        assert_eq!(blurred.exposure_scale(), camera.exposure_scale());
+        // a pinhole, like the default camera, can't be exposed by iso rather than going black
+        assert_eq!(Camera::<f64>::default().with_iso(100.0, 0.01).unwrap_err(), CameraError::NoLight);
+    }
+
+    #[test]
+    pub fn test_orthographic_rays_are_parallel() {
+        let camera = Camera::<f64>::look_at(point![0.0, 0.0, 5.0], point![0.0, 0.0, 0.0], 40.0, AspectRatio::new(2, 1))
+            .unwrap()
+            .with_projection(Projection::orthographic(3.0));
+        let corner = camera.ray(0.0, 0.0).unwrap();
+        let centre = camera.ray(0.5, 0.5).unwrap();
+        assert_eq!(corner.orientation(), centre.orientation());
+        assert!((centre.origin() - point![0.0, 0.0, 5.0]).norm() < 1e-12);
+        // the view is 3 high and 6 wide whatever the distance
+        assert!((corner.origin() - point![-3.0, -1.5, 5.0]).norm() < 1e-12);
+    }
+
+    #[test]
+    pub fn test_fisheye_sees_nothing_outside_its_image_circle() {
+        let camera = Camera::<f64>::default()
+            .with_projection(Projection::fisheye(180.0, FisheyeMapping::Equisolid));
+        assert!(camera.ray(0.5, 0.5).is_some());
+        // the circle fits the height of a 16:9 frame so the corners are dark
+        assert!(camera.ray(0.0, 0.0).is_none());
+        // a panorama has no focal length, and stays a pinhole whatever it's asked for
+        assert_eq!(camera.focal_length(), None);
+        let stopped = camera.with_f_number(2.0);
+        assert_eq!((stopped.aperture(), stopped.f_number()), (0.0, f64::INFINITY));
+        assert!(Camera::<f64>::default().focal_length().is_some());
+    }
+
+    #[test]
+    pub fn test_looking_along_y_needs_another_up() {
+        for target in [point![0.0, 5.0, 0.0], point![0.0, -5.0, 0.0]] {
+            let result = Camera::<f64>::look_at(Point3::origin(), target, 60.0, AspectRatio::default());
+            assert_eq!(result.unwrap_err(), CameraError::UpParallelToView);
+
+            let camera = Camera::look_at_with_up(Point3::origin(), target, -Vector3::z(), 60.0, AspectRatio::default())
+                .expect("a different up vector should work");
+            let centre = camera.ray(0.5, 0.5).unwrap();
+            assert!((centre.orientation().into_inner() - target.coords / 5.0).norm() < 1e-12);
+            for (u, v) in [(0.0, 0.0), (1.0, 1.0), (0.2, 0.7)] {
+                let ray = camera.ray(u, v).unwrap();
+                assert!(ray.orientation().iter().all(|x| x.is_finite()));
+            }
+            // the top of the image points along the given up, -z here
+            assert!((camera.up().into_inner() + Vector3::z()).norm() < 1e-12);
+        }
 
+        let origin = Point3::<f64>::origin();
+        assert_eq!(Camera::look_at(origin, origin, 60.0, AspectRatio::default()).unwrap_err(), CameraError::CoincidentTarget);
+        assert_eq!(
+            Camera::look_at_with_up(origin, point![1.0, 0.0, 0.0], Vector3::zeros(), 60.0, AspectRatio::default()).unwrap_err(),
+            CameraError::ZeroUp,
+        );
+    }
+
+    #[test]
+    pub fn test_roll_turns_the_image() {
+        let camera = Camera::<f64>::default().with_roll(90.0);
+        // rolled a quarter turn anticlockwise, right now points up
+        assert!((camera.right().into_inner() - Vector3::y()).norm() < 1e-12);
+        assert!((camera.up().into_inner() + Vector3::x()).norm() < 1e-12);
+        let centre = camera.ray(0.5, 0.5).unwrap();
+        assert!((centre.orientation().into_inner() + Vector3::z()).norm() < 1e-12);
+    }
+}
@@ -0,0 +1,241 @@
+use std::{fs, io, path::Path, sync::Arc};
+
+use nalgebra::{point, Point2};
+
+use crate::{Scalar, fingerprint::{Fingerprint, Fingerprinter}, geometry::surface::concentric_disk};
+
+/// the shape of the lens opening, which is also the shape out of focus highlights take on.
+/// every shape is sampled within the unit disk and scaled by the camera's aperture radius
+#[derive(Debug, Clone, Default)]
+pub enum Aperture<T>
+where
+    T: Scalar
+{
+    #[default]
+    Circle,
+    /// a regular polygon with `blades` corners on the unit circle, turned by `rotation` radians
+    Polygon { blades: usize, rotation: T },
+    /// an arbitrary grayscale image stretched over the square around the unit disk
+    Mask(Arc<ApertureMask<T>>),
+}
+
+impl<T> Aperture<T>
+where
+    T: Scalar
+{
+    /// panics with fewer than three blades, which don't close a shape
+    pub fn polygon(blades: usize, rotation: T) -> Self {
+        assert!(blades >= 3, "a polygonal aperture needs at least three blades");
+        Aperture::Polygon { blades, rotation }
+    }
+
+    pub fn mask(mask: ApertureMask<T>) -> Self {
+        Aperture::Mask(Arc::new(mask))
+    }
+
+    /// maps a pair of uniform numbers in 0..1 to a point uniformly distributed over the opening
+    pub fn sample(&self, u: T, v: T) -> Point2<T> {
+        match self {
+            Aperture::Circle => concentric_disk(u, v),
+            Aperture::Polygon { blades, rotation } => sample_polygon(*blades, *rotation, u, v),
+            Aperture::Mask(mask) => mask.sample(u, v),
+        }
+    }
+}
+
+/// picks one of the triangles fanning out from the centre, then a uniform point inside it
+fn sample_polygon<T: Scalar>(blades: usize, rotation: T, u: T, v: T) -> Point2<T> {
+    let scaled = u * T::from_float(blades as f64);
+    let blade = (scaled.floor().to_float() as usize).min(blades - 1);
+    // reuse what's left of u once the triangle has been chosen
+    let u = scaled - T::from_float(blade as f64);
+
+    let step = T::two_pi() / T::from_float(blades as f64);
+    let corner = |i: usize| {
+        let angle = rotation + step * T::from_float(i as f64);
+        point![angle.cos(), angle.sin()]
+    };
+    let (a, b) = (corner(blade), corner(blade + 1));
+
+    // uniform barycentric coordinates for the triangle (origin, a, b)
+    let root = u.sqrt();
+    let (wa, wb) = (root * (T::one() - v), root * v);
+    Point2::from(a.coords * wa + b.coords * wb)
+}
+
+/// a grayscale image used as a lens opening, brighter pixels let through more light
+#[derive(Debug, Clone)]
+pub struct ApertureMask<T>
+where
+    T: Scalar
+{
+    width: usize,
+    height: usize,
+    /// running total of the pixel weights in row order, bottom row first
+    cdf: Vec<T>,
+}
+
+impl<T> ApertureMask<T>
+where
+    T: Scalar
+{
+    /// builds a mask from weights given row by row starting at the top, like an image.
+    /// panics if the weights don't fill the image or are all zero
+    pub fn new(width: usize, height: usize, weights: &[T]) -> Self {
+        assert_eq!(weights.len(), width * height, "mask weights don't match its size");
+        let mut total = T::zero();
+        let cdf: Vec<T> = (0..height)
+            .rev()
+            .flat_map(|row| weights[row * width..(row + 1) * width].iter())
+            .map(|weight| {
+                total += weight.max(T::zero());
+                total
+            })
+            .collect();
+        assert!(total > T::zero(), "an aperture mask must let some light through");
+        Self { width, height, cdf }
+    }
+
+    /// reads a plain (P2) or binary (P5) 8 bit pgm image
+    pub fn from_pgm<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let bytes = fs::read(path)?;
+        let invalid = |msg: &str| io::Error::new(io::ErrorKind::InvalidData, msg.to_string());
+
+        // the header is four whitespace separated tokens, comments run to the end of the line
+        let mut tokens = Vec::new();
+        let mut pos = 0;
+        while tokens.len() < 4 && pos < bytes.len() {
+            match bytes[pos] {
+                b'#' => while pos < bytes.len() && bytes[pos] != b'\n' { pos += 1 },
+                c if c.is_ascii_whitespace() => pos += 1,
+                _ => {
+                    let start = pos;
+                    while pos < bytes.len() && !bytes[pos].is_ascii_whitespace() { pos += 1 }
+                    tokens.push(String::from_utf8_lossy(&bytes[start..pos]).into_owned());
+                },
+            }
+        }
+        if tokens.len() < 4 {
+            return Err(invalid("truncated pgm header"));
+        }
+        let number = |token: &str| token.parse::<usize>().map_err(|_| invalid("bad number in pgm header"));
+        let (width, height, max) = (number(&tokens[1])?, number(&tokens[2])?, number(&tokens[3])?);
+        if max == 0 || max > 255 {
+            return Err(invalid("only 8 bit pgm images are supported"));
+        }
+
+        let values: Vec<usize> = match tokens[0].as_str() {
+            "P2" => String::from_utf8_lossy(&bytes[pos..])
+                .split_ascii_whitespace()
+                .map(number)
+                .collect::<io::Result<_>>()?,
+            // exactly one whitespace byte separates the header from the raster
+            "P5" => bytes.get(pos + 1..).unwrap_or_default().iter().map(|b| *b as usize).collect(),
+            _ => return Err(invalid("not a pgm image")),
+        };
+        if values.len() < width * height {
+            return Err(invalid("truncated pgm raster"));
+        }
+
+        let weights: Vec<T> = values[..width * height]
+            .iter()
+            .map(|value| T::from_float(*value as f64 / max as f64))
+            .collect();
+        if weights.iter().all(|weight| *weight <= T::zero()) {
+            return Err(invalid("aperture mask is completely black"));
+        }
+        Ok(Self::new(width, height, &weights))
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// chooses a pixel in proportion to its brightness using `u`, then jitters within it
+    pub fn sample(&self, u: T, v: T) -> Point2<T> {
+        let total = self.cdf[self.cdf.len() - 1];
+        let target = u * total;
+        let index = self.cdf.partition_point(|sum| *sum <= target).min(self.cdf.len() - 1);
+        let below = if index == 0 { T::zero() } else { self.cdf[index - 1] };
+        let weight = self.cdf[index] - below;
+        let jitter = if weight > T::zero() { ((target - below) / weight).clamp(T::zero(), T::one()) } else { T::HALF };
+
+        let (column, row) = (index % self.width, index / self.width);
+        let x = (T::from_float(column as f64) + jitter) / T::from_float(self.width as f64);
+        let y = (T::from_float(row as f64) + v) / T::from_float(self.height as f64);
+        point![x * T::TWO - T::one(), y * T::TWO - T::one()]
+    }
+}
+
+impl<T> Fingerprint for Aperture<T>
+where
+    T: Scalar
+{
+    fn fingerprint(&self, state: &mut Fingerprinter) {
+        match self {
+            Aperture::Circle => state.usize(0),
+            Aperture::Polygon { blades, rotation } => {
+                state.usize(1);
+                state.usize(*blades);
+                state.scalar(*rotation);
+            },
+            Aperture::Mask(mask) => {
+                state.usize(2);
+                state.add(mask);
+            },
+        }
+    }
+}
+
+impl<T> Fingerprint for ApertureMask<T>
+where
+    T: Scalar
+{
+    fn fingerprint(&self, state: &mut Fingerprinter) {
+        state.usize(self.width);
+        state.usize(self.height);
+        self.cdf.iter().for_each(|weight| state.scalar(*weight));
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    pub fn test_polygon_samples_stay_inside_the_blades() {
+        let aperture = Aperture::polygon(6, 0.3_f64);
+        // the inscribed circle of a hexagon with unit corners
+        let apothem = (std::f64::consts::PI / 6.0).cos();
+        let mut outside_apothem = 0;
+        for i in 0..64 {
+            for j in 0..64 {
+                let p = aperture.sample((i as f64 + 0.5) / 64.0, (j as f64 + 0.5) / 64.0);
+                let angle = p.y.atan2(p.x) - 0.3;
+                let sector = std::f64::consts::PI / 3.0;
+                // distance to the edge along this direction
+                let offset = angle.rem_euclid(sector) - sector / 2.0;
+                assert!(p.coords.norm() * offset.cos() <= apothem + 1e-9);
+                if p.coords.norm() > apothem {
+                    outside_apothem += 1;
+                }
+            }
+        }
+        // the corners should actually be reached
+        assert!(outside_apothem > 0);
+    }
+
+    #[test]
+    pub fn test_mask_only_samples_lit_pixels() {
+        // only the top right pixel of a 2x2 mask is open
+        let mask = ApertureMask::new(2, 2, &[0.0_f64, 1.0, 0.0, 0.0]);
+        for i in 0..16 {
+            let p = mask.sample(i as f64 / 16.0, 0.7);
+            assert!(p.x >= 0.0 && p.y >= 0.0, "{p}");
+        }
+    }
+}
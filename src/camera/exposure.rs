@@ -0,0 +1,94 @@
+use crate::{Scalar, camera::CameraError, fingerprint::{Fingerprint, Fingerprinter}};
+
+/// ratio between the saturation point of a sensor and the luminance of a well exposed scene,
+/// from the saturation based speed in ISO 12232 with a lens transmission of 0.65
+const SATURATION_FACTOR: f64 = 1.2;
+
+/// how much light the camera lets through, measured in exposure value at ISO 100. each step
+/// of ev halves the exposure, so brighter scenes want higher values. radiance in physical
+/// units (cd/m²) is scaled so the brightest value the sensor can record maps to one
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Exposure<T>
+where
+    T: Scalar
+{
+    ev100: T,
+    /// stops added on top of the metered exposure, positive is brighter
+    compensation: T,
+}
+
+impl<T> Exposure<T>
+where
+    T: Scalar
+{
+    /// an exposure value at ISO 100, around 15 for a sunny day or 7 for a bright interior
+    pub fn from_ev100(ev100: T) -> Self {
+        Self { ev100, compensation: T::zero() }
+    }
+
+    /// the exposure a real camera would get at this iso behind a lens at `f_number` with the
+    /// sensor exposed for `exposure_time` seconds, see [`Camera::with_iso`] to take the
+    /// f-number from the camera. fails unless all three are positive and finite, as a pinhole
+    /// or a shutter that never opens would let no light through at all
+    ///
+    /// [`Camera::with_iso`]: crate::Camera::with_iso
+    pub fn from_iso(iso: T, f_number: T, exposure_time: T) -> Result<Self, CameraError> {
+        let usable = |value: T| value > T::zero() && value < T::INF;
+        if !(usable(iso) && usable(f_number) && usable(exposure_time)) {
+            return Err(CameraError::NoLight);
+        }
+        let ev100 = (f_number * f_number / exposure_time * T::from_float(100.0) / iso).log2();
+        Ok(Self::from_ev100(ev100))
+    }
+
+    /// brightens the image by `stops` without changing the metered exposure
+    pub fn with_compensation(self, stops: T) -> Self {
+        Self { compensation: stops, ..self }
+    }
+
+    pub fn ev100(&self) -> T {
+        self.ev100
+    }
+
+    pub fn compensation(&self) -> T {
+        self.compensation
+    }
+
+    /// the factor radiance is multiplied by before it reaches the image
+    pub fn scale(&self) -> T {
+        let max_luminance = T::from_float(SATURATION_FACTOR) * T::TWO.powf(self.ev100 - self.compensation);
+        T::one() / max_luminance
+    }
+}
+
+impl<T> Fingerprint for Exposure<T>
+where
+    T: Scalar
+{
+    fn fingerprint(&self, state: &mut Fingerprinter) {
+        state.scalar(self.ev100);
+        state.scalar(self.compensation);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    pub fn test_sunny_sixteen() {
+        // iso 100 at 1/100s and f/16 is the classic exposure for bright sunlight
+        let exposure = Exposure::from_iso(100.0_f64, 16.0, 0.01).unwrap();
+        assert!((exposure.ev100() - 25600.0_f64.log2()).abs() < 1e-12);
+        // one stop of compensation lets in twice the light, as does doubling the iso or the time
+        let brighter = exposure.with_compensation(1.0).scale();
+        assert!((brighter / exposure.scale() - 2.0).abs() < 1e-12);
+        assert!((Exposure::from_iso(200.0, 16.0, 0.01).unwrap().scale() - brighter).abs() < 1e-12);
+        assert!((Exposure::from_iso(100.0, 16.0, 0.02).unwrap().scale() - brighter).abs() < 1e-12);
+
+        // nothing that would leave the image black
+        assert_eq!(Exposure::from_iso(100.0, f64::INFINITY, 0.01), Err(CameraError::NoLight));
+        assert_eq!(Exposure::from_iso(100.0, 16.0, 0.0), Err(CameraError::NoLight));
+        assert_eq!(Exposure::from_iso(0.0, 16.0, 0.01), Err(CameraError::NoLight));
+    }
+}
@@ -0,0 +1,198 @@
+use nalgebra::{Point3, Vector3};
+
+use crate::{
+    Scalar,
+    Camera,
+    camera::{CameraError, CameraPath},
+    geometry::{Aabb, Interpolation, Keyframes},
+    image::AspectRatio,
+};
+
+/// keys per revolution of a turntable path, enough that the spline between them stays on the circle
+const TURNTABLE_KEYS: usize = 36;
+
+/// places cameras so that everything inside `bounds` is in shot. directions are given as an
+/// azimuth in degrees around +y, starting from +z and turning towards +x, and an elevation
+/// in degrees above the horizon. every camera looks at the centre of the bounds with +y up
+#[derive(Debug, Clone)]
+pub struct Framing<T>
+where
+    T: Scalar
+{
+    bounds: Aabb<T>,
+    /// extra room around the subject as a fraction of its size in the frame
+    padding: T,
+    /// in degrees, like [`Camera::look_at`]
+    vertical_fov: T,
+    aspect_ratio: AspectRatio,
+}
+
+impl<T> Framing<T>
+where
+    T: Scalar
+{
+    /// framing for `bounds` with a 90 degree field of view and a tenth of padding, see
+    /// [`StaticWorld::bounds`](crate::geometry::world::StaticWorld::bounds) for a whole scene
+    pub fn new(bounds: Aabb<T>) -> Self {
+        Self {
+            bounds,
+            padding: T::from_float(0.1),
+            vertical_fov: T::from_float(90.0),
+            aspect_ratio: AspectRatio::default(),
+        }
+    }
+
+    /// 0 lets the subject touch the edge of the frame, 0.5 leaves it filling two thirds of it
+    pub fn with_padding(self, padding: T) -> Self {
+        Self { padding, ..self }
+    }
+
+    /// the vertical field of view in degrees
+    pub fn with_vertical_fov(self, vertical_fov: T) -> Self {
+        Self { vertical_fov, ..self }
+    }
+
+    pub fn with_aspect_ratio(self, aspect_ratio: AspectRatio) -> Self {
+        Self { aspect_ratio, ..self }
+    }
+
+    pub fn bounds(&self) -> Aabb<T> {
+        self.bounds
+    }
+
+    /// the point every camera looks at and focuses on
+    pub fn target(&self) -> Point3<T> {
+        self.bounds.centroid()
+    }
+
+    /// the closest a camera looking from this direction can be with every corner of the bounds
+    /// in shot. each corner needs enough depth in front of the camera for its sideways and
+    /// vertical offsets to fit inside the padded field of view
+    pub fn distance(&self, azimuth: T, elevation: T) -> T {
+        let (right, up, back) = basis(azimuth, elevation);
+        let half_fov = self.vertical_fov * T::pi() / T::from_float(360.0);
+        let grow = T::one() + self.padding;
+        let tan_vertical = half_fov.tan() / grow;
+        let tan_horizontal = half_fov.tan() * T::from_float(self.aspect_ratio.as_f64()) / grow;
+        let target = self.target();
+        self.bounds
+            .corners()
+            .iter()
+            .map(|corner| {
+                let offset = corner - target;
+                let reach = (offset.dot(&right).abs() / tan_horizontal).max(offset.dot(&up).abs() / tan_vertical);
+                offset.dot(&back) + reach
+            })
+            .fold(T::zero(), |acc, distance| acc.max(distance))
+    }
+
+    /// where the camera looking from this direction sits, `distance` away from the target
+    fn position_at(&self, azimuth: T, elevation: T, distance: T) -> Point3<T> {
+        let (_, _, back) = basis(azimuth, elevation);
+        self.target() + back * distance
+    }
+
+    fn camera_at(&self, azimuth: T, elevation: T, distance: T) -> Result<Camera<T>, CameraError> {
+        Camera::look_at(self.position_at(azimuth, elevation, distance), self.target(), self.vertical_fov, self.aspect_ratio)
+    }
+
+    /// the closest camera looking from this direction that fits the whole subject in.
+    /// fails looking straight up or down, where +y can't be up
+    pub fn camera(&self, azimuth: T, elevation: T) -> Result<Camera<T>, CameraError> {
+        self.camera_at(azimuth, elevation, self.distance(azimuth, elevation))
+    }
+
+    /// azimuths spread evenly round a full turn, starting from 0
+    fn ring(count: usize) -> impl Iterator<Item = T> {
+        (0..count).map(move |i| T::from_float(360.0 * i as f64 / count as f64))
+    }
+
+    /// `count` cameras spaced evenly round the subject at the same elevation, all at the
+    /// distance the widest view needs so the subject stays the same size from one to the next
+    pub fn orbit(&self, count: usize, elevation: T) -> Result<Vec<Camera<T>>, CameraError> {
+        let distance = Self::ring(count).fold(T::zero(), |acc, azimuth| acc.max(self.distance(azimuth, elevation)));
+        Self::ring(count)
+            .map(|azimuth| self.camera_at(azimuth, elevation, distance))
+            .collect()
+    }
+
+    /// a camera path making one full turn round the subject over `duration`, starting from
+    /// an azimuth of 0, ready to be rendered with a [`FrameSequence`](crate::image::sequence::FrameSequence)
+    pub fn turntable(&self, elevation: T, duration: T) -> CameraPath<T> {
+        let distance = Self::ring(TURNTABLE_KEYS).fold(T::zero(), |acc, azimuth| acc.max(self.distance(azimuth, elevation)));
+        let positions = (0..=TURNTABLE_KEYS)
+            .map(|i| {
+                let turned = T::from_float(i as f64 / TURNTABLE_KEYS as f64);
+                (duration * turned, self.position_at(T::from_float(360.0) * turned, elevation, distance))
+            })
+            .collect();
+        CameraPath::new(Keyframes::new(positions), Keyframes::new(vec![(T::zero(), self.target())]))
+            .with_interpolation(Interpolation::CatmullRom)
+            .with_vertical_fov(self.vertical_fov)
+            .with_aspect_ratio(self.aspect_ratio)
+    }
+}
+
+/// the right, up and backwards vectors of a camera looking from this direction, matching
+/// what [`Camera::look_at`] works out for the same view
+fn basis<T: Scalar>(azimuth: T, elevation: T) -> (Vector3<T>, Vector3<T>, Vector3<T>) {
+    let radians = T::pi() / T::from_float(180.0);
+    let (sin_azimuth, cos_azimuth) = (azimuth * radians).sin_cos();
+    let (sin_elevation, cos_elevation) = (elevation * radians).sin_cos();
+    let back = Vector3::new(cos_elevation * sin_azimuth, sin_elevation, cos_elevation * cos_azimuth);
+    let right = Vector3::new(cos_azimuth, T::zero(), -sin_azimuth);
+    (right, back.cross(&right), back)
+}
+
+#[cfg(test)]
+mod test {
+    use nalgebra::point;
+
+    use super::*;
+    use crate::{Material, geometry::{Plane, Sphere, world::StaticWorld}};
+
+    /// how far out of the frame `point` is, as a fraction of the half width or height. at
+    /// most 1 for anything in shot
+    fn frame_position(camera: &Camera<f64>, point: Point3<f64>) -> f64 {
+        let offset = point - camera.origin();
+        let depth = offset.dot(&camera.orientation());
+        let half_height = (camera.projection().vertical_fov_at(1.0) / 2.0).tan();
+        let half_width = half_height * camera.aspect_ratio().as_f64();
+        (offset.dot(&camera.right()) / depth / half_width).abs().max((offset.dot(&camera.up()) / depth / half_height).abs())
+    }
+
+    #[test]
+    pub fn test_framed_cameras_fit_the_whole_scene() {
+        let mut world = StaticWorld::default();
+        world.push_plane(Plane::default());
+        world.push_sphere(Sphere::new(point![0.0, 0.5, 0.0], 0.5, Material::default()));
+        world.push_sphere(Sphere::new(point![3.0, 1.0, -2.0], 1.0, Material::default()));
+        // the ground plane is ignored rather than making the scene infinite
+        let bounds = world.bounds().unwrap();
+        assert_eq!(bounds, Aabb::new(point![-0.5, 0.0, -3.0], point![4.0, 2.0, 0.5]));
+
+        let framing = Framing::new(bounds).with_vertical_fov(40.0).with_padding(0.25);
+        let camera = framing.camera(30.0, 20.0).unwrap();
+        assert!((camera.orientation().into_inner() + basis(30.0, 20.0).2).norm() < 1e-12);
+        let fits: Vec<_> = bounds.corners().iter().map(|corner| frame_position(&camera, *corner)).collect();
+        // the padding leaves a quarter of the subject's size spare, and no more
+        assert!(fits.iter().all(|fit| *fit <= 1.0 / 1.25 + 1e-9));
+        assert!(fits.iter().any(|fit| (fit - 1.0 / 1.25).abs() < 1e-9));
+        assert!(framing.camera(0.0, 90.0).is_err());
+
+        let orbit = framing.orbit(8, 20.0).unwrap();
+        assert_eq!(orbit.len(), 8);
+        let distance = (orbit[0].origin() - framing.target()).norm();
+        for camera in &orbit {
+            assert!(((camera.origin() - framing.target()).norm() - distance).abs() < 1e-9);
+            assert!(bounds.corners().iter().all(|corner| frame_position(camera, *corner) <= 1.0));
+        }
+
+        let turntable = framing.turntable(20.0, 4.0);
+        assert_eq!((turntable.start_time(), turntable.end_time()), (0.0, 4.0));
+        assert!((turntable.position_at(0.0) - turntable.position_at(4.0)).norm() < 1e-9);
+        // a quarter of the way through it's turned a quarter of the way round
+        let quarter = turntable.position_at(1.0) - framing.target();
+        assert!((quarter.normalize() - basis(90.0, 20.0).2).norm() < 1e-9);
+    }
+}
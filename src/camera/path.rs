@@ -0,0 +1,170 @@
+use nalgebra::{Point3, Vector3};
+
+use crate::{
+    Scalar,
+    Camera,
+    camera::{CameraError, Projection},
+    geometry::{Interpolation, Keyframes},
+    image::AspectRatio,
+};
+
+/// an animated camera made of keyframed positions and the points it looks at,
+/// which don't need to share the same key times. every frame is a copy of a template
+/// camera moved along the path, so its projection, lens and exposure carry through
+#[derive(Debug, Clone)]
+pub struct CameraPath<T>
+where
+    T: Scalar
+{
+    positions: Keyframes<T, Point3<T>>,
+    targets: Keyframes<T, Point3<T>>,
+    interpolation: Interpolation,
+    up: Vector3<T>,
+    /// everything but the position, orientation, focus and shutter of each frame
+    camera: Camera<T>,
+}
+
+impl<T> CameraPath<T>
+where
+    T: Scalar
+{
+    /// a path moving linearly between the keys, with +y up and the [`Camera::default`] pinhole
+    /// with a 90 degree field of view
+    pub fn new(positions: Keyframes<T, Point3<T>>, targets: Keyframes<T, Point3<T>>) -> Self {
+        Self {
+            positions,
+            targets,
+            interpolation: Interpolation::Linear,
+            up: Vector3::y(),
+            camera: Camera::default(),
+        }
+    }
+
+    pub fn with_interpolation(self, interpolation: Interpolation) -> Self {
+        Self { interpolation, ..self }
+    }
+
+    pub fn with_up(self, up: Vector3<T>) -> Self {
+        Self { up, ..self }
+    }
+
+    /// the camera every frame is copied from. its position, orientation and focus are replaced
+    /// by the path's, and its shutter by the frame's, everything else is kept
+    pub fn with_camera(self, camera: Camera<T>) -> Self {
+        Self { camera, ..self }
+    }
+
+    /// switches the template camera to a perspective projection with this vertical field of
+    /// view in degrees
+    pub fn with_vertical_fov(self, vertical_fov: T) -> Self {
+        Self { camera: self.camera.with_projection(Projection::perspective(vertical_fov)), ..self }
+    }
+
+    pub fn with_aspect_ratio(self, aspect_ratio: AspectRatio) -> Self {
+        Self { camera: self.camera.with_aspect_ratio(aspect_ratio), ..self }
+    }
+
+    pub fn camera(&self) -> &Camera<T> {
+        &self.camera
+    }
+
+    pub fn positions(&self) -> &Keyframes<T, Point3<T>> {
+        &self.positions
+    }
+
+    pub fn targets(&self) -> &Keyframes<T, Point3<T>> {
+        &self.targets
+    }
+
+    /// the earliest key time of either the positions or the targets
+    pub fn start_time(&self) -> T {
+        self.positions.start_time().min(self.targets.start_time())
+    }
+
+    /// the latest key time of either the positions or the targets
+    pub fn end_time(&self) -> T {
+        self.positions.end_time().max(self.targets.end_time())
+    }
+
+    pub fn position_at(&self, time: T) -> Point3<T> {
+        self.positions.at_with(time, self.interpolation)
+    }
+
+    pub fn target_at(&self, time: T) -> Point3<T> {
+        self.targets.at_with(time, self.interpolation)
+    }
+
+    /// the camera at `time`, focused on the target with its shutter held at that moment so
+    /// nothing blurs. see [`CameraPath::camera_over`] for motion blur
+    pub fn camera_at(&self, time: T) -> Result<Camera<T>, CameraError> {
+        self.camera_over(time, T::zero())
+    }
+
+    /// the camera at `time` with its shutter open for `shutter_time`, centred on that moment,
+    /// so anything moving blurs over it
+    pub fn camera_over(&self, time: T, shutter_time: T) -> Result<Camera<T>, CameraError> {
+        let half = shutter_time.max(T::zero()) / T::TWO;
+        self.camera
+            .clone()
+            .with_look_at(self.position_at(time), self.target_at(time), self.up)
+            .map(|camera| camera.with_shutter(time - half, time + half))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use nalgebra::{point, vector};
+
+    use super::*;
+    use crate::camera::Exposure;
+
+    #[test]
+    pub fn test_path_cameras_follow_their_keys() {
+        let positions = Keyframes::new(vec![
+            (0.0, point![4.0, 1.0, 0.0]),
+            (1.0, point![0.0, 1.0, 4.0]),
+            (2.0, point![-4.0, 1.0, 0.0]),
+        ]);
+        let path = CameraPath::new(positions, Keyframes::new(vec![(0.0, Point3::<f64>::origin())]))
+            .with_interpolation(Interpolation::CatmullRom);
+        assert_eq!((path.start_time(), path.end_time()), (0.0, 2.0));
+
+        let camera = path.camera_at(1.0).unwrap();
+        assert_eq!(camera.origin(), point![0.0, 1.0, 4.0]);
+        assert_eq!(camera.shutter(), (1.0, 1.0));
+        assert_eq!(path.camera_over(1.0, 0.5).unwrap().shutter(), (0.75, 1.25));
+        let centre = camera.ray(0.5, 0.5).unwrap();
+        assert!((centre.project(camera.focus_distance()) - Point3::origin()).norm() < 1e-12);
+
+        // the spline swings wider than the straight line between the keys
+        let halfway = path.position_at(0.5);
+        assert!(halfway.coords.norm() > point![2.0, 1.0, 2.0].coords.norm());
+    }
+
+    #[test]
+    pub fn test_path_cameras_keep_the_template_settings() {
+        let positions = Keyframes::linear(point![0.0, 0.0, 4.0], point![4.0, 0.0, 0.0]);
+        let template = Camera::<f64>::default()
+            .with_projection(Projection::orthographic(3.0))
+            .with_aspect_ratio(AspectRatio::new(2, 1))
+            .with_aperture(0.05)
+            .with_exposure(Exposure::from_ev100(10.0));
+        let path = CameraPath::new(positions, Keyframes::new(vec![(0.0, Point3::origin())]))
+            .with_camera(template.clone());
+
+        let camera = path.camera_over(1.0, 0.5).unwrap();
+        assert_eq!(camera.projection(), template.projection());
+        assert_eq!(camera.aspect_ratio(), template.aspect_ratio());
+        assert_eq!(camera.aperture(), 0.05);
+        assert_eq!(camera.exposure_scale(), template.exposure_scale());
+        assert_eq!(camera.origin(), point![4.0, 0.0, 0.0]);
+        assert!((camera.orientation().into_inner() - vector![-1.0, 0.0, 0.0]).norm() < 1e-12);
+        assert!((camera.focus_distance() - 4.0).abs() < 1e-12);
+        assert_eq!(camera.shutter(), (0.75, 1.25));
+
+        // the field of view shortcut only changes the projection
+        let wide = path.with_vertical_fov(120.0).camera_at(0.0).unwrap();
+        assert_eq!(wide.projection(), Projection::perspective(120.0));
+        assert_eq!(wide.aperture(), 0.05);
+    }
+}
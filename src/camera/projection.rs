@@ -0,0 +1,156 @@
+use nalgebra::{vector, Vector3};
+
+use crate::{Scalar, fingerprint::{Fingerprint, Fingerprinter}};
+
+/// how a fisheye lens spreads angles from the view direction across its image circle
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FisheyeMapping {
+    /// distance from the centre is proportional to the angle, so angles are even across the image
+    Equidistant,
+    /// equal areas of the image cover equal solid angles, like most real fisheye lenses
+    Equisolid,
+}
+
+/// how points on the image are mapped to rays leaving the camera
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Projection<T>
+where
+    T: Scalar
+{
+    /// rays fan out from the lens, `vertical_fov` is the full vertical angle in radians
+    Perspective { vertical_fov: T },
+    /// rays all leave parallel to the view direction, `height` is the vertical extent
+    /// of the view in scene units
+    Orthographic { height: T },
+    /// latitude and longitude covering every direction, the centre of the image looks
+    /// straight ahead and the edges straight behind. best rendered at 2:1
+    Equirectangular,
+    /// a circular image inscribed in the height of the frame covering `fov` radians across,
+    /// anything outside the circle sees nothing
+    Fisheye { fov: T, mapping: FisheyeMapping },
+}
+
+impl<T> Projection<T>
+where
+    T: Scalar
+{
+    /// a perspective projection with the vertical field of view given in degrees
+    pub fn perspective(vertical_fov: T) -> Self {
+        Projection::Perspective { vertical_fov: vertical_fov * T::pi() / T::from_float(180.0) }
+    }
+
+    pub fn orthographic(height: T) -> Self {
+        Projection::Orthographic { height }
+    }
+
+    /// a fisheye with the field of view across its image circle given in degrees
+    pub fn fisheye(fov: T, mapping: FisheyeMapping) -> Self {
+        Projection::Fisheye { fov: fov * T::pi() / T::from_float(180.0), mapping }
+    }
+
+    /// whether rays pass through a flat viewport, panoramic projections can't be focused
+    /// on a plane and are always rendered as a pinhole
+    pub fn is_planar(&self) -> bool {
+        matches!(self, Projection::Perspective { .. } | Projection::Orthographic { .. })
+    }
+
+    /// height of the image when projected onto the plane `distance` in front of the camera,
+    /// None for panoramic projections
+    pub fn height_at(&self, distance: T) -> Option<T> {
+        match self {
+            Projection::Perspective { vertical_fov } => Some(T::TWO * (*vertical_fov / T::TWO).tan() * distance),
+            Projection::Orthographic { height } => Some(*height),
+            Projection::Equirectangular | Projection::Fisheye { .. } => None,
+        }
+    }
+
+    /// the vertical angle the image covers when seen from `distance` away, for an orthographic
+    /// view this is the perspective lens that would frame the plane at that distance the same way
+    pub fn vertical_fov_at(&self, distance: T) -> T {
+        match self {
+            Projection::Perspective { vertical_fov } => *vertical_fov,
+            Projection::Orthographic { height } => T::TWO * (*height / (T::TWO * distance)).atan(),
+            Projection::Equirectangular => T::pi(),
+            Projection::Fisheye { fov, .. } => *fov,
+        }
+    }
+
+    /// direction of the ray through image coordinates (u, v) for a panoramic projection, in
+    /// camera space where +x is right, +y is up and the camera looks down -z. None outside
+    /// the image circle of a fisheye or for planar projections
+    pub fn panoramic_direction(&self, u: T, v: T, aspect: T) -> Option<Vector3<T>> {
+        match self {
+            Projection::Equirectangular => {
+                let longitude = (u - T::HALF) * T::two_pi();
+                let latitude = (v - T::HALF) * T::pi();
+                let (sin_lon, cos_lon) = longitude.sin_cos();
+                let (sin_lat, cos_lat) = latitude.sin_cos();
+                Some(vector![cos_lat * sin_lon, sin_lat, -cos_lat * cos_lon])
+            },
+            Projection::Fisheye { fov, mapping } => {
+                let (x, y) = ((u - T::HALF) * T::TWO * aspect, (v - T::HALF) * T::TWO);
+                let radius = (x * x + y * y).sqrt();
+                if radius > T::one() {
+                    return None;
+                }
+                let half_fov = *fov / T::TWO;
+                let theta = match mapping {
+                    FisheyeMapping::Equidistant => radius * half_fov,
+                    FisheyeMapping::Equisolid => T::TWO * (radius * (half_fov / T::TWO).sin()).asin(),
+                };
+                let phi = y.atan2(x);
+                let (sin_theta, cos_theta) = theta.sin_cos();
+                Some(vector![sin_theta * phi.cos(), sin_theta * phi.sin(), -cos_theta])
+            },
+            Projection::Perspective { .. } | Projection::Orthographic { .. } => None,
+        }
+    }
+}
+
+impl<T> Fingerprint for Projection<T>
+where
+    T: Scalar
+{
+    fn fingerprint(&self, state: &mut Fingerprinter) {
+        match self {
+            Projection::Perspective { vertical_fov } => {
+                state.usize(0);
+                state.scalar(*vertical_fov);
+            },
+            Projection::Orthographic { height } => {
+                state.usize(1);
+                state.scalar(*height);
+            },
+            Projection::Equirectangular => state.usize(2),
+            Projection::Fisheye { fov, mapping } => {
+                state.usize(3);
+                state.scalar(*fov);
+                state.usize(*mapping as usize);
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    pub fn test_panoramas_look_ahead_from_the_centre() {
+        let forward = vector![0.0, 0.0, -1.0];
+        let equirect = Projection::<f64>::Equirectangular;
+        assert!((equirect.panoramic_direction(0.5, 0.5, 2.0).unwrap() - forward).norm() < 1e-12);
+        // the left and right edges meet behind the camera, the top row is straight up
+        assert!((equirect.panoramic_direction(0.0, 0.5, 2.0).unwrap() - vector![0.0, 0.0, 1.0]).norm() < 1e-12);
+        assert!((equirect.panoramic_direction(0.3, 1.0, 2.0).unwrap() - vector![0.0, 1.0, 0.0]).norm() < 1e-12);
+
+        for mapping in [FisheyeMapping::Equidistant, FisheyeMapping::Equisolid] {
+            let fisheye = Projection::fisheye(180.0, mapping);
+            assert!((fisheye.panoramic_direction(0.5, 0.5, 1.0).unwrap() - forward).norm() < 1e-12);
+            // the edge of the image circle is 90 degrees off axis
+            let edge = fisheye.panoramic_direction(1.0, 0.5, 1.0).unwrap();
+            assert!((edge - vector![1.0, 0.0, 0.0]).norm() < 1e-12);
+            assert!(fisheye.panoramic_direction(1.0, 1.0, 1.0).is_none());
+        }
+    }
+}
@@ -0,0 +1,123 @@
+use crate::{Scalar, Camera, fingerprint::{Fingerprint, Fingerprinter}};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Eye {
+    Left,
+    Right,
+}
+
+/// where one eye of a stereo pair sits relative to the rig's camera
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct StereoEye<T>
+where
+    T: Scalar
+{
+    /// distance along the camera's right vector, negative for the left eye
+    pub offset: T,
+    /// distance at which the two eyes' images line up, infinite for parallel eyes
+    pub convergence: T,
+}
+
+/// a pair of cameras either side of a centre camera. perspective eyes use off-axis frustums
+/// which meet on the plane `convergence` in front of the rig, equirectangular eyes use
+/// omni-directional stereo so that every direction gets the right parallax
+#[derive(Debug, Clone)]
+pub struct StereoRig<T>
+where
+    T: Scalar
+{
+    camera: Camera<T>,
+    interocular: T,
+    convergence: T,
+}
+
+impl<T> StereoRig<T>
+where
+    T: Scalar
+{
+    /// `interocular` is the distance between the eyes, usually around 0.064 for a person in metres.
+    /// a `convergence` of `T::INF` keeps the eyes parallel
+    pub fn new(camera: Camera<T>, interocular: T, convergence: T) -> Self {
+        Self { camera, interocular, convergence }
+    }
+
+    pub fn with_interocular(self, interocular: T) -> Self {
+        Self { interocular, ..self }
+    }
+
+    pub fn with_convergence(self, convergence: T) -> Self {
+        Self { convergence, ..self }
+    }
+
+    pub fn camera(&self) -> &Camera<T> {
+        &self.camera
+    }
+
+    pub fn interocular(&self) -> T {
+        self.interocular
+    }
+
+    pub fn convergence(&self) -> T {
+        self.convergence
+    }
+
+    /// the camera for one eye, which can be rendered like any other
+    pub fn eye(&self, eye: Eye) -> Camera<T> {
+        let half = self.interocular / T::TWO;
+        let offset = match eye {
+            Eye::Left => -half,
+            Eye::Right => half,
+        };
+        self.camera.clone().with_stereo_eye(StereoEye { offset, convergence: self.convergence })
+    }
+}
+
+impl<T> Fingerprint for StereoEye<T>
+where
+    T: Scalar
+{
+    fn fingerprint(&self, state: &mut Fingerprinter) {
+        state.scalar(self.offset);
+        state.scalar(self.convergence);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use nalgebra::point;
+
+    use super::*;
+    use crate::{camera::Projection, image::AspectRatio};
+
+    #[test]
+    pub fn test_eyes_converge_on_the_convergence_plane() {
+        let camera = Camera::<f64>::look_at(point![0.0, 0.0, 0.0], point![0.0, 0.0, -2.0], 60.0, AspectRatio::default()).unwrap();
+        let rig = StereoRig::new(camera, 0.064, 3.0);
+        let (left, right) = (rig.eye(Eye::Left), rig.eye(Eye::Right));
+        for (u, v) in [(0.5, 0.5), (0.1, 0.8), (0.9, 0.2)] {
+            let (l, r) = (left.ray(u, v).unwrap(), right.ray(u, v).unwrap());
+            assert!((l.origin() - point![-0.032, 0.0, 0.0]).norm() < 1e-12);
+            assert!((r.origin() - point![0.032, 0.0, 0.0]).norm() < 1e-12);
+            // both eyes see the same point at the convergence distance
+            let depth = |ray: &crate::geometry::Ray<f64>| 3.0 / -ray.orientation().z;
+            assert!((l.project(depth(&l)) - r.project(depth(&r))).norm() < 1e-9);
+        }
+    }
+
+    #[test]
+    pub fn test_omni_directional_eyes_circle_the_centre() {
+        let camera = Camera::<f64>::default()
+            .with_projection(Projection::Equirectangular)
+            .with_aspect_ratio(AspectRatio::new(2, 1));
+        let rig = StereoRig::new(camera, 0.064, f64::INFINITY);
+        let left = rig.eye(Eye::Left);
+        for u in [0.1, 0.3, 0.5, 0.8] {
+            let ray = left.ray(u, 0.5).unwrap();
+            // every eye position is on the circle and at right angles to the ray
+            assert!((ray.origin().coords.norm() - 0.032).abs() < 1e-12);
+            assert!(ray.origin().coords.dot(&ray.orientation()).abs() < 1e-12);
+        }
+        // looking straight up the eyes come together
+        assert!(left.ray(0.3, 1.0).unwrap().origin().coords.norm() < 1e-12);
+    }
+}
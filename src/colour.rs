@@ -65,3 +65,8 @@ pub fn light_green<T: Scalar>() -> Vector3<T> {
 pub fn light_blue<T: Scalar>() -> Vector3<T> {
     vector![T::from_float(0.5), T::from_float(0.7), T::from_float(1.0)]
 }
+
+/// the perceived brightness of a linear rgb colour, using the rec. 709 weights
+pub fn luminance<T: Scalar>(colour: &Vector3<T>) -> T {
+    colour.dot(&vector![T::from_float(0.2126), T::from_float(0.7152), T::from_float(0.0722)])
+}
@@ -0,0 +1,173 @@
+//! stable hashes of scenes and cameras, used to check a checkpoint is resumed with what it
+//! was made with. every type feeds its fields in explicitly rather than going through its
+//! debug output, so the hash stays the same between builds and compiler versions
+
+use std::sync::Arc;
+
+use nalgebra::{Point2, Point3, Similarity3, Unit, Vector3};
+
+use crate::Scalar;
+
+/// something that can feed everything that affects how it renders into a [`Fingerprinter`]
+pub trait Fingerprint {
+    fn fingerprint(&self, state: &mut Fingerprinter);
+}
+
+/// FNV-1a over whatever is fed in, it must stay the same between builds so nothing from
+/// std's randomly seeded hashers can be used
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Fingerprinter {
+    hash: u64,
+}
+
+impl Default for Fingerprinter {
+    fn default() -> Self {
+        Self { hash: 0xcbf29ce484222325 }
+    }
+}
+
+impl Fingerprinter {
+    pub fn bytes(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.hash = (self.hash ^ *byte as u64).wrapping_mul(0x100000001b3);
+        }
+    }
+
+    pub fn u64(&mut self, value: u64) {
+        self.bytes(&value.to_le_bytes());
+    }
+
+    pub fn usize(&mut self, value: usize) {
+        self.u64(value as u64);
+    }
+
+    pub fn bool(&mut self, value: bool) {
+        self.u64(value as u64);
+    }
+
+    /// scalars are widened to f64, [`Fingerprinter::precision`] tells f32 and f64 apart
+    pub fn scalar<T: Scalar>(&mut self, value: T) {
+        self.bytes(&value.to_float().to_le_bytes());
+    }
+
+    /// the size of the scalar type, so the same scene in different precisions differs
+    pub fn precision<T: Scalar>(&mut self) {
+        self.usize(std::mem::size_of::<T>());
+    }
+
+    pub fn add<F: Fingerprint + ?Sized>(&mut self, value: &F) -> &mut Self {
+        value.fingerprint(self);
+        self
+    }
+
+    pub fn finish(&self) -> u64 {
+        self.hash
+    }
+}
+
+impl<T: Scalar> Fingerprint for Point2<T> {
+    fn fingerprint(&self, state: &mut Fingerprinter) {
+        self.iter().for_each(|c| state.scalar(*c));
+    }
+}
+
+impl<T: Scalar> Fingerprint for Point3<T> {
+    fn fingerprint(&self, state: &mut Fingerprinter) {
+        self.iter().for_each(|c| state.scalar(*c));
+    }
+}
+
+impl<T: Scalar> Fingerprint for Vector3<T> {
+    fn fingerprint(&self, state: &mut Fingerprinter) {
+        self.iter().for_each(|c| state.scalar(*c));
+    }
+}
+
+impl<T: Scalar> Fingerprint for Unit<Vector3<T>> {
+    fn fingerprint(&self, state: &mut Fingerprinter) {
+        self.as_ref().fingerprint(state);
+    }
+}
+
+impl<T: Scalar> Fingerprint for Similarity3<T> {
+    fn fingerprint(&self, state: &mut Fingerprinter) {
+        self.to_homogeneous().iter().for_each(|c| state.scalar(*c));
+    }
+}
+
+impl<F: Fingerprint> Fingerprint for [F] {
+    fn fingerprint(&self, state: &mut Fingerprinter) {
+        state.usize(self.len());
+        self.iter().for_each(|item| item.fingerprint(state));
+    }
+}
+
+impl<F: Fingerprint, const N: usize> Fingerprint for [F; N] {
+    fn fingerprint(&self, state: &mut Fingerprinter) {
+        self.as_slice().fingerprint(state);
+    }
+}
+
+impl<F: Fingerprint> Fingerprint for Vec<F> {
+    fn fingerprint(&self, state: &mut Fingerprinter) {
+        self.as_slice().fingerprint(state);
+    }
+}
+
+impl<F: Fingerprint> Fingerprint for Option<F> {
+    fn fingerprint(&self, state: &mut Fingerprinter) {
+        match self {
+            Some(value) => state.add(&1_usize).add(value),
+            None => state.add(&0_usize),
+        };
+    }
+}
+
+impl Fingerprint for usize {
+    fn fingerprint(&self, state: &mut Fingerprinter) {
+        state.usize(*self);
+    }
+}
+
+impl<F: Fingerprint + ?Sized> Fingerprint for Box<F> {
+    fn fingerprint(&self, state: &mut Fingerprinter) {
+        (**self).fingerprint(state);
+    }
+}
+
+impl<F: Fingerprint + ?Sized> Fingerprint for Arc<F> {
+    fn fingerprint(&self, state: &mut Fingerprinter) {
+        (**self).fingerprint(state);
+    }
+}
+
+impl<F: Fingerprint + ?Sized> Fingerprint for &F {
+    fn fingerprint(&self, state: &mut Fingerprinter) {
+        (**self).fingerprint(state);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use nalgebra::point;
+
+    use super::*;
+
+    #[test]
+    pub fn test_fingerprints_follow_values_and_precision() {
+        let hash = |points: &[Point3<f64>]| Fingerprinter::default().add(points).finish();
+        assert_eq!(hash(&[point![1.0, 2.0, 3.0]]), hash(&[point![1.0, 2.0, 3.0]]));
+        assert_ne!(hash(&[point![1.0, 2.0, 3.0]]), hash(&[point![1.0, 2.0, 3.5]]));
+        // lengths are included, so moving values between neighbours changes the hash
+        assert_ne!(Fingerprinter::default().add(&vec![1_usize, 2]).add(&vec![3_usize]).finish(),
+            Fingerprinter::default().add(&vec![1_usize]).add(&vec![2_usize, 3]).finish());
+
+        let mut double = Fingerprinter::default();
+        double.precision::<f64>();
+        double.scalar(0.5_f64);
+        let mut single = Fingerprinter::default();
+        single.precision::<f32>();
+        single.scalar(0.5_f32);
+        assert_ne!(double.finish(), single.finish());
+    }
+}
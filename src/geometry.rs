@@ -5,22 +5,180 @@ pub mod plane;
 pub mod world;
 pub mod aabb;
 pub mod bvh;
+pub mod instance;
+pub mod frame;
+pub mod surface;
+pub mod quad;
+pub mod disk;
+pub mod cuboid;
+pub mod roots;
+pub mod cylinder;
+pub mod cone;
+pub mod torus;
+pub mod csg;
+pub mod sdf;
+pub mod motion;
 
 pub use ray::Ray;
 pub use intersect::{Intersectable, Intersection};
 pub use sphere::Sphere;
 pub use plane::Plane;
 pub use aabb::Aabb;
+pub use bvh::{Bvh, Blas, Tlas};
+pub use instance::Instance;
+pub use frame::Frame;
+pub use surface::{Surface, SurfaceSample};
+pub use quad::Quad;
+pub use disk::Disk;
+pub use cuboid::Cuboid;
+pub use cylinder::Cylinder;
+pub use cone::Cone;
+pub use torus::Torus;
+pub use csg::{Csg, CsgOperation, Solid, Span, Boundary};
+pub use sdf::{Sdf, SdfShape};
+pub use motion::{Interpolate, Interpolation, Keyframes};
 
-use crate::Scalar;
+use std::fmt;
 
-// use core::fmt::Debug;
+use crate::{Scalar, fingerprint::{Fingerprint, Fingerprinter}};
 
-// #[derive(Clone, Debug)]
+/// a shape built with sizes that leave nothing to hit, or nothing to work out a normal from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ShapeError {
+    /// the edges of a quad are parallel or zero, so it has no area
+    FlatQuad,
+    /// the corners of a cuboid share a coordinate, so some of its faces have no area
+    FlatCuboid,
+    /// a radius that's zero, negative or not finite
+    BadRadius,
+    /// a height that's zero, negative or not finite
+    BadHeight,
+}
+
+impl fmt::Display for ShapeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ShapeError::FlatQuad => write!(f, "quad edges don't span an area"),
+            ShapeError::FlatCuboid => write!(f, "cuboid corners share a coordinate, leaving it flat"),
+            ShapeError::BadRadius => write!(f, "radius must be positive and finite"),
+            ShapeError::BadHeight => write!(f, "height must be positive and finite"),
+        }
+    }
+}
+
+impl std::error::Error for ShapeError {}
+
+#[derive(Clone, Debug)]
 pub enum Geometry<T>
 where
     T: Scalar
 {
     Sphere(Sphere<T>),
     Plane(Plane<T>),
-}
\ No newline at end of file
+    Instance(Instance<T>),
+    Quad(Quad<T>),
+    Disk(Disk<T>),
+    Cuboid(Cuboid<T>),
+    Cylinder(Cylinder<T>),
+    Cone(Cone<T>),
+    Torus(Torus<T>),
+    Csg(Csg<T>),
+    Sdf(Sdf<T>),
+}
+
+impl<T> Intersectable<T> for Geometry<T>
+where
+    T: Scalar
+{
+    fn intersect(&self, ray: Ray<T>, min_depth: T, max_depth: T) -> Option<Intersection<T>> {
+        match self {
+            Geometry::Sphere(s) => s.intersect(ray, min_depth, max_depth),
+            Geometry::Plane(p) => p.intersect(ray, min_depth, max_depth),
+            Geometry::Instance(i) => i.intersect(ray, min_depth, max_depth),
+            Geometry::Quad(q) => q.intersect(ray, min_depth, max_depth),
+            Geometry::Disk(d) => d.intersect(ray, min_depth, max_depth),
+            Geometry::Cuboid(c) => c.intersect(ray, min_depth, max_depth),
+            Geometry::Cylinder(c) => c.intersect(ray, min_depth, max_depth),
+            Geometry::Cone(c) => c.intersect(ray, min_depth, max_depth),
+            Geometry::Torus(t) => t.intersect(ray, min_depth, max_depth),
+            Geometry::Csg(c) => c.intersect(ray, min_depth, max_depth),
+            Geometry::Sdf(s) => s.intersect(ray, min_depth, max_depth),
+        }
+    }
+
+    fn bounding_box(&self) -> Option<Aabb<T>> {
+        match self {
+            Geometry::Sphere(s) => s.bounding_box(),
+            Geometry::Plane(p) => p.bounding_box(),
+            Geometry::Instance(i) => i.bounding_box(),
+            Geometry::Quad(q) => q.bounding_box(),
+            Geometry::Disk(d) => d.bounding_box(),
+            Geometry::Cuboid(c) => c.bounding_box(),
+            Geometry::Cylinder(c) => c.bounding_box(),
+            Geometry::Cone(c) => c.bounding_box(),
+            Geometry::Torus(t) => t.bounding_box(),
+            Geometry::Csg(c) => c.bounding_box(),
+            Geometry::Sdf(s) => s.bounding_box(),
+        }
+    }
+}
+
+impl<T> Geometry<T>
+where
+    T: Scalar
+{
+    /// whether this has an inside that can take part in a [`Csg`]. open surfaces don't, and
+    /// distance fields are left out as marching for every crossing would be far too slow
+    pub fn is_solid(&self) -> bool {
+        match self {
+            Geometry::Sphere(_) | Geometry::Plane(_) | Geometry::Cuboid(_) | Geometry::Torus(_) | Geometry::Csg(_) => true,
+            Geometry::Cylinder(c) => c.is_closed(),
+            Geometry::Cone(c) => c.is_closed(),
+            Geometry::Instance(i) => i.blas().primitives().all(Geometry::is_solid),
+            Geometry::Quad(_) | Geometry::Disk(_) | Geometry::Sdf(_) => false,
+        }
+    }
+}
+
+/// anything that isn't [`Geometry::is_solid`] contributes nothing when used in a [`Csg`]
+impl<T> Solid<T> for Geometry<T>
+where
+    T: Scalar
+{
+    fn spans(&self, ray: Ray<T>) -> Vec<Span<T>> {
+        match self {
+            Geometry::Sphere(s) => s.spans(ray),
+            Geometry::Plane(p) => p.spans(ray),
+            Geometry::Cuboid(c) => c.spans(ray),
+            Geometry::Cylinder(c) => c.spans(ray),
+            Geometry::Cone(c) => c.spans(ray),
+            Geometry::Torus(t) => t.spans(ray),
+            Geometry::Csg(c) => c.spans(ray),
+            Geometry::Instance(i) => i.spans(ray),
+            Geometry::Quad(_) | Geometry::Disk(_) | Geometry::Sdf(_) => Vec::new(),
+        }
+    }
+}
+
+impl<T> Fingerprint for Geometry<T>
+where
+    T: Scalar
+{
+    fn fingerprint(&self, state: &mut Fingerprinter) {
+        let (variant, shape): (usize, &dyn Fingerprint) = match self {
+            Geometry::Sphere(s) => (0, s),
+            Geometry::Plane(p) => (1, p),
+            Geometry::Instance(i) => (2, i),
+            Geometry::Quad(q) => (3, q),
+            Geometry::Disk(d) => (4, d),
+            Geometry::Cuboid(c) => (5, c),
+            Geometry::Cylinder(c) => (6, c),
+            Geometry::Cone(c) => (7, c),
+            Geometry::Torus(t) => (8, t),
+            Geometry::Csg(c) => (9, c),
+            Geometry::Sdf(s) => (10, s),
+        };
+        state.usize(variant);
+        shape.fingerprint(state);
+    }
+}
@@ -1,8 +1,9 @@
-use nalgebra::{point, vector, Point3, Vector3, Matrix};
+use nalgebra::{point, Point3, Vector3, Matrix, Similarity3};
 
 use crate::{
-    geometry::{Intersectable, Intersection, Ray},
+    geometry::Ray,
     Scalar,
+    fingerprint::{Fingerprint, Fingerprinter},
 };
 
 // not sure what works best yet
@@ -28,7 +29,11 @@ use crate::{
 
 // }
 
+/// flat primitives get a tiny thickness so their boxes are never degenerate
+pub(crate) const FLAT_MARGIN: f64 = 1e-4;
+
 /// this two vector approach works well with nalgebra
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub struct Aabb<T>
 where
     T: Scalar
@@ -41,13 +46,119 @@ impl<T> Aabb<T>
 where
     T: Scalar
 {
-    pub fn intersect(&self, ray: Ray<T>, min_depth: T) -> bool {
+    /// creates a box from two opposite corners, in any order
+    pub fn new(a: Point3<T>, b: Point3<T>) -> Self {
+        let (min, max) = a.coords.inf_sup(&b.coords);
+        Self { min: min.into(), max: max.into() }
+    }
+
+    /// the box around a circle of `radius` facing along `normal`, the circle's extent
+    /// along each axis shrinks as the normal turns towards that axis
+    pub fn around_circle(center: Point3<T>, normal: &Vector3<T>, radius: T) -> Self {
+        let extent = normal.map(|c| radius * (T::one() - c * c).max(T::zero()).sqrt());
+        Self { min: center - extent, max: center + extent }
+    }
+
+    /// the smallest box containing every point, or None for an empty iterator
+    pub fn from_points<I: IntoIterator<Item = Point3<T>>>(points: I) -> Option<Self> {
+        points
+            .into_iter()
+            .map(|p| Self { min: p, max: p })
+            .reduce(|acc, next| acc.union(&next))
+    }
+
+    pub fn min(&self) -> Point3<T> {
+        self.min
+    }
+
+    pub fn max(&self) -> Point3<T> {
+        self.max
+    }
+
+    pub fn extent(&self) -> Vector3<T> {
+        self.max - self.min
+    }
+
+    pub fn centroid(&self) -> Point3<T> {
+        nalgebra::center(&self.min, &self.max)
+    }
+
+    pub fn surface_area(&self) -> T {
+        let e = self.extent();
+        (e.x * e.y + e.y * e.z + e.z * e.x) * T::TWO
+    }
+
+    /// index of the axis along which the box is widest
+    pub fn longest_axis(&self) -> usize {
+        self.extent().imax()
+    }
+
+    pub fn union(&self, other: &Self) -> Self {
+        Self {
+            min: self.min.coords.inf(&other.min.coords).into(),
+            max: self.max.coords.sup(&other.max.coords).into(),
+        }
+    }
+
+    /// the region inside both boxes, if they touch at all
+    pub fn overlap(&self, other: &Self) -> Option<Self> {
+        let min = self.min.coords.sup(&other.min.coords);
+        let max = self.max.coords.inf(&other.max.coords);
+        if min.iter().zip(max.iter()).all(|(lo, hi)| lo <= hi) {
+            Some(Self { min: min.into(), max: max.into() })
+        } else {
+            None
+        }
+    }
+
+    /// grows the box by `margin` in every direction, useful for flat primitives
+    pub fn inflate(&self, margin: T) -> Self {
+        let margin = Vector3::repeat(margin);
+        Self { min: self.min - margin, max: self.max + margin }
+    }
+
+    /// the eight corners of the box
+    pub fn corners(&self) -> [Point3<T>; 8] {
+        let (lo, hi) = (self.min, self.max);
+        [
+            point![lo.x, lo.y, lo.z], point![hi.x, lo.y, lo.z],
+            point![lo.x, hi.y, lo.z], point![hi.x, hi.y, lo.z],
+            point![lo.x, lo.y, hi.z], point![hi.x, lo.y, hi.z],
+            point![lo.x, hi.y, hi.z], point![hi.x, hi.y, hi.z],
+        ]
+    }
+
+    /// the box enclosing this box after it has been moved by `transform`
+    pub fn transform(&self, transform: &Similarity3<T>) -> Self {
+        Self::from_points(self.corners().iter().map(|c| transform.transform_point(c)))
+            .expect("a box always has corners")
+    }
+
+    pub fn intersect(&self, ray: Ray<T>, min_depth: T, max_depth: T) -> bool {
+        self.clip(ray, min_depth, max_depth).is_some()
+    }
+
+    /// the range of depths along `ray` spent inside the box, limited to [min_depth, max_depth]
+    pub fn clip(&self, ray: Ray<T>, min_depth: T, max_depth: T) -> Option<(T, T)> {
         let t0 = (self.min - ray.origin()).component_div(&ray.orientation());
         let t1 = (self.max - ray.origin()).component_div(&ray.orientation());
         let (t_min, t_max) = t0.inf_sup(&t1);
         // maybe need to check for nans, not sure how inf_sup handles it.
         let (t_low, t_hi) = (Matrix::max(&t_min), Matrix::min(&t_max));
         // NaN indicates the grazing case, as long as we slightly inflate the bounding box then false here is correct
-        t_hi >= t_low && t_hi >= min_depth
+        if t_hi >= t_low && t_hi >= min_depth && t_low <= max_depth {
+            Some((t_low.max(min_depth), t_hi.min(max_depth)))
+        } else {
+            None
+        }
     }
-}
\ No newline at end of file
+}
+
+impl<T> Fingerprint for Aabb<T>
+where
+    T: Scalar
+{
+    fn fingerprint(&self, state: &mut Fingerprinter) {
+        state.add(&self.min).add(&self.max);
+    }
+}
@@ -1,16 +1,357 @@
-// use nalgebra::{Point3, Vector3, point, vector};
+use nalgebra::Point3;
 
-use crate::{geometry::{Aabb, Geometry}, Scalar};
+use crate::{
+    geometry::{Aabb, Geometry, Instance, Intersectable, Intersection, Ray},
+    Scalar,
+    fingerprint::{Fingerprint, Fingerprinter},
+};
 
-struct BvhBox<T>(Aabb<T>, Geometry<T>) where T: Scalar;
+/// number of buckets used when searching for a surface area heuristic split
+const SAH_BINS: usize = 12;
+/// leaves at or below this size are never split
+const MIN_LEAF_SIZE: usize = 2;
+/// leaves above this size are always split, even if the heuristic says it's not worth it
+const MAX_LEAF_SIZE: usize = 8;
 
-struct Bvh<T>
+/// a bottom level structure over the actual geometry of a single object
+pub type Blas<T> = Bvh<T, Geometry<T>>;
+
+/// a top level structure over instances which each point into a shared [`Blas`]
+pub type Tlas<T> = Bvh<T, Instance<T>>;
+
+#[derive(Debug, Clone)]
+enum NodeKind {
+    /// a range of primitives in `Bvh::boxed`
+    Leaf { first: usize, count: usize },
+    /// the left child always directly follows its parent, so only the right needs storing
+    Interior { right: usize, axis: usize },
+}
+
+#[derive(Debug, Clone)]
+struct BvhNode<T>
+where
+    T: Scalar
+{
+    bounds: Aabb<T>,
+    kind: NodeKind,
+}
+
+/// a primitive's bounds alongside its index in the input, used only while building
+struct BuildRef<T>
 where
     T: Scalar
 {
-    boxes: Vec<(Aabb<T>, Geometry<T>)>,
-    tree: Vec<usize>,
+    bounds: Aabb<T>,
+    centroid: Point3<T>,
+    index: usize,
+}
+
+/// a flattened bounding volume hierarchy over any intersectable primitive,
+/// built with a binned surface area heuristic
+#[derive(Debug, Clone)]
+pub struct Bvh<T, P>
+where
+    T: Scalar
+{
+    nodes: Vec<BvhNode<T>>,
+    boxed: Vec<P>,
     // infinite sized objects have to be tracked outside the bb
-    unboxed: Vec<usize>,
+    unboxed: Vec<P>,
+}
+
+impl<T, P> Bvh<T, P>
+where
+    T: Scalar,
+    P: Intersectable<T>,
+{
+    pub fn new(primitives: Vec<P>) -> Self {
+        let mut refs = Vec::new();
+        let mut slots = Vec::new();
+        let mut unboxed = Vec::new();
+        for primitive in primitives {
+            match primitive.bounding_box() {
+                Some(bounds) => {
+                    refs.push(BuildRef { bounds, centroid: bounds.centroid(), index: slots.len() });
+                    slots.push(Some(primitive));
+                },
+                None => unboxed.push(primitive),
+            }
+        }
+
+        let mut nodes = Vec::new();
+        if !refs.is_empty() {
+            build(&mut nodes, &mut refs, 0);
+        }
+
+        // leaves index into the build order, so reorder the primitives to match
+        let boxed = refs
+            .iter()
+            .map(|r| slots[r.index].take().expect("each primitive is referenced exactly once"))
+            .collect();
+
+        Self { nodes, boxed, unboxed }
+    }
+
+    /// number of primitives held by the hierarchy, bounded or otherwise
+    pub fn len(&self) -> usize {
+        self.boxed.len() + self.unboxed.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// every primitive held, in no particular order
+    pub fn primitives(&self) -> impl Iterator<Item = &P> {
+        self.boxed.iter().chain(&self.unboxed)
+    }
+}
+
+/// recursively builds the subtree over `refs` and returns the index of its root node,
+/// `offset` is the position of `refs[0]` in the final primitive ordering
+fn build<T: Scalar>(nodes: &mut Vec<BvhNode<T>>, refs: &mut [BuildRef<T>], offset: usize) -> usize {
+    let bounds = refs
+        .iter()
+        .map(|r| r.bounds)
+        .reduce(|acc, next| acc.union(&next))
+        .expect("nodes are never built over an empty range");
+    let index = nodes.len();
+    let leaf = BvhNode { bounds, kind: NodeKind::Leaf { first: offset, count: refs.len() } };
+
+    if refs.len() <= MIN_LEAF_SIZE {
+        nodes.push(leaf);
+        return index;
+    }
+
+    let centroids = Aabb::from_points(refs.iter().map(|r| r.centroid))
+        .expect("refs is not empty");
+    let axis = centroids.longest_axis();
+    let split = match sah_split(refs, &bounds, &centroids, axis) {
+        Some(split) => split,
+        None if refs.len() <= MAX_LEAF_SIZE => {
+            nodes.push(leaf);
+            return index;
+        },
+        None => median_split(refs, axis),
+    };
+
+    nodes.push(BvhNode { bounds, kind: NodeKind::Interior { right: 0, axis } });
+    let (left_refs, right_refs) = refs.split_at_mut(split);
+    build(nodes, left_refs, offset);
+    let right = build(nodes, right_refs, offset + split);
+    nodes[index].kind = NodeKind::Interior { right, axis };
+    index
+}
+
+/// partitions `refs` at the cheapest bucket boundary and returns the split position,
+/// or None if no split is cheaper than leaving them all in one leaf
+fn sah_split<T: Scalar>(refs: &mut [BuildRef<T>], bounds: &Aabb<T>, centroids: &Aabb<T>, axis: usize) -> Option<usize> {
+    let (low, width) = (centroids.min()[axis], centroids.extent()[axis]);
+    if width <= T::zero() {
+        // every centroid is in the same place, nothing to separate
+        return None;
+    }
+
+    let bin_of = |r: &BuildRef<T>| {
+        let relative = ((r.centroid[axis] - low) / width).to_float();
+        ((relative * SAH_BINS as f64) as usize).min(SAH_BINS - 1)
+    };
+
+    let mut bins: [(usize, Option<Aabb<T>>); SAH_BINS] = [(0, None); SAH_BINS];
+    for r in refs.iter() {
+        let bin = &mut bins[bin_of(r)];
+        bin.0 += 1;
+        bin.1 = Some(bin.1.map_or(r.bounds, |b| b.union(&r.bounds)));
+    }
+
+    let cost_of = |range: &[(usize, Option<Aabb<T>>)]| {
+        let count: usize = range.iter().map(|b| b.0).sum();
+        let area = range
+            .iter()
+            .filter_map(|b| b.1)
+            .reduce(|acc, next| acc.union(&next))
+            .map_or(T::zero(), |b| b.surface_area());
+        area * T::from_float(count as f64)
+    };
+
+    let (best_bin, best_cost) = (1..SAH_BINS)
+        .map(|split| (split, cost_of(&bins[..split]) + cost_of(&bins[split..])))
+        .reduce(|acc, next| if next.1 < acc.1 { next } else { acc })?;
+
+    let leaf_cost = bounds.surface_area() * T::from_float(refs.len() as f64);
+    if best_cost >= leaf_cost && refs.len() <= MAX_LEAF_SIZE {
+        return None;
+    }
+
+    let split = partition(refs, |r| bin_of(r) < best_bin);
+    if split == 0 || split == refs.len() {
+        None
+    } else {
+        Some(split)
+    }
+}
+
+/// fallback for when the heuristic can't separate the primitives, always splits in half
+fn median_split<T: Scalar>(refs: &mut [BuildRef<T>], axis: usize) -> usize {
+    let mid = refs.len() / 2;
+    refs.select_nth_unstable_by(mid, |a, b| {
+        a.centroid[axis]
+            .partial_cmp(&b.centroid[axis])
+            .unwrap_or(core::cmp::Ordering::Equal)
+    });
+    mid
+}
+
+/// in place partition, returns the number of elements for which `pred` was true
+fn partition<E, F: Fn(&E) -> bool>(items: &mut [E], pred: F) -> usize {
+    let mut split = 0;
+    for i in 0..items.len() {
+        if pred(&items[i]) {
+            items.swap(i, split);
+            split += 1;
+        }
+    }
+    split
+}
+
+/// records `next` as the closest hit so far, returning the new maximum search depth
+fn nearest<T: Scalar>(closest: &mut Option<Intersection<T>>, next: Intersection<T>) -> T {
+    let depth = next.depth();
+    *closest = Some(next);
+    depth
+}
+
+impl<T, P> Intersectable<T> for Bvh<T, P>
+where
+    T: Scalar,
+    P: Intersectable<T>,
+{
+    fn intersect(&self, ray: Ray<T>, min_depth: T, max_depth: T) -> Option<Intersection<T>> {
+        let mut closest = None;
+        let mut max_depth = max_depth;
+
+        for primitive in &self.unboxed {
+            if let Some(hit) = primitive.intersect(ray, min_depth, max_depth) {
+                max_depth = nearest(&mut closest, hit);
+            }
+        }
+
+        if self.nodes.is_empty() {
+            return closest;
+        }
+
+        let mut stack = Vec::with_capacity(64);
+        stack.push(0);
+        while let Some(index) = stack.pop() {
+            let node = &self.nodes[index];
+            if !node.bounds.intersect(ray, min_depth, max_depth) {
+                continue;
+            }
+            match node.kind {
+                NodeKind::Leaf { first, count } => {
+                    for primitive in &self.boxed[first..first + count] {
+                        if let Some(hit) = primitive.intersect(ray, min_depth, max_depth) {
+                            max_depth = nearest(&mut closest, hit);
+                        }
+                    }
+                },
+                NodeKind::Interior { right, axis } => {
+                    // visit the child nearer the ray origin first so later boxes can be culled
+                    if ray.orientation()[axis] < T::zero() {
+                        stack.push(index + 1);
+                        stack.push(right);
+                    } else {
+                        stack.push(right);
+                        stack.push(index + 1);
+                    }
+                },
+            }
+        }
+
+        closest
+    }
+
+    fn bounding_box(&self) -> Option<Aabb<T>> {
+        if self.unboxed.is_empty() {
+            self.nodes.first().map(|root| root.bounds)
+        } else {
+            None
+        }
+    }
+}
+
+impl<T, P> FromIterator<P> for Bvh<T, P>
+where
+    T: Scalar,
+    P: Intersectable<T>,
+{
+    fn from_iter<I: IntoIterator<Item = P>>(iter: I) -> Self {
+        Self::new(iter.into_iter().collect())
+    }
+}
+
+impl<T, P> Fingerprint for Bvh<T, P>
+where
+    T: Scalar,
+    P: Intersectable<T> + Fingerprint
+{
+    /// the hierarchy is rebuilt the same way from the same primitives, so they're all that's needed
+    fn fingerprint(&self, state: &mut Fingerprinter) {
+        state.usize(self.len());
+        self.primitives().for_each(|primitive| primitive.fingerprint(state));
+    }
 }
 
+#[cfg(test)]
+mod test {
+    use std::sync::Arc;
+
+    use nalgebra::{point, vector, Similarity3, Vector3};
+
+    use super::*;
+    use crate::{geometry::{Plane, Sphere}, Material};
+
+    fn sphere_grid(n: usize) -> Vec<Geometry<f64>> {
+        (0..n * n)
+            .map(|i| {
+                let (x, z) = ((i % n) as f64, (i / n) as f64);
+                Geometry::Sphere(Sphere::new(point![x, 0.0, -z], 0.3, Material::default()))
+            })
+            .chain(std::iter::once(Geometry::Plane(Plane::default())))
+            .collect()
+    }
+
+    fn brute_force(geometry: &[Geometry<f64>], ray: Ray<f64>) -> Option<Intersection<f64>> {
+        geometry
+            .iter()
+            .filter_map(|g| g.intersect(ray, 1e-5, f64::INFINITY))
+            .reduce(|acc, next| if next.depth() < acc.depth() { next } else { acc })
+    }
+
+    #[test]
+    pub fn test_bvh_matches_brute_force() {
+        let geometry = sphere_grid(10);
+        let bvh = Blas::new(geometry.clone());
+        for i in 0..200 {
+            let angle = i as f64 * 0.013;
+            let ray = Ray::from_orientation(point![-1.0, 0.2, 1.0], vector![angle.sin() + 0.5, -0.1, -angle.cos()]);
+            let expected = brute_force(&geometry, ray).map(|hit| hit.point());
+            let actual = bvh.intersect(ray, 1e-5, f64::INFINITY).map(|hit| hit.point());
+            assert_eq!(expected, actual, "ray {i}");
+        }
+    }
+
+    #[test]
+    pub fn test_instance_hit_matches_transformed_geometry() {
+        let blas = Arc::new(Blas::new(vec![Geometry::Sphere(Sphere::new(point![0.0, 0.0, 0.0], 1.0, Material::default()))]));
+        let transform = Similarity3::new(vector![2.0, 0.0, -5.0], Vector3::zeros(), 0.5);
+        let tlas = Tlas::new(vec![Instance::new(blas, transform)]);
+
+        let ray = Ray::from_orientation(point![2.0, 0.0, 0.0], vector![0.0, 0.0, -1.0]);
+        let hit = tlas.intersect(ray, 1e-5, f64::INFINITY).expect("ray should hit the instance");
+        assert!((hit.point() - point![2.0, 0.0, -4.5]).norm() < 1e-9);
+        assert!((hit.normal().into_inner() - vector![0.0, 0.0, 1.0]).norm() < 1e-9);
+        assert!((hit.depth() - 4.5).abs() < 1e-9);
+        assert!(tlas.intersect(ray, 1e-5, 4.0).is_none());
+    }
+}
@@ -0,0 +1,224 @@
+use nalgebra::{Point2, Point3, Unit, Vector3, vector};
+
+use crate::{
+    Scalar,
+    Material,
+    fingerprint::{Fingerprint, Fingerprinter},
+    geometry::{
+        csg::{pair_spans, Solid, Span},
+        cylinder::polar_uv,
+        roots::{solve_quadratic, Roots},
+        Aabb,
+        Frame,
+        Intersectable,
+        Intersection,
+        Ray,
+        ShapeError,
+    },
+};
+
+/// a circular cone with its base on `base`, narrowing to a point `height` along `axis`. the
+/// base is closed with a disk unless it's made [`Cone::without_cap`], like a [`Cylinder`]
+///
+/// [`Cylinder`]: crate::geometry::Cylinder
+#[derive(Debug, Clone)]
+pub struct Cone<T>
+where
+    T: Scalar
+{
+    frame: Frame<T>,
+    radius: T,
+    height: T,
+    capped: bool,
+    material: Material<T>,
+}
+
+impl<T> Cone<T>
+where
+    T: Scalar
+{
+    /// fails unless the radius and height are positive and finite, as the slope of the side
+    /// is one over the other
+    pub fn new(base: Point3<T>, axis: Unit<Vector3<T>>, radius: T, height: T, material: Material<T>) -> Result<Self, ShapeError> {
+        let usable = |value: T| value > T::zero() && value < T::INF;
+        if !usable(radius) {
+            return Err(ShapeError::BadRadius);
+        }
+        if !usable(height) {
+            return Err(ShapeError::BadHeight);
+        }
+        Ok(Self { frame: Frame::from_axis(base, axis), radius, height, capped: true, material })
+    }
+
+    /// an open cone without the disk across its base
+    pub fn without_cap(self) -> Self {
+        Self { capped: false, ..self }
+    }
+
+    /// whether the surface encloses a volume, an open cone has no inside
+    pub fn is_closed(&self) -> bool {
+        self.capped
+    }
+
+    pub fn material(&self) -> Material<T> {
+        self.material
+    }
+
+    pub fn with_material(self, material: Material<T>) -> Self {
+        Self { material, ..self }
+    }
+
+    fn local_ray(&self, ray: &Ray<T>) -> (Point3<T>, Vector3<T>) {
+        (self.frame.to_local_point(&ray.origin()), self.frame.to_local_vector(&ray.orientation()))
+    }
+
+    /// the intersection `depth` along the world space `ray`
+    fn hit(&self, ray: Ray<T>, depth: T) -> Intersection<T> {
+        let (origin, direction) = self.local_ray(&ray);
+        let (normal, uv) = self.local_surface(&(origin + direction * depth));
+        let normal = Unit::new_normalize(self.frame.to_world_vector(&normal));
+        Intersection::new(ray.project(depth), ray, normal, self.material).with_uv(uv)
+    }
+
+    /// every depth at which `ray` crosses the surface, in local space
+    pub(crate) fn local_hits(&self, origin: &Point3<T>, direction: &Vector3<T>) -> Roots<T> {
+        // x^2 + y^2 = (k (h - z))^2 where k is the slope of the side
+        let k = self.radius / self.height;
+        let k2 = k * k;
+        let h = self.height - origin.z;
+        let a = direction.x * direction.x + direction.y * direction.y - k2 * direction.z * direction.z;
+        let b = (origin.x * direction.x + origin.y * direction.y + k2 * h * direction.z) * T::TWO;
+        let c = origin.x * origin.x + origin.y * origin.y - k2 * h * h;
+
+        let mut hits = Roots::default();
+        for &depth in solve_quadratic(a, b, c).as_slice() {
+            // the equation also describes the mirrored cone above the apex
+            let z = origin.z + direction.z * depth;
+            if z >= T::zero() && z <= self.height {
+                hits.push(depth);
+            }
+        }
+        if self.capped && direction.z != T::zero() {
+            let depth = -origin.z / direction.z;
+            let (x, y) = (origin.x + direction.x * depth, origin.y + direction.y * depth);
+            if x * x + y * y <= self.radius * self.radius {
+                hits.push(depth);
+            }
+        }
+        hits
+    }
+
+    /// outward normal and uv at a local point on the surface
+    pub(crate) fn local_surface(&self, point: &Point3<T>) -> (Vector3<T>, Point2<T>) {
+        let distance = point.coords.xy().norm();
+        let on_cap = self.capped
+            && point.z <= self.height * T::from_float(1e-6)
+            && distance < self.radius * T::from_float(1.0 - 1e-6);
+        if on_cap {
+            (-Vector3::z(), polar_uv(point, self.radius))
+        } else if distance <= self.radius * T::from_float(1e-6) {
+            // the side's normal shrinks to nothing at the apex, so it points along the axis
+            (Vector3::z(), Point2::new(T::zero(), T::one()))
+        } else {
+            let k = self.radius / self.height;
+            let angle = point.y.atan2(point.x) + T::pi();
+            (
+                vector![point.x, point.y, k * k * (self.height - point.z)],
+                Point2::new(angle / T::two_pi(), point.z / self.height),
+            )
+        }
+    }
+}
+
+impl<T> Intersectable<T> for Cone<T>
+where
+    T: Scalar
+{
+    fn intersect(&self, ray: Ray<T>, min_depth: T, max_depth: T) -> Option<Intersection<T>> {
+        let (origin, direction) = self.local_ray(&ray);
+        let depth = self.local_hits(&origin, &direction).first_within(min_depth, max_depth)?;
+        Some(self.hit(ray, depth))
+    }
+
+    fn bounding_box(&self) -> Option<Aabb<T>> {
+        let (axis, base) = (self.frame.w(), self.frame.origin());
+        let apex = base + axis.into_inner() * self.height;
+        Some(Aabb::around_circle(base, &axis, self.radius).union(&Aabb::new(apex, apex)))
+    }
+}
+
+impl<T> Solid<T> for Cone<T>
+where
+    T: Scalar
+{
+    fn spans(&self, ray: Ray<T>) -> Vec<Span<T>> {
+        if !self.is_closed() {
+            return Vec::new();
+        }
+        let (origin, direction) = self.local_ray(&ray);
+        pair_spans(self.local_hits(&origin, &direction).as_slice(), |depth| self.hit(ray, depth))
+    }
+}
+
+impl<T> Fingerprint for Cone<T>
+where
+    T: Scalar
+{
+    fn fingerprint(&self, state: &mut Fingerprinter) {
+        state.add(&self.frame);
+        state.scalar(self.radius);
+        state.scalar(self.height);
+        state.bool(self.capped);
+        state.add(&self.material);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use nalgebra::point;
+
+    use super::*;
+
+    #[test]
+    pub fn test_cone_base_and_side() {
+        let cone = Cone::new(Point3::origin(), Vector3::y_axis(), 1.0, 2.0, Material::default()).unwrap();
+        assert!(cone.is_closed());
+        let up = Ray::from_orientation(point![0.25, -3.0, 0.0], vector![0.0, 1.0, 0.0]);
+        let hit = cone.intersect(up, 1e-5, f64::INFINITY).expect("should hit the base");
+        assert!((hit.depth() - 3.0).abs() < 1e-9);
+        assert!((hit.normal().into_inner() + Vector3::y()).norm() < 1e-9);
+
+        // halfway up the side is half the radius out, and the normal leans up by the slope
+        let across = Ray::from_orientation(point![-3.0, 1.0, 0.0], vector![1.0, 0.0, 0.0]);
+        let hit = cone.intersect(across, 1e-5, f64::INFINITY).expect("should hit the side");
+        assert!((hit.depth() - 2.5).abs() < 1e-9);
+        assert!((hit.normal().into_inner() - vector![-2.0, 1.0, 0.0].normalize()).norm() < 1e-9);
+        let above = Ray::from_orientation(point![-3.0, 2.5, 0.0], vector![1.0, 0.0, 0.0]);
+        assert!(cone.intersect(above, 1e-5, f64::INFINITY).is_none(), "the mirrored cone past the apex isn't there");
+
+        // without its base the ray goes in underneath and hits the inside of the side
+        let open = cone.without_cap();
+        assert!(!open.is_closed());
+        let hit = open.intersect(up, 1e-5, f64::INFINITY).expect("should hit the inside of the side");
+        assert!((hit.point().y - 1.5).abs() < 1e-9);
+    }
+
+    #[test]
+    pub fn test_cone_apex_faces_along_the_axis() {
+        let cone = Cone::new(Point3::origin(), Vector3::y_axis(), 1.0, 2.0, Material::default()).unwrap();
+        let down = Ray::from_orientation(point![0.0, 5.0, 0.0], vector![0.0, -1.0, 0.0]);
+        let hit = cone.intersect(down, 1e-5, f64::INFINITY).expect("should hit the apex");
+        assert!((hit.depth() - 3.0).abs() < 1e-9);
+        assert!((hit.normal().into_inner() - Vector3::y()).norm() < 1e-9);
+    }
+
+    #[test]
+    pub fn test_flat_cones_are_rejected() {
+        for height in [0.0, -1.0, f64::NAN] {
+            let cone = Cone::<f64>::new(Point3::origin(), Vector3::y_axis(), 1.0, height, Material::default());
+            assert_eq!(cone.unwrap_err(), ShapeError::BadHeight, "{height}");
+        }
+        let pin = Cone::<f64>::new(Point3::origin(), Vector3::y_axis(), 0.0, 1.0, Material::default());
+        assert_eq!(pin.unwrap_err(), ShapeError::BadRadius);
+    }
+}
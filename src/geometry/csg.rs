@@ -0,0 +1,338 @@
+use nalgebra::Point3;
+use tracing::warn;
+
+use crate::{
+    Scalar,
+    fingerprint::{Fingerprint, Fingerprinter},
+    geometry::{Aabb, Geometry, Intersectable, Intersection, Ray},
+};
+
+/// one end of a [`Span`], the intersection is missing for spans which
+/// run off to infinity such as the inside of a half-space
+#[derive(Debug, Clone)]
+pub struct Boundary<T>
+where
+    T: Scalar
+{
+    /// signed distance along the ray, negative boundaries are behind the origin
+    pub depth: T,
+    pub intersection: Option<Intersection<T>>,
+}
+
+impl<T> Boundary<T>
+where
+    T: Scalar
+{
+    pub fn new(depth: T, intersection: Intersection<T>) -> Self {
+        Self { depth, intersection: Some(intersection) }
+    }
+
+    pub fn infinite(depth: T) -> Self {
+        Self { depth, intersection: None }
+    }
+
+    /// the surface as seen from the other side, used when a solid is subtracted
+    fn inverted(self) -> Self {
+        Self { intersection: self.intersection.map(Intersection::inverted), ..self }
+    }
+}
+
+/// a stretch of a ray spent inside a solid
+#[derive(Debug, Clone)]
+pub struct Span<T>
+where
+    T: Scalar
+{
+    pub enter: Boundary<T>,
+    pub exit: Boundary<T>,
+}
+
+/// closed shapes with a well defined inside, which can report every interval a ray
+/// spends inside them rather than just the first surface it crosses
+pub trait Solid<T>: Intersectable<T>
+where
+    T: Scalar
+{
+    /// every span along the whole line through `ray`, including behind its origin,
+    /// sorted by depth and not overlapping
+    fn spans(&self, ray: Ray<T>) -> Vec<Span<T>>;
+}
+
+/// pairs up sorted surface crossings into spans, `hit` builds the intersection at a depth.
+/// only closed surfaces cross an even number of times, open ones should return no spans
+pub(crate) fn pair_spans<T, F>(depths: &[T], hit: F) -> Vec<Span<T>>
+where
+    T: Scalar,
+    F: Fn(T) -> Intersection<T>,
+{
+    depths
+        .chunks_exact(2)
+        .map(|pair| Span {
+            enter: Boundary::new(pair[0], hit(pair[0])),
+            exit: Boundary::new(pair[1], hit(pair[1])),
+        })
+        .collect()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CsgOperation {
+    Union,
+    Intersection,
+    /// the left child with the right carved out of it
+    Difference,
+}
+
+impl CsgOperation {
+    fn inside(&self, left: bool, right: bool) -> bool {
+        match self {
+            CsgOperation::Union => left || right,
+            CsgOperation::Intersection => left && right,
+            CsgOperation::Difference => left && !right,
+        }
+    }
+}
+
+/// combines two solids by tracking where the ray enters and leaves each of them
+#[derive(Debug, Clone)]
+pub struct Csg<T>
+where
+    T: Scalar
+{
+    operation: CsgOperation,
+    left: Box<Geometry<T>>,
+    right: Box<Geometry<T>>,
+}
+
+impl<T> Csg<T>
+where
+    T: Scalar
+{
+    /// children without an inside are kept but never contribute, see [`Geometry::is_solid`]
+    pub fn new(operation: CsgOperation, left: Geometry<T>, right: Geometry<T>) -> Self {
+        for (side, child) in [("left", &left), ("right", &right)] {
+            if !child.is_solid() {
+                warn!("the {side} side of a {operation:?} has no inside, so it's left out");
+            }
+        }
+        Self { operation, left: Box::new(left), right: Box::new(right) }
+    }
+
+    pub fn union(left: Geometry<T>, right: Geometry<T>) -> Self {
+        Self::new(CsgOperation::Union, left, right)
+    }
+
+    pub fn intersection(left: Geometry<T>, right: Geometry<T>) -> Self {
+        Self::new(CsgOperation::Intersection, left, right)
+    }
+
+    pub fn difference(left: Geometry<T>, right: Geometry<T>) -> Self {
+        Self::new(CsgOperation::Difference, left, right)
+    }
+
+    pub fn operation(&self) -> CsgOperation {
+        self.operation
+    }
+}
+
+/// a change of state for one child while sweeping along the ray
+struct Event<T>
+where
+    T: Scalar
+{
+    boundary: Boundary<T>,
+    from_left: bool,
+    entering: bool,
+}
+
+fn events<T: Scalar>(spans: Vec<Span<T>>, from_left: bool) -> impl Iterator<Item = Event<T>> {
+    spans.into_iter().flat_map(move |span| [
+        Event { boundary: span.enter, from_left, entering: true },
+        Event { boundary: span.exit, from_left, entering: false },
+    ])
+}
+
+/// the spans of `operation` applied to two sets of spans along the same ray
+pub(crate) fn combine<T: Scalar>(operation: CsgOperation, left: Vec<Span<T>>, right: Vec<Span<T>>) -> Vec<Span<T>> {
+    let mut events: Vec<_> = events(left, true)
+        .chain(events(right, false))
+        .collect();
+    events.sort_by(|a, b| a.boundary.depth.partial_cmp(&b.boundary.depth).unwrap_or(core::cmp::Ordering::Equal));
+
+    let (mut in_left, mut in_right) = (false, false);
+    let mut open: Option<Boundary<T>> = None;
+    let mut spans = Vec::new();
+    for event in events {
+        let was_inside = operation.inside(in_left, in_right);
+        if event.from_left {
+            in_left = event.entering;
+        } else {
+            in_right = event.entering;
+        }
+        let inside = operation.inside(in_left, in_right);
+        if was_inside == inside {
+            continue;
+        }
+        // surfaces of a subtracted solid face the wrong way for the result
+        let boundary = if !event.from_left && operation == CsgOperation::Difference {
+            event.boundary.inverted()
+        } else {
+            event.boundary
+        };
+        match open.take() {
+            None => open = Some(boundary),
+            Some(enter) => spans.push(Span { enter, exit: boundary }),
+        }
+    }
+    spans
+}
+
+impl<T> Solid<T> for Csg<T>
+where
+    T: Scalar
+{
+    fn spans(&self, ray: Ray<T>) -> Vec<Span<T>> {
+        combine(self.operation, self.left.spans(ray), self.right.spans(ray))
+    }
+}
+
+impl<T> Intersectable<T> for Csg<T>
+where
+    T: Scalar
+{
+    fn intersect(&self, ray: Ray<T>, min_depth: T, max_depth: T) -> Option<Intersection<T>> {
+        self.spans(ray)
+            .into_iter()
+            .flat_map(|span| [span.enter, span.exit])
+            .find(|boundary| boundary.depth >= min_depth && boundary.depth <= max_depth)
+            .and_then(|boundary| boundary.intersection)
+    }
+
+    fn bounding_box(&self) -> Option<Aabb<T>> {
+        let (left, right) = (self.left.bounding_box(), self.right.bounding_box());
+        match self.operation {
+            CsgOperation::Union => Some(left?.union(&right?)),
+            CsgOperation::Intersection => match (left, right) {
+                (Some(l), Some(r)) => l.overlap(&r),
+                (bounds, None) | (None, bounds) => bounds,
+            },
+            CsgOperation::Difference => left,
+        }
+    }
+}
+
+/// signed depth of `point` along `ray`
+pub(crate) fn signed_depth<T: Scalar>(ray: &Ray<T>, point: &Point3<T>) -> T {
+    (point - ray.origin()).dot(&ray.orientation())
+}
+
+impl<T> Fingerprint for Csg<T>
+where
+    T: Scalar
+{
+    fn fingerprint(&self, state: &mut Fingerprinter) {
+        state.usize(self.operation as usize);
+        state.add(&self.left).add(&self.right);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use nalgebra::{point, vector, Similarity3, Translation3, UnitQuaternion, Vector3};
+
+    use super::*;
+    use std::sync::Arc;
+
+    use crate::{geometry::{Blas, Cone, Cylinder, Instance, Sphere}, Material};
+
+    fn drilled_sphere() -> Csg<f64> {
+        let sphere = Sphere::new(Point3::origin(), 1.0, Material::default());
+        let drill = Cylinder::new(point![-2.0, 0.0, 0.0], Vector3::x_axis(), 0.25, 4.0, Material::default());
+        Csg::difference(Geometry::Sphere(sphere), Geometry::Cylinder(drill))
+    }
+
+    #[test]
+    pub fn test_difference_lets_rays_down_the_hole() {
+        let csg = drilled_sphere();
+        let down_the_hole = Ray::from_orientation(point![-5.0, 0.0, 0.0], vector![1.0, 0.0, 0.0]);
+        assert!(csg.intersect(down_the_hole, 1e-5, f64::INFINITY).is_none());
+
+        let beside_the_hole = Ray::from_orientation(point![-5.0, 0.5, 0.0], vector![1.0, 0.0, 0.0]);
+        let hit = csg.intersect(beside_the_hole, 1e-5, f64::INFINITY).expect("should hit the sphere");
+        assert!((hit.point().x + 0.75_f64.sqrt()).abs() < 1e-9);
+    }
+
+    #[test]
+    pub fn test_difference_flips_carved_surfaces() {
+        // straight down through the top of the sphere and into the bore
+        let csg = drilled_sphere();
+        let ray = Ray::from_orientation(point![0.0, 5.0, 0.0], vector![0.0, -1.0, 0.0]);
+        let spans = csg.spans(ray);
+        assert_eq!(spans.len(), 2);
+        let depths: Vec<_> = spans.iter().flat_map(|s| [s.enter.depth, s.exit.depth]).collect();
+        for (depth, expected) in depths.iter().zip([4.0, 4.75, 5.25, 6.0]) {
+            assert!((depth - expected).abs() < 1e-9, "{depth} != {expected}");
+        }
+        // leaving the first span means dropping into the bore, the carved surface must
+        // face out of the remaining material rather than out of the cylinder
+        let exit = spans[0].exit.intersection.as_ref().unwrap();
+        assert!((exit.normal().into_inner() + Vector3::y()).norm() < 1e-9);
+        assert_eq!(exit.face(), crate::geometry::intersect::Face::Back);
+    }
+
+    #[test]
+    pub fn test_intersection_of_spheres_is_a_lens() {
+        let left = Sphere::new(point![0.0, 0.0, 0.5], 1.0, Material::default());
+        let right = Sphere::new(point![0.0, 0.0, -0.5], 1.0, Material::default());
+        let lens = Csg::intersection(Geometry::Sphere(left), Geometry::Sphere(right));
+        let ray = Ray::from_orientation(point![0.0, 0.0, 5.0], vector![0.0, 0.0, -1.0]);
+        let hit = lens.intersect(ray, 1e-5, f64::INFINITY).expect("should hit the lens");
+        assert!((hit.depth() - 4.5).abs() < 1e-9);
+        let bounds = lens.bounding_box().unwrap();
+        assert!((bounds.min().z + 0.5).abs() < 1e-9 && (bounds.max().z - 0.5).abs() < 1e-9);
+    }
+
+    #[test]
+    pub fn test_open_shapes_have_no_inside() {
+        let sphere = Geometry::Sphere(Sphere::new(Point3::<f64>::origin(), 1.0, Material::default()));
+        let cone = Cone::new(point![0.0, -2.0, 0.0], Vector3::y_axis(), 0.5, 4.0, Material::default()).unwrap().without_cap();
+        assert!(!Geometry::Cone(cone.clone()).is_solid());
+        let capped = Cone::new(point![0.0, -2.0, 0.0], Vector3::y_axis(), 0.5, 4.0, Material::default()).unwrap();
+        assert!(Geometry::Cone(capped.clone()).is_solid());
+
+        // the open cone crosses the ray once on its way through the sphere, which used to leave
+        // a lone crossing paired with nothing and the difference carved in the wrong place
+        let ray = Ray::from_orientation(point![0.1, 5.0, 0.0], vector![0.0, -1.0, 0.0]);
+        assert!(Geometry::Cone(cone.clone()).spans(ray).is_empty());
+        let open = Csg::difference(sphere.clone(), Geometry::Cone(cone.clone()));
+        let spans = open.spans(ray);
+        assert_eq!(spans.len(), 1);
+        let half_chord = 0.99_f64.sqrt();
+        assert!((spans[0].enter.depth - (5.0 - half_chord)).abs() < 1e-9);
+        assert!((spans[0].exit.depth - (5.0 + half_chord)).abs() < 1e-9);
+
+        // capped, it's a solid and carves a hole straight through
+        let capped = Csg::difference(sphere, Geometry::Cone(capped));
+        let hit = capped.intersect(ray, 1e-5, f64::INFINITY);
+        assert!(hit.is_none(), "should go down the hole, hit {hit:?}");
+    }
+
+    #[test]
+    pub fn test_instances_take_part_in_csg() {
+        let blas = Arc::new(Blas::new(vec![Geometry::Sphere(Sphere::new(Point3::<f64>::origin(), 1.0, Material::default()))]));
+        let transform = Similarity3::from_parts(Translation3::new(0.0, 0.0, -1.0), UnitQuaternion::identity(), 2.0);
+        let instanced = Geometry::Instance(Instance::new(blas, transform));
+        let direct = Geometry::Sphere(Sphere::new(point![0.0, 0.0, -1.0], 2.0, Material::default()));
+        let drill = Geometry::Cylinder(Cylinder::new(point![-3.0, 0.0, 0.0], Vector3::x_axis(), 0.25, 6.0, Material::default()));
+
+        let ray = Ray::from_orientation(point![0.0, 0.0, 5.0], vector![0.0, 0.0, -1.0]);
+        let from_instance = Csg::difference(instanced, drill.clone()).spans(ray);
+        let from_sphere = Csg::difference(direct, drill).spans(ray);
+        assert_eq!(from_instance.len(), from_sphere.len());
+        for (a, b) in from_instance.iter().zip(&from_sphere) {
+            assert!((a.enter.depth - b.enter.depth).abs() < 1e-9 && (a.exit.depth - b.exit.depth).abs() < 1e-9);
+            let (a, b) = (a.enter.intersection.as_ref().unwrap(), b.enter.intersection.as_ref().unwrap());
+            assert!((a.point() - b.point()).norm() < 1e-9);
+            assert!((a.normal().into_inner() - b.normal().into_inner()).norm() < 1e-9);
+        }
+    }
+}
@@ -0,0 +1,192 @@
+use nalgebra::{Point3, vector};
+
+use crate::{
+    Scalar,
+    Material,
+    fingerprint::{Fingerprint, Fingerprinter},
+    geometry::{
+        aabb::FLAT_MARGIN,
+        csg::{signed_depth, Boundary, Solid, Span},
+        Aabb,
+        Intersectable,
+        Intersection,
+        Quad,
+        Ray,
+        ShapeError,
+        Surface,
+        SurfaceSample,
+    },
+};
+
+/// an axis aligned box built from six outward facing quads,
+/// rotated boxes can be made by instancing
+#[derive(Debug, Clone)]
+pub struct Cuboid<T>
+where
+    T: Scalar
+{
+    bounds: Aabb<T>,
+    faces: [Quad<T>; 6],
+    material: Material<T>,
+}
+
+impl<T> Cuboid<T>
+where
+    T: Scalar
+{
+    /// creates a box from two opposite corners, in any order. fails if the corners share a
+    /// coordinate, leaving the box flat, as its side faces would have no area
+    pub fn new(a: Point3<T>, b: Point3<T>, material: Material<T>) -> Result<Self, ShapeError> {
+        let bounds = Aabb::new(a, b);
+        let (lo, hi) = (bounds.min(), bounds.max());
+        let e = bounds.extent();
+        if !(e.x > T::zero() && e.y > T::zero() && e.z > T::zero()) {
+            return Err(ShapeError::FlatCuboid);
+        }
+        let (dx, dy, dz) = (vector![e.x, T::zero(), T::zero()], vector![T::zero(), e.y, T::zero()], vector![T::zero(), T::zero(), e.z]);
+        // edge order is chosen so each normal points away from the box
+        let faces = [
+            Quad::new(lo, dz, dy, material)?,
+            Quad::new(Point3::new(hi.x, lo.y, lo.z), dy, dz, material)?,
+            Quad::new(lo, dx, dz, material)?,
+            Quad::new(Point3::new(lo.x, hi.y, lo.z), dz, dx, material)?,
+            Quad::new(lo, dy, dx, material)?,
+            Quad::new(Point3::new(lo.x, lo.y, hi.z), dx, dy, material)?,
+        ];
+        Ok(Self { bounds, faces, material })
+    }
+
+    pub fn material(&self) -> Material<T> {
+        self.material
+    }
+
+    pub fn with_material(self, material: Material<T>) -> Self {
+        Self { faces: self.faces.map(|face| face.with_material(material)), material, ..self }
+    }
+
+    pub fn faces(&self) -> &[Quad<T>; 6] {
+        &self.faces
+    }
+}
+
+impl<T> Intersectable<T> for Cuboid<T>
+where
+    T: Scalar
+{
+    fn intersect(&self, ray: Ray<T>, min_depth: T, max_depth: T) -> Option<Intersection<T>> {
+        if !self.bounds.inflate(T::from_float(FLAT_MARGIN)).intersect(ray, min_depth, max_depth) {
+            return None;
+        }
+        let mut max_depth = max_depth;
+        let mut closest = None;
+        for face in &self.faces {
+            if let Some(hit) = face.intersect(ray, min_depth, max_depth) {
+                max_depth = hit.depth();
+                closest = Some(hit);
+            }
+        }
+        closest
+    }
+
+    fn bounding_box(&self) -> Option<Aabb<T>> {
+        Some(self.bounds)
+    }
+}
+
+impl<T> Solid<T> for Cuboid<T>
+where
+    T: Scalar
+{
+    fn spans(&self, ray: Ray<T>) -> Vec<Span<T>> {
+        let mut hits: Vec<_> = self.faces
+            .iter()
+            .filter_map(|face| face.intersect(ray, -T::INF, T::INF))
+            .map(|hit| Boundary::new(signed_depth(&ray, &hit.point()), hit))
+            .collect();
+        hits.sort_by(|a, b| a.depth.partial_cmp(&b.depth).unwrap_or(core::cmp::Ordering::Equal));
+        // a ray through an edge can clip more than two faces, only the outermost matter
+        match (hits.first(), hits.last()) {
+            (Some(enter), Some(exit)) if hits.len() >= 2 => vec![Span { enter: enter.clone(), exit: exit.clone() }],
+            _ => Vec::new(),
+        }
+    }
+}
+
+impl<T> Surface<T> for Cuboid<T>
+where
+    T: Scalar
+{
+    fn area(&self) -> T {
+        self.bounds.surface_area()
+    }
+
+    fn sample_surface(&self, u: T, v: T, time: T) -> SurfaceSample<T> {
+        // pick a face in proportion to its area then reuse `u` within that face
+        let mut target = u * self.area();
+        for face in &self.faces {
+            let area = face.area();
+            if target < area {
+                return face.sample_surface(target / area, v, time);
+            }
+            target -= area;
+        }
+        let last = &self.faces[5];
+        last.sample_surface(T::one(), v, time)
+    }
+}
+
+impl<T> Fingerprint for Cuboid<T>
+where
+    T: Scalar
+{
+    /// the faces are built from the bounds, so they're all that's needed
+    fn fingerprint(&self, state: &mut Fingerprinter) {
+        state.add(&self.bounds).add(&self.material);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use nalgebra::{point, vector};
+
+    use super::*;
+
+    #[test]
+    pub fn test_cuboid_faces_point_outwards() {
+        let cuboid = Cuboid::new(point![1.0, 1.0, 1.0], point![-1.0, -1.0, -1.0], Material::default()).unwrap();
+        let directions = [
+            vector![1.0, 0.0, 0.0], vector![-1.0, 0.0, 0.0],
+            vector![0.0, 1.0, 0.0], vector![0.0, -1.0, 0.0],
+            vector![0.0, 0.0, 1.0], vector![0.0, 0.0, -1.0],
+        ];
+        for direction in directions {
+            // fire from outside the box back towards its center
+            let ray = Ray::from_orientation(Point3::origin() + direction * 5.0, -direction);
+            let hit = cuboid.intersect(ray, 1e-5, f64::INFINITY).expect("ray aimed at the box should hit");
+            assert!((hit.normal().into_inner() - direction).norm() < 1e-9);
+            assert!((hit.depth() - 4.0).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    pub fn test_cuboid_samples_lie_on_surface() {
+        let cuboid = Cuboid::<f64>::new(point![0.0, 0.0, 0.0], point![1.0, 2.0, 3.0], Material::default()).unwrap();
+        assert!((cuboid.area() - 22.0).abs() < 1e-9);
+        for i in 0..64 {
+            let (u, v) = (i as f64 / 64.0, (i * 37 % 64) as f64 / 64.0);
+            let sample = cuboid.sample_surface(u, v, 0.0);
+            let bounds = cuboid.bounding_box().unwrap();
+            let (lo, hi) = (bounds.min(), bounds.max());
+            let on_face = (0..3).any(|axis| {
+                (sample.point[axis] - lo[axis]).abs() < 1e-9 || (sample.point[axis] - hi[axis]).abs() < 1e-9
+            });
+            assert!(on_face, "sample {i} at {:?} is not on a face", sample.point);
+        }
+    }
+
+    #[test]
+    pub fn test_flat_cuboids_are_rejected() {
+        let flat = Cuboid::<f64>::new(point![0.0, 0.0, 0.0], point![1.0, 0.0, 3.0], Material::default());
+        assert_eq!(flat.unwrap_err(), ShapeError::FlatCuboid);
+    }
+}
@@ -0,0 +1,184 @@
+use nalgebra::{Point2, Point3, Unit, Vector3, vector};
+
+use crate::{
+    Scalar,
+    Material,
+    fingerprint::{Fingerprint, Fingerprinter},
+    geometry::{
+        csg::{pair_spans, Solid, Span},
+        roots::{solve_quadratic, Roots},
+        Aabb,
+        Frame,
+        Intersectable,
+        Intersection,
+        Ray,
+    },
+};
+
+/// a circular cylinder standing on `base` and extending `height` along `axis`
+#[derive(Debug, Clone)]
+pub struct Cylinder<T>
+where
+    T: Scalar
+{
+    frame: Frame<T>,
+    radius: T,
+    height: T,
+    capped: bool,
+    material: Material<T>,
+}
+
+impl<T> Cylinder<T>
+where
+    T: Scalar
+{
+    pub fn new(base: Point3<T>, axis: Unit<Vector3<T>>, radius: T, height: T, material: Material<T>) -> Self {
+        Self { frame: Frame::from_axis(base, axis), radius, height, capped: true, material }
+    }
+
+    /// an open tube without end caps
+    pub fn without_caps(self) -> Self {
+        Self { capped: false, ..self }
+    }
+
+    /// whether the surface encloses a volume, an open cylinder has no inside
+    pub fn is_closed(&self) -> bool {
+        self.capped
+    }
+
+    pub fn material(&self) -> Material<T> {
+        self.material
+    }
+
+    pub fn with_material(self, material: Material<T>) -> Self {
+        Self { material, ..self }
+    }
+
+    fn local_ray(&self, ray: &Ray<T>) -> (Point3<T>, Vector3<T>) {
+        (self.frame.to_local_point(&ray.origin()), self.frame.to_local_vector(&ray.orientation()))
+    }
+
+    /// the intersection `depth` along the world space `ray`
+    fn hit(&self, ray: Ray<T>, depth: T) -> Intersection<T> {
+        let (origin, direction) = self.local_ray(&ray);
+        let (normal, uv) = self.local_surface(&(origin + direction * depth));
+        let normal = Unit::new_normalize(self.frame.to_world_vector(&normal));
+        Intersection::new(ray.project(depth), ray, normal, self.material).with_uv(uv)
+    }
+
+    /// every depth at which `ray` crosses the surface, in local space
+    pub(crate) fn local_hits(&self, origin: &Point3<T>, direction: &Vector3<T>) -> Roots<T> {
+        let mut hits = Roots::default();
+        let a = direction.x * direction.x + direction.y * direction.y;
+        let b = (origin.x * direction.x + origin.y * direction.y) * T::TWO;
+        let c = origin.x * origin.x + origin.y * origin.y - self.radius * self.radius;
+        for &depth in solve_quadratic(a, b, c).as_slice() {
+            let z = origin.z + direction.z * depth;
+            if z >= T::zero() && z <= self.height {
+                hits.push(depth);
+            }
+        }
+        if self.capped && direction.z != T::zero() {
+            for cap in [T::zero(), self.height] {
+                let depth = (cap - origin.z) / direction.z;
+                let (x, y) = (origin.x + direction.x * depth, origin.y + direction.y * depth);
+                if x * x + y * y <= self.radius * self.radius {
+                    hits.push(depth);
+                }
+            }
+        }
+        hits
+    }
+
+    /// outward normal and uv at a local point on the surface
+    pub(crate) fn local_surface(&self, point: &Point3<T>) -> (Vector3<T>, Point2<T>) {
+        let tolerance = self.height * T::from_float(1e-6);
+        let radial = point.coords.xy().norm();
+        let on_cap = self.capped && radial < self.radius * T::from_float(1.0 - 1e-6);
+        if on_cap && point.z <= tolerance {
+            (-Vector3::z(), polar_uv(point, self.radius))
+        } else if on_cap && point.z >= self.height - tolerance {
+            (Vector3::z(), polar_uv(point, self.radius))
+        } else {
+            let angle = point.y.atan2(point.x) + T::pi();
+            (vector![point.x, point.y, T::zero()], Point2::new(angle / T::two_pi(), point.z / self.height))
+        }
+    }
+}
+
+/// radius then angle, used for flat end caps
+pub(crate) fn polar_uv<T: Scalar>(point: &Point3<T>, radius: T) -> Point2<T> {
+    let angle = point.y.atan2(point.x) + T::pi();
+    Point2::new(point.coords.xy().norm() / radius, angle / T::two_pi())
+}
+
+impl<T> Intersectable<T> for Cylinder<T>
+where
+    T: Scalar
+{
+    fn intersect(&self, ray: Ray<T>, min_depth: T, max_depth: T) -> Option<Intersection<T>> {
+        let (origin, direction) = self.local_ray(&ray);
+        let depth = self.local_hits(&origin, &direction).first_within(min_depth, max_depth)?;
+        Some(self.hit(ray, depth))
+    }
+
+    fn bounding_box(&self) -> Option<Aabb<T>> {
+        let (axis, base) = (self.frame.w(), self.frame.origin());
+        let top = base + axis.into_inner() * self.height;
+        Some(
+            Aabb::around_circle(base, &axis, self.radius)
+                .union(&Aabb::around_circle(top, &axis, self.radius))
+        )
+    }
+}
+
+impl<T> Solid<T> for Cylinder<T>
+where
+    T: Scalar
+{
+    fn spans(&self, ray: Ray<T>) -> Vec<Span<T>> {
+        if !self.is_closed() {
+            return Vec::new();
+        }
+        let (origin, direction) = self.local_ray(&ray);
+        pair_spans(self.local_hits(&origin, &direction).as_slice(), |depth| self.hit(ray, depth))
+    }
+}
+
+impl<T> Fingerprint for Cylinder<T>
+where
+    T: Scalar
+{
+    fn fingerprint(&self, state: &mut Fingerprinter) {
+        state.add(&self.frame);
+        state.scalar(self.radius);
+        state.scalar(self.height);
+        state.bool(self.capped);
+        state.add(&self.material);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use nalgebra::point;
+
+    use super::*;
+
+    #[test]
+    pub fn test_cylinder_caps_and_sides() {
+        let cylinder = Cylinder::new(Point3::origin(), Vector3::y_axis(), 0.5, 2.0, Material::default());
+        let down = Ray::from_orientation(point![0.1, 5.0, 0.0], vector![0.0, -1.0, 0.0]);
+        let hit = cylinder.intersect(down, 1e-5, f64::INFINITY).expect("should hit the top cap");
+        assert!((hit.depth() - 3.0).abs() < 1e-9);
+        assert!((hit.normal().into_inner() - Vector3::y()).norm() < 1e-9);
+
+        let across = Ray::from_orientation(point![-3.0, 1.0, 0.0], vector![1.0, 0.0, 0.0]);
+        let hit = cylinder.intersect(across, 1e-5, f64::INFINITY).expect("should hit the side");
+        assert!((hit.depth() - 2.5).abs() < 1e-9);
+        assert!((hit.normal().into_inner() + Vector3::x()).norm() < 1e-9);
+
+        let open = cylinder.without_caps();
+        let hit = open.intersect(down, 1e-5, f64::INFINITY);
+        assert!(hit.is_none(), "an open tube should let the ray straight through");
+    }
+}
@@ -0,0 +1,157 @@
+use nalgebra::{Point2, Point3, Unit, Vector3, point};
+
+use crate::{
+    Scalar,
+    Material,
+    fingerprint::{Fingerprint, Fingerprinter},
+    geometry::{
+        aabb::FLAT_MARGIN,
+        surface::concentric_disk,
+        Aabb,
+        Frame,
+        Intersectable,
+        Intersection,
+        Ray,
+        ShapeError,
+        Surface,
+        SurfaceSample,
+    },
+};
+
+/// a flat circle facing along `normal`
+#[derive(Debug, Clone)]
+pub struct Disk<T>
+where
+    T: Scalar
+{
+    frame: Frame<T>,
+    radius: T,
+    material: Material<T>,
+}
+
+impl<T> Disk<T>
+where
+    T: Scalar
+{
+    /// fails unless the radius is positive and finite, as hits are placed by their fraction
+    /// of the radius
+    pub fn new(center: Point3<T>, normal: Unit<Vector3<T>>, radius: T, material: Material<T>) -> Result<Self, ShapeError> {
+        if !(radius > T::zero() && radius < T::INF) {
+            return Err(ShapeError::BadRadius);
+        }
+        Ok(Self { frame: Frame::from_axis(center, normal), radius, material })
+    }
+
+    pub fn center(&self) -> Point3<T> {
+        self.frame.origin()
+    }
+
+    pub fn normal(&self) -> Unit<Vector3<T>> {
+        self.frame.w()
+    }
+
+    pub fn radius(&self) -> T {
+        self.radius
+    }
+
+    pub fn material(&self) -> Material<T> {
+        self.material
+    }
+
+    pub fn with_material(self, material: Material<T>) -> Self {
+        Self { material, ..self }
+    }
+}
+
+impl<T> Intersectable<T> for Disk<T>
+where
+    T: Scalar
+{
+    fn intersect(&self, ray: Ray<T>, min_depth: T, max_depth: T) -> Option<Intersection<T>> {
+        let normal = self.normal();
+        let ray_dot_normal = ray.orientation().dot(&normal);
+        if ray_dot_normal == T::zero() {
+            return None;
+        }
+        let depth = (self.center() - ray.origin()).dot(&normal) / ray_dot_normal;
+        if depth < min_depth || depth > max_depth {
+            return None;
+        }
+        let point = ray.project(depth);
+        let local = self.frame.to_local_point(&point);
+        let distance = local.coords.xy().norm();
+        if distance > self.radius {
+            return None;
+        }
+        // polar coordinates, radius then angle
+        let angle = local.y.atan2(local.x) + T::pi();
+        let uv = Point2::new(distance / self.radius, angle / T::two_pi());
+        Some(Intersection::new(point, ray, normal, self.material).with_uv(uv))
+    }
+
+    fn bounding_box(&self) -> Option<Aabb<T>> {
+        Some(Aabb::around_circle(self.center(), &self.normal(), self.radius).inflate(T::from_float(FLAT_MARGIN)))
+    }
+}
+
+impl<T> Surface<T> for Disk<T>
+where
+    T: Scalar
+{
+    fn area(&self) -> T {
+        T::pi() * self.radius * self.radius
+    }
+
+    fn sample_surface(&self, u: T, v: T, _time: T) -> SurfaceSample<T> {
+        let p = concentric_disk(u, v);
+        let local = point![p.x * self.radius, p.y * self.radius, T::zero()];
+        SurfaceSample { point: self.frame.to_world_point(&local), normal: self.normal() }
+    }
+}
+
+impl<T> Fingerprint for Disk<T>
+where
+    T: Scalar
+{
+    fn fingerprint(&self, state: &mut Fingerprinter) {
+        state.add(&self.frame);
+        state.scalar(self.radius);
+        state.add(&self.material);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use nalgebra::vector;
+
+    use super::*;
+
+    #[test]
+    pub fn test_disk_hits_within_its_radius() {
+        let disk = Disk::<f64>::new(point![0.0, 1.0, 0.0], Vector3::y_axis(), 2.0, Material::default()).unwrap();
+        assert!((disk.area() - 4.0 * std::f64::consts::PI).abs() < 1e-12);
+
+        let ray = Ray::from_orientation(point![1.0, 3.0, 1.0], vector![0.0, -1.0, 0.0]);
+        let hit = disk.intersect(ray, 1e-5, f64::INFINITY).expect("ray inside the radius should hit");
+        assert!((hit.depth() - 2.0).abs() < 1e-12);
+        assert!((hit.uv().x - 2.0_f64.sqrt() / 2.0).abs() < 1e-12);
+        let outside = Ray::from_orientation(point![1.5, 3.0, 1.5], vector![0.0, -1.0, 0.0]);
+        assert!(disk.intersect(outside, 1e-5, f64::INFINITY).is_none());
+        let edge_on = Ray::from_orientation(point![-5.0, 1.0, 0.0], vector![1.0, 0.0, 0.0]);
+        assert!(disk.intersect(edge_on, 1e-5, f64::INFINITY).is_none());
+
+        for i in 0..64 {
+            let sample = disk.sample_surface(i as f64 / 64.0, (i * 37 % 64) as f64 / 64.0, 0.0);
+            assert!((sample.point.y - 1.0).abs() < 1e-12);
+            assert!((sample.point - disk.center()).norm() <= 2.0 + 1e-12);
+        }
+    }
+
+    #[test]
+    pub fn test_disks_without_a_radius_are_rejected() {
+        for radius in [0.0, -1.0, f64::NAN, f64::INFINITY] {
+            let disk = Disk::<f64>::new(Point3::origin(), Vector3::y_axis(), radius, Material::default());
+            assert_eq!(disk.unwrap_err(), ShapeError::BadRadius, "{radius}");
+        }
+    }
+}
@@ -0,0 +1,73 @@
+use nalgebra::{Point3, Unit, Vector3, vector};
+
+use crate::{Scalar, fingerprint::{Fingerprint, Fingerprinter}};
+
+/// an orthonormal coordinate frame, used by primitives that are simpler to
+/// intersect in their own local space with `w` as the "up" axis
+#[derive(Debug, Clone, Copy)]
+pub struct Frame<T>
+where
+    T: Scalar
+{
+    origin: Point3<T>,
+    u: Unit<Vector3<T>>,
+    v: Unit<Vector3<T>>,
+    w: Unit<Vector3<T>>,
+}
+
+impl<T> Frame<T>
+where
+    T: Scalar
+{
+    /// builds an arbitrary but continuous frame around `w`
+    pub fn from_axis(origin: Point3<T>, w: Unit<Vector3<T>>) -> Self {
+        // branchless basis from Duff et al. "Building an Orthonormal Basis, Revisited"
+        let sign = if w.z >= T::zero() { T::one() } else { -T::one() };
+        let a = -T::one() / (sign + w.z);
+        let b = w.x * w.y * a;
+        let u = Unit::new_unchecked(vector![T::one() + sign * w.x * w.x * a, sign * b, -sign * w.x]);
+        let v = Unit::new_unchecked(vector![b, sign + w.y * w.y * a, -w.y]);
+        Self { origin, u, v, w }
+    }
+
+    pub fn origin(&self) -> Point3<T> {
+        self.origin
+    }
+
+    pub fn u(&self) -> Unit<Vector3<T>> {
+        self.u
+    }
+
+    pub fn v(&self) -> Unit<Vector3<T>> {
+        self.v
+    }
+
+    pub fn w(&self) -> Unit<Vector3<T>> {
+        self.w
+    }
+
+    pub fn to_local_vector(&self, vector: &Vector3<T>) -> Vector3<T> {
+        vector![vector.dot(&self.u), vector.dot(&self.v), vector.dot(&self.w)]
+    }
+
+    pub fn to_local_point(&self, point: &Point3<T>) -> Point3<T> {
+        Point3::from(self.to_local_vector(&(point - self.origin)))
+    }
+
+    pub fn to_world_vector(&self, vector: &Vector3<T>) -> Vector3<T> {
+        self.u.into_inner() * vector.x + self.v.into_inner() * vector.y + self.w.into_inner() * vector.z
+    }
+
+    pub fn to_world_point(&self, point: &Point3<T>) -> Point3<T> {
+        self.origin + self.to_world_vector(&point.coords)
+    }
+}
+
+impl<T> Fingerprint for Frame<T>
+where
+    T: Scalar
+{
+    fn fingerprint(&self, state: &mut Fingerprinter) {
+        state.add(&self.origin).add(&self.u).add(&self.v).add(&self.w);
+    }
+}
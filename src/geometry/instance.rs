@@ -0,0 +1,140 @@
+use std::sync::Arc;
+
+use nalgebra::Similarity3;
+
+use crate::{
+    geometry::{
+        csg::{combine, Boundary, CsgOperation, Solid, Span},
+        motion::{Interpolate, Keyframes},
+        Aabb, Blas, Intersectable, Intersection, Ray,
+    },
+    Scalar,
+    fingerprint::{Fingerprint, Fingerprinter},
+};
+
+/// a placement of some shared geometry in the world, many instances can point at the
+/// same [`Blas`] so large repeated scenes only pay for the geometry once
+#[derive(Debug, Clone)]
+pub struct Instance<T>
+where
+    T: Scalar
+{
+    blas: Arc<Blas<T>>,
+    /// object space to world space
+    transform: Similarity3<T>,
+    /// world space to object space, cached as every ray needs it
+    inverse: Similarity3<T>,
+    /// overrides `transform` for instances which move while the shutter is open
+    motion: Option<Keyframes<T, Similarity3<T>>>,
+}
+
+/// number of intermediate transforms checked between keys when bounding a motion
+const MOTION_BOUND_STEPS: usize = 16;
+
+impl<T> Instance<T>
+where
+    T: Scalar
+{
+    pub fn new(blas: Arc<Blas<T>>, transform: Similarity3<T>) -> Self {
+        let inverse = transform.inverse();
+        Self { blas, transform, inverse, motion: None }
+    }
+
+    /// animates the placement over time, the original transform is ignored from then on
+    pub fn with_motion(self, motion: Keyframes<T, Similarity3<T>>) -> Self {
+        Self { motion: Some(motion), ..self }
+    }
+
+    /// object to world and world to object transforms at `time`
+    fn transforms_at(&self, time: T) -> (Similarity3<T>, Similarity3<T>) {
+        match &self.motion {
+            Some(motion) => {
+                let transform = motion.at(time);
+                (transform, transform.inverse())
+            },
+            None => (self.transform, self.inverse),
+        }
+    }
+
+    pub fn blas(&self) -> &Arc<Blas<T>> {
+        &self.blas
+    }
+
+    /// the object to world transform at `time`, following the motion if there is one
+    pub fn transform_at(&self, time: T) -> Similarity3<T> {
+        self.transforms_at(time).0
+    }
+}
+
+impl<T> Intersectable<T> for Instance<T>
+where
+    T: Scalar
+{
+    fn intersect(&self, ray: Ray<T>, min_depth: T, max_depth: T) -> Option<Intersection<T>> {
+        let (transform, inverse) = self.transforms_at(ray.time());
+        let local_ray = ray.transformed(&inverse);
+        // the local ray is renormalised, so depths shrink or grow with the scale
+        let scale = inverse.scaling();
+        self.blas
+            .intersect(local_ray, min_depth * scale, max_depth * scale)
+            .map(|hit| hit.transformed(&transform, ray))
+    }
+
+    fn bounding_box(&self) -> Option<Aabb<T>> {
+        let local = self.blas.bounding_box()?;
+        let motion = match &self.motion {
+            Some(motion) => motion,
+            None => return Some(local.transform(&self.transform)),
+        };
+        let reach = local
+            .corners()
+            .iter()
+            .fold(T::zero(), |acc, corner| acc.max(corner.coords.norm()));
+        let frames = motion.frames();
+        let mut bounds = local.transform(&frames[0].1);
+        for pair in frames.windows(2) {
+            let ((_, start), (_, end)) = (&pair[0], &pair[1]);
+            // rotating corners bow outwards between samples, by at most the sagitta of each step
+            let step = start.isometry.rotation.angle_to(&end.isometry.rotation) / T::from_float(MOTION_BOUND_STEPS as f64);
+            let sagitta = reach * start.scaling().max(end.scaling()) * (T::one() - (step * T::HALF).cos());
+            for i in 1..=MOTION_BOUND_STEPS {
+                let t = T::from_float(i as f64 / MOTION_BOUND_STEPS as f64);
+                bounds = bounds.union(&local.transform(&start.interpolate(end, t)).inflate(sagitta));
+            }
+        }
+        Some(bounds)
+    }
+}
+
+/// the instanced geometry is a union of everything in its [`Blas`], traced in object space
+impl<T> Solid<T> for Instance<T>
+where
+    T: Scalar
+{
+    fn spans(&self, ray: Ray<T>) -> Vec<Span<T>> {
+        let (transform, inverse) = self.transforms_at(ray.time());
+        let local_ray = ray.transformed(&inverse);
+        let scale = inverse.scaling();
+        let to_world = |boundary: Boundary<T>| Boundary {
+            depth: boundary.depth / scale,
+            intersection: boundary.intersection.map(|hit| hit.transformed(&transform, ray)),
+        };
+        self.blas
+            .primitives()
+            .map(|primitive| primitive.spans(local_ray))
+            .reduce(|acc, next| combine(CsgOperation::Union, acc, next))
+            .unwrap_or_default()
+            .into_iter()
+            .map(|span| Span { enter: to_world(span.enter), exit: to_world(span.exit) })
+            .collect()
+    }
+}
+
+impl<T> Fingerprint for Instance<T>
+where
+    T: Scalar
+{
+    fn fingerprint(&self, state: &mut Fingerprinter) {
+        state.add(&self.blas).add(&self.transform).add(&self.motion);
+    }
+}
@@ -1,11 +1,10 @@
 use core::fmt::Debug;
 
-use nalgebra::{vector, Vector3, Point3, Unit};
-use rand::Rng;
-use rand_distr::StandardNormal;
+use nalgebra::{vector, Vector3, Point2, Point3, Unit, Similarity3};
 
 use crate::{
     Scalar,
+    sampler::Sampler,
     material::{Material, self},
     geometry::{Ray, Aabb},
     colour
@@ -25,7 +24,7 @@ enum Scatter {
     Refract,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Intersection<T>
 where
     T: Scalar
@@ -35,6 +34,8 @@ where
     material: Material<T>,
     face: Face,
     incident: Ray<T>,
+    /// surface parameterisation at the hit, for texturing
+    uv: Point2<T>,
 }
 
 enum Refract {
@@ -46,7 +47,7 @@ enum Refract {
 fn refraction_type<T: Scalar>(incident: Unit<Vector3<T>>, normal: Unit<Vector3<T>>, refraction_ratio: T) -> Refract {
     let cos_theta = incident.dot(&normal);
     let sin_theta = (T::one() - cos_theta * cos_theta).sqrt();
-    if (refraction_ratio * sin_theta > T::one()) {
+    if refraction_ratio * sin_theta > T::one() {
         Refract::Reflect
     } else {
         Refract::Refract
@@ -59,7 +60,16 @@ where
 {
     pub fn new(point: Point3<T>, incident: Ray<T>, normal: Unit<Vector3<T>>, material: Material<T>) -> Self {
         let face = if normal.dot(&incident.orientation()) > T::zero() { Face::Back } else { Face::Front };
-        Intersection { point, normal, material, face, incident }
+        Intersection { point, normal, material, face, incident, uv: Point2::origin() }
+    }
+
+    pub fn with_uv(self, uv: Point2<T>) -> Self {
+        Self { uv, ..self }
+    }
+
+    /// the same hit with the surface facing the other way
+    pub fn inverted(self) -> Self {
+        Self::new(self.point, self.incident, -self.normal, self.material).with_uv(self.uv)
     }
 
     pub fn point(&self) -> Point3<T> {
@@ -78,6 +88,24 @@ where
         self.face
     }
 
+    pub fn uv(&self) -> Point2<T> {
+        self.uv
+    }
+
+    /// distance along the incident ray to this intersection
+    pub fn depth(&self) -> T {
+        (self.point - self.incident.origin()).norm()
+    }
+
+    /// maps an intersection found in an object's local frame back out through `transform`,
+    /// `incident` should be the original ray before it was moved into the local frame
+    pub fn transformed(self, transform: &Similarity3<T>, incident: Ray<T>) -> Self {
+        let point = transform.transform_point(&self.point);
+        // similarities scale uniformly so normals only need rotating
+        let normal = Unit::new_normalize(transform.isometry.rotation * self.normal.into_inner());
+        Self::new(point, incident, normal, self.material).with_uv(self.uv)
+    }
+
     // pub fn front_face(&self) -> bool {
     //     self.face == Face::Front
     // }
@@ -86,15 +114,13 @@ where
     //     self.face == Face::Back
     // }
   
-    /// select an orientation for this
-    fn scatter_type(&self) -> Scatter {
-        let roll_transmit = T::from_float(rand::thread_rng().gen());
-        if roll_transmit < self.material.transmissibility() {
+    /// select an orientation for this from a pair of rolls
+    fn scatter_type(&self, roll: Point2<T>) -> Scatter {
+        if roll.x < self.material.transmissibility() {
             return Scatter::Refract;
         };
 
-        let roll_reflect = T::from_float(rand::thread_rng().gen());
-        if roll_reflect < self.material.coherency() {
+        if roll.y < self.material.coherency() {
             Scatter::Specular
         } else {
             Scatter::Diffuse
@@ -102,8 +128,8 @@ where
     }
 
     /// return an orientation for a diffuse reflection from this intersection
-    fn lambertian_orientation(&self) -> Vector3<T> {
-        self.normal.into_inner() + random_spherical_unit().into_inner()
+    fn lambertian_orientation(&self, sample: Point2<T>) -> Vector3<T> {
+        self.normal.into_inner() + spherical_unit(sample).into_inner()
     }
 
     /// return an orientation for a specular reflection from this intersection
@@ -154,11 +180,17 @@ where
 
     }
 
-    // generate a scattered ray based on material properties
-    pub fn scatter(&self) -> Ray<T> {
+    // generate a scattered ray based on material properties, drawing its random decisions from `sampler`
+    pub fn scatter<S>(&self, sampler: &mut S) -> Ray<T>
+    where
+        S: Sampler<T> + ?Sized
+    {
+        // always draw both pairs so every bounce uses the same number of dimensions
+        let roll = sampler.next_2d();
+        let direction = sampler.next_2d();
         // we're assuming the normal is still normalised here
-        let (orientation, medium) = match self.scatter_type() {
-            Scatter::Diffuse => (self.lambertian_orientation(), self.incident.medium()),
+        let (orientation, medium) = match self.scatter_type(roll) {
+            Scatter::Diffuse => (self.lambertian_orientation(direction), self.incident.medium()),
             Scatter::Specular => (self.specular_orientation(), self.incident.medium()),
             Scatter::Refract => self.refracted_orientation(),
         };
@@ -170,7 +202,9 @@ where
         };
         let albedo = self.material.albedo() * albedo_scale;
         let attenuation = colour::white() - self.incident.gain().component_mul(&albedo);
-        Ray::from_orientation_attenuated(self.point, orientation, attenuation).with_medium(medium)
+        Ray::from_orientation_attenuated(self.point, orientation, attenuation)
+            .with_medium(medium)
+            .with_time(self.incident.time())
     }
 }
 
@@ -186,15 +220,13 @@ fn checkerboard_dark<T: Scalar>(point: Point3<T>) -> bool {
     (modulo(point.x, T::TWO) >= T::one()) ^ (modulo(point.z, T::TWO) >= T::one())
 }
 
-/// returns a random vector on uniformly distributed over the surface of the unit sphere
-/// generate normally distributed values for x, y, z and normalise
-fn random_spherical_unit<T: Scalar>() -> Unit<Vector3<T>> {
-    let mut rng = rand::thread_rng();
-    Unit::new_normalize(vector![
-        T::from_float(rng.sample(StandardNormal)),
-        T::from_float(rng.sample(StandardNormal)),
-        T::from_float(rng.sample(StandardNormal))
-    ])
+/// maps a point in the unit square to a point on the unit sphere, uniformly distributed
+/// over its surface. z is uniform in -1..1 (archimedes' hat box) and the angle around z uniform
+fn spherical_unit<T: Scalar>(sample: Point2<T>) -> Unit<Vector3<T>> {
+    let z = T::one() - sample.x * T::TWO;
+    let radius = (T::one() - z * z).max(T::zero()).sqrt();
+    let phi = T::two_pi() * sample.y;
+    Unit::new_unchecked(vector![radius * phi.cos(), radius * phi.sin(), z])
 }
 
 pub trait Intersectable<T>
@@ -205,4 +237,19 @@ where
     fn bounding_box(&self) -> Option<Aabb<T>>;
     // fn material(&self) -> Material<T>;
     // fn normal(&self, point: Point3<T>) -> Unit<Vector3<T>>;
-}
\ No newline at end of file
+}
+
+/// lets a scene be shared between several renders without cloning it
+impl<T, I> Intersectable<T> for &I
+where
+    T: Scalar,
+    I: Intersectable<T> + ?Sized,
+{
+    fn intersect(&self, ray: Ray<T>, min_depth: T, max_depth: T) -> Option<Intersection<T>> {
+        (**self).intersect(ray, min_depth, max_depth)
+    }
+
+    fn bounding_box(&self) -> Option<Aabb<T>> {
+        (**self).bounding_box()
+    }
+}
@@ -0,0 +1,225 @@
+use nalgebra::{Point3, Similarity3, Translation3};
+
+use crate::{Scalar, fingerprint::{Fingerprint, Fingerprinter}};
+
+/// values which can be blended between keyframes, `t` runs from 0 at `self` to 1 at `other`
+pub trait Interpolate<T>
+where
+    T: Scalar
+{
+    fn interpolate(&self, other: &Self, t: T) -> Self;
+}
+
+impl<T> Interpolate<T> for T
+where
+    T: Scalar
+{
+    fn interpolate(&self, other: &Self, t: T) -> Self {
+        *self + (*other - *self) * t
+    }
+}
+
+impl<T> Interpolate<T> for Point3<T>
+where
+    T: Scalar
+{
+    fn interpolate(&self, other: &Self, t: T) -> Self {
+        Point3::from(self.coords.lerp(&other.coords, t))
+    }
+}
+
+impl<T> Interpolate<T> for Similarity3<T>
+where
+    T: Scalar
+{
+    /// translation and scale are blended linearly and the rotation is slerped
+    fn interpolate(&self, other: &Self, t: T) -> Self {
+        let translation = self.isometry.translation.vector.lerp(&other.isometry.translation.vector, t);
+        let rotation = self.isometry.rotation.slerp(&other.isometry.rotation, t);
+        let scaling = self.scaling().interpolate(&other.scaling(), t);
+        Similarity3::from_parts(Translation3::from(translation), rotation, scaling)
+    }
+}
+
+/// how to blend between keys
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Interpolation {
+    /// straight lines between keys, with sharp changes of direction at each one
+    #[default]
+    Linear,
+    /// a smooth curve through every key, following the direction of the keys either side
+    CatmullRom,
+}
+
+/// a value animated over time, held constant before the first and after the last key
+#[derive(Debug, Clone)]
+pub struct Keyframes<T, V>
+where
+    T: Scalar
+{
+    frames: Vec<(T, V)>,
+}
+
+impl<T, V> Keyframes<T, V>
+where
+    T: Scalar,
+    V: Interpolate<T> + Clone,
+{
+    /// keys may be given in any order, panics if there are none
+    pub fn new(mut frames: Vec<(T, V)>) -> Self {
+        assert!(!frames.is_empty(), "an animation needs at least one keyframe");
+        frames.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap_or(core::cmp::Ordering::Equal));
+        Self { frames }
+    }
+
+    /// a straight line from `start` at time 0 to `end` at time 1
+    pub fn linear(start: V, end: V) -> Self {
+        Self::new(vec![(T::zero(), start), (T::one(), end)])
+    }
+
+    pub fn frames(&self) -> &[(T, V)] {
+        &self.frames
+    }
+
+    pub fn start_time(&self) -> T {
+        self.frames[0].0
+    }
+
+    pub fn end_time(&self) -> T {
+        self.frames[self.frames.len() - 1].0
+    }
+
+    /// index of the segment containing `time` and how far through it `time` is
+    pub fn segment(&self, time: T) -> (usize, T) {
+        let next = self.frames.partition_point(|(key, _)| *key <= time);
+        if next == 0 {
+            (0, T::zero())
+        } else if next == self.frames.len() {
+            (self.frames.len() - 1, T::zero())
+        } else {
+            let (start, end) = (self.frames[next - 1].0, self.frames[next].0);
+            (next - 1, (time - start) / (end - start))
+        }
+    }
+
+    /// the value at `time`, blended linearly between the surrounding keys
+    pub fn at(&self, time: T) -> V {
+        let (index, t) = self.segment(time);
+        match self.frames.get(index + 1) {
+            Some((_, next)) if t > T::zero() => self.frames[index].1.interpolate(next, t),
+            _ => self.frames[index].1.clone(),
+        }
+    }
+
+    /// the value at `time` using the given interpolation
+    pub fn at_with(&self, time: T, interpolation: Interpolation) -> V {
+        match interpolation {
+            Interpolation::Linear => self.at(time),
+            Interpolation::CatmullRom => self.catmull_rom(time),
+        }
+    }
+
+    /// the value at `time` on a catmull-rom spline through the keys, parameterised by the key
+    /// times so uneven spacing doesn't cause overshoots. built from repeated blends in the
+    /// style of Barry and Goldman so it only needs [`Interpolate`]
+    pub fn catmull_rom(&self, time: T) -> V {
+        let (index, t) = self.segment(time);
+        if index + 1 >= self.frames.len() || t <= T::zero() {
+            return self.frames[index].1.clone();
+        }
+        let (k1, p1) = (self.frames[index].0, &self.frames[index].1);
+        let (k2, p2) = (self.frames[index + 1].0, &self.frames[index + 1].1);
+        // past either end, reflect the neighbouring key to make a phantom one
+        let (k0, p0) = match index.checked_sub(1) {
+            Some(i) => (self.frames[i].0, self.frames[i].1.clone()),
+            None => (k1 - (k2 - k1), p2.interpolate(p1, T::TWO)),
+        };
+        let (k3, p3) = match self.frames.get(index + 2) {
+            Some((k, p)) => (*k, p.clone()),
+            None => (k2 + (k2 - k1), p1.interpolate(p2, T::TWO)),
+        };
+
+        let blend = |a: &V, b: &V, start: T, end: T| a.interpolate(b, (time - start) / (end - start));
+        let a1 = blend(&p0, p1, k0, k1);
+        let a2 = blend(p1, p2, k1, k2);
+        let a3 = blend(p2, &p3, k2, k3);
+        let b1 = blend(&a1, &a2, k0, k2);
+        let b2 = blend(&a2, &a3, k1, k3);
+        blend(&b1, &b2, k1, k2)
+    }
+}
+
+impl<T, V> Fingerprint for Keyframes<T, V>
+where
+    T: Scalar,
+    V: Fingerprint
+{
+    fn fingerprint(&self, state: &mut Fingerprinter) {
+        state.usize(self.frames.len());
+        for (time, value) in &self.frames {
+            state.scalar(*time);
+            state.add(value);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Arc;
+
+    use nalgebra::{point, vector, Vector3};
+
+    use super::*;
+    use crate::{geometry::{Blas, Geometry, Instance, Intersectable, Ray, Sphere, Surface}, Material};
+
+    #[test]
+    pub fn test_keyframes_hold_at_the_ends() {
+        let path = Keyframes::new(vec![(1.0, 10.0), (0.0, 0.0), (2.0, 0.0)]);
+        assert_eq!(path.at(-1.0), 0.0);
+        assert_eq!(path.at(0.5), 5.0);
+        assert_eq!(path.at(1.5), 5.0);
+        assert_eq!(path.at(3.0), 0.0);
+    }
+
+    #[test]
+    pub fn test_catmull_rom_passes_through_keys_smoothly() {
+        let path = Keyframes::new(vec![(0.0, 0.0_f64), (1.0, 1.0), (2.0, 0.0), (3.0, 1.0)]);
+        for (time, value) in path.frames().to_vec() {
+            assert!((path.catmull_rom(time) - value).abs() < 1e-12);
+        }
+        // a straight line stays straight
+        let line = Keyframes::new(vec![(0.0, 0.0_f64), (1.0, 2.0), (2.0, 4.0)]);
+        assert!((line.catmull_rom(0.25) - 0.5).abs() < 1e-12);
+        assert!((line.catmull_rom(1.5) - 3.0).abs() < 1e-12);
+        // a plateau between a rise and a fall bulges over the top
+        let hill = Keyframes::new(vec![(0.0, 0.0), (1.0, 1.0), (2.0, 1.0), (3.0, 0.0)]);
+        assert!(hill.catmull_rom(1.5) > 1.0);
+    }
+
+    #[test]
+    pub fn test_moving_geometry_is_hit_where_it_is_at_the_time() {
+        let path = Keyframes::linear(point![-1.0, 0.0, -2.0], point![1.0, 0.0, -2.0]);
+        let sphere = Sphere::new(Point3::origin(), 0.25, Material::default()).with_path(path);
+        let bounds = sphere.bounding_box().unwrap();
+        assert_eq!((bounds.min().x, bounds.max().x), (-1.25, 1.25));
+
+        let ray = Ray::from_orientation(point![1.0, 0.0, 0.0], vector![0.0, 0.0, -1.0]);
+        assert!(sphere.intersect(ray.with_time(0.0), 1e-5, f64::INFINITY).is_none());
+        assert!(sphere.intersect(ray.with_time(1.0), 1e-5, f64::INFINITY).is_some());
+        // normals and surface samples follow the sphere too
+        assert_eq!(sphere.normal(point![1.25, 0.0, -2.0], 1.0).into_inner(), Vector3::x());
+        let sample = sphere.sample_surface(0.3, 0.6, 0.5);
+        assert!(((sample.point - point![0.0, 0.0, -2.0]).norm() - 0.25).abs() < 1e-12);
+
+        let blas = Arc::new(Blas::new(vec![Geometry::Sphere(Sphere::new(Point3::origin(), 0.25, Material::default()))]));
+        let instance = Instance::new(blas, Similarity3::identity()).with_motion(Keyframes::linear(
+            Similarity3::new(vector![-1.0, 0.0, -2.0], Vector3::zeros(), 1.0),
+            Similarity3::new(vector![1.0, 0.0, -2.0], Vector3::zeros(), 1.0),
+        ));
+        assert!(instance.intersect(ray.with_time(0.0), 1e-5, f64::INFINITY).is_none());
+        assert!(instance.intersect(ray.with_time(1.0), 1e-5, f64::INFINITY).is_some());
+        assert_eq!(instance.transform_at(0.5).isometry.translation.vector, vector![0.0, 0.0, -2.0]);
+        let bounds = instance.bounding_box().unwrap();
+        assert!(bounds.min().x <= -1.25 && bounds.max().x >= 1.25);
+    }
+}
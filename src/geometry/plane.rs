@@ -3,7 +3,9 @@ use nalgebra::{Vector3, Point3, Unit, vector, point};
 use crate::{
     Scalar,
     Material,
+    fingerprint::{Fingerprint, Fingerprinter},
     geometry::{
+        csg::{Boundary, Solid, Span},
         Aabb,
         Intersectable,
         Intersection,
@@ -72,4 +74,37 @@ where
     fn bounding_box(&self) -> Option<Aabb<T>> {
         None
     }
-}
\ No newline at end of file
+}
+/// a plane bounds the half-space behind its normal
+impl<T> Solid<T> for Plane<T>
+where
+    T: Scalar
+{
+    fn spans(&self, ray: Ray<T>) -> Vec<Span<T>> {
+        let ray_dot_normal = ray.orientation().dot(&self.normal);
+        let height = (ray.origin() - self.origin).dot(&self.normal);
+        if ray_dot_normal == T::zero() {
+            return if height < T::zero() {
+                vec![Span { enter: Boundary::infinite(-T::INF), exit: Boundary::infinite(T::INF) }]
+            } else {
+                Vec::new()
+            };
+        }
+        let depth = -height / ray_dot_normal;
+        let surface = Boundary::new(depth, Intersection::new(ray.project(depth), ray, self.normal, self.material));
+        if ray_dot_normal < T::zero() {
+            vec![Span { enter: surface, exit: Boundary::infinite(T::INF) }]
+        } else {
+            vec![Span { enter: Boundary::infinite(-T::INF), exit: surface }]
+        }
+    }
+}
+
+impl<T> Fingerprint for Plane<T>
+where
+    T: Scalar
+{
+    fn fingerprint(&self, state: &mut Fingerprinter) {
+        state.add(&self.origin).add(&self.normal).add(&self.material);
+    }
+}
@@ -0,0 +1,160 @@
+use nalgebra::{Point2, Point3, Unit, Vector3};
+
+use crate::{
+    Scalar,
+    Material,
+    fingerprint::{Fingerprint, Fingerprinter},
+    geometry::{
+        aabb::FLAT_MARGIN,
+        Aabb,
+        Intersectable,
+        Intersection,
+        Ray,
+        ShapeError,
+        Surface,
+        SurfaceSample,
+    },
+};
+
+/// a parallelogram spanned by two edges from a corner
+#[derive(Debug, Clone)]
+pub struct Quad<T>
+where
+    T: Scalar
+{
+    origin: Point3<T>,
+    edge_u: Vector3<T>,
+    edge_v: Vector3<T>,
+    normal: Unit<Vector3<T>>,
+    /// scaled normal used to project hits back onto the edges
+    w: Vector3<T>,
+    material: Material<T>,
+}
+
+impl<T> Quad<T>
+where
+    T: Scalar
+{
+    /// the normal follows the right hand rule, `edge_u` cross `edge_v`. fails if the edges
+    /// are parallel or either is zero, which leaves no area to hit or normal to face along
+    pub fn new(origin: Point3<T>, edge_u: Vector3<T>, edge_v: Vector3<T>, material: Material<T>) -> Result<Self, ShapeError> {
+        let n = edge_u.cross(&edge_v);
+        let normal = Unit::try_new(n, T::zero()).ok_or(ShapeError::FlatQuad)?;
+        let w = n / n.norm_squared();
+        Ok(Self { origin, edge_u, edge_v, normal, w, material })
+    }
+
+    pub fn origin(&self) -> Point3<T> {
+        self.origin
+    }
+
+    pub fn normal(&self) -> Unit<Vector3<T>> {
+        self.normal
+    }
+
+    pub fn material(&self) -> Material<T> {
+        self.material
+    }
+
+    pub fn with_material(self, material: Material<T>) -> Self {
+        Self { material, ..self }
+    }
+
+    /// the point at fractions `u` and `v` along each edge
+    pub fn point(&self, u: T, v: T) -> Point3<T> {
+        self.origin + self.edge_u * u + self.edge_v * v
+    }
+}
+
+impl<T> Intersectable<T> for Quad<T>
+where
+    T: Scalar
+{
+    fn intersect(&self, ray: Ray<T>, min_depth: T, max_depth: T) -> Option<Intersection<T>> {
+        let ray_dot_normal = ray.orientation().dot(&self.normal);
+        if ray_dot_normal == T::zero() {
+            return None;
+        }
+        let depth = (self.origin - ray.origin()).dot(&self.normal) / ray_dot_normal;
+        if depth < min_depth || depth > max_depth {
+            return None;
+        }
+        let point = ray.project(depth);
+        let planar = point - self.origin;
+        let alpha = self.w.dot(&planar.cross(&self.edge_v));
+        let beta = self.w.dot(&self.edge_u.cross(&planar));
+        let unit = T::zero()..=T::one();
+        if unit.contains(&alpha) && unit.contains(&beta) {
+            Some(Intersection::new(point, ray, self.normal, self.material).with_uv(Point2::new(alpha, beta)))
+        } else {
+            None
+        }
+    }
+
+    fn bounding_box(&self) -> Option<Aabb<T>> {
+        Aabb::from_points([
+            self.point(T::zero(), T::zero()),
+            self.point(T::one(), T::zero()),
+            self.point(T::zero(), T::one()),
+            self.point(T::one(), T::one()),
+        ]).map(|bounds| bounds.inflate(T::from_float(FLAT_MARGIN)))
+    }
+}
+
+impl<T> Surface<T> for Quad<T>
+where
+    T: Scalar
+{
+    fn area(&self) -> T {
+        self.edge_u.cross(&self.edge_v).norm()
+    }
+
+    fn sample_surface(&self, u: T, v: T, _time: T) -> SurfaceSample<T> {
+        SurfaceSample { point: self.point(u, v), normal: self.normal }
+    }
+}
+
+impl<T> Fingerprint for Quad<T>
+where
+    T: Scalar
+{
+    /// the normal is worked out from the edges, so they're all that's needed
+    fn fingerprint(&self, state: &mut Fingerprinter) {
+        state.add(&self.origin).add(&self.edge_u).add(&self.edge_v).add(&self.material);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use nalgebra::{point, vector};
+
+    use super::*;
+
+    #[test]
+    pub fn test_quad_hits_inside_its_edges() {
+        let quad = Quad::<f64>::new(point![0.0, 0.0, -2.0], vector![2.0, 0.0, 0.0], vector![0.0, 1.0, 0.0], Material::default()).unwrap();
+        assert_eq!(quad.normal().into_inner(), vector![0.0, 0.0, 1.0]);
+        assert!((quad.area() - 2.0).abs() < 1e-12);
+
+        let ray = Ray::from_orientation(point![1.5, 0.25, 0.0], vector![0.0, 0.0, -1.0]);
+        let hit = quad.intersect(ray, 1e-5, f64::INFINITY).expect("ray through the middle should hit");
+        assert!((hit.depth() - 2.0).abs() < 1e-12);
+        assert!((hit.uv() - point![0.75, 0.25]).norm() < 1e-12);
+
+        // past either edge, or the far corner, it misses
+        for (x, y) in [(-0.1, 0.5), (2.1, 0.5), (1.0, 1.1), (2.1, 1.1)] {
+            let ray = Ray::from_orientation(point![x, y, 0.0], vector![0.0, 0.0, -1.0]);
+            assert!(quad.intersect(ray, 1e-5, f64::INFINITY).is_none(), "({x}, {y})");
+        }
+        let sample = quad.sample_surface(0.5, 0.5, 0.0);
+        assert_eq!(sample.point, point![1.0, 0.5, -2.0]);
+    }
+
+    #[test]
+    pub fn test_degenerate_quads_are_rejected() {
+        let parallel = Quad::<f64>::new(Point3::origin(), vector![1.0, 0.0, 0.0], vector![-2.0, 0.0, 0.0], Material::default());
+        assert_eq!(parallel.unwrap_err(), ShapeError::FlatQuad);
+        let zero = Quad::<f64>::new(Point3::origin(), vector![1.0, 0.0, 0.0], Vector3::zeros(), Material::default());
+        assert_eq!(zero.unwrap_err(), ShapeError::FlatQuad);
+    }
+}
@@ -1,5 +1,4 @@
-use nalgebra::{Vector3, Point3, Unit, vector};
-use tracing::{span, Level};
+use nalgebra::{Vector3, Point3, Unit, Similarity3};
 
 use crate::Scalar;
 use crate::colour;
@@ -15,7 +14,9 @@ where
     attenuation: Vector3<T>,
     /// refractive index of the current medium
     /// maybe convert to a struct later for volumetric calculations
-    medium: T
+    medium: T,
+    /// moment within the camera shutter interval that this ray was sent
+    time: T,
 }
 
 impl<T> Default for Ray<T>
@@ -27,7 +28,8 @@ where
             origin: Point3::origin(),
             orientation: -Vector3::z_axis(),
             attenuation: colour::black(),
-            medium: T::from_float(material::refractive_index::AIR)
+            medium: T::from_float(material::refractive_index::AIR),
+            time: T::zero(),
         }
     }
 }
@@ -38,9 +40,9 @@ where
 {
     pub fn from_orientation_attenuated(origin: Point3<T>, orientation: Vector3<T>, attenuation: Vector3<T>) -> Self {
         Self {
-            origin: origin,
+            origin,
             orientation: Unit::new_normalize(orientation),
-            attenuation: attenuation,
+            attenuation,
             ..Default::default()
         }
     }
@@ -97,6 +99,23 @@ where
         Self {medium, ..self}
     }
 
+    pub fn time(&self) -> T {
+        self.time
+    }
+
+    pub fn with_time(self, time: T) -> Self {
+        Self { time, ..self }
+    }
+
+    /// moves the ray into the frame described by `transform`, preserving its gain, medium and time
+    pub fn transformed(&self, transform: &Similarity3<T>) -> Self {
+        Self {
+            origin: transform.transform_point(&self.origin),
+            orientation: Unit::new_normalize(transform.transform_vector(&self.orientation)),
+            ..*self
+        }
+    }
+
     pub fn x(&self) -> T {
         self.orientation.x
     }
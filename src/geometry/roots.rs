@@ -0,0 +1,228 @@
+//! polynomial root finding for the analytic primitives
+//!
+//! everything is solved in f64 regardless of the scalar type, the quartic in
+//! particular loses far too much precision in f32 to be usable for a torus.
+
+use crate::Scalar;
+
+/// coefficients closer to zero than this are treated as exactly zero
+const EPSILON: f64 = 1e-12;
+
+/// up to four real roots in ascending order, small enough to live on the stack
+#[derive(Debug, Clone, Copy)]
+pub struct Roots<T>
+where
+    T: Scalar
+{
+    values: [T; 4],
+    len: usize,
+}
+
+impl<T> Default for Roots<T>
+where
+    T: Scalar
+{
+    fn default() -> Self {
+        Self { values: [T::zero(); 4], len: 0 }
+    }
+}
+
+impl<T> Roots<T>
+where
+    T: Scalar
+{
+    /// adds a root, keeping the set sorted. roots past the fourth are dropped
+    pub fn push(&mut self, root: T) {
+        if self.len == self.values.len() {
+            return;
+        }
+        let mut i = self.len;
+        while i > 0 && self.values[i - 1] > root {
+            self.values[i] = self.values[i - 1];
+            i -= 1;
+        }
+        self.values[i] = root;
+        self.len += 1;
+    }
+
+    pub fn as_slice(&self) -> &[T] {
+        &self.values[..self.len]
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// the smallest root within [min, max]
+    pub fn first_within(&self, min: T, max: T) -> Option<T> {
+        self.as_slice().iter().copied().find(|root| *root >= min && *root <= max)
+    }
+}
+
+impl<T> FromIterator<T> for Roots<T>
+where
+    T: Scalar
+{
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut roots = Self::default();
+        iter.into_iter().for_each(|root| roots.push(root));
+        roots
+    }
+}
+
+fn is_zero(x: f64) -> bool {
+    x.abs() < EPSILON
+}
+
+/// real roots of `a x^2 + b x + c`, avoiding the cancellation of the textbook formula
+pub fn solve_quadratic<T: Scalar>(a: T, b: T, c: T) -> Roots<T> {
+    quadratic(a.to_float(), b.to_float(), c.to_float())
+        .as_slice()
+        .iter()
+        .map(|root| T::from_float(*root))
+        .collect()
+}
+
+fn quadratic(a: f64, b: f64, c: f64) -> Roots<f64> {
+    if is_zero(a) {
+        return if is_zero(b) { Roots::default() } else { [-c / b].into_iter().collect() };
+    }
+    let discriminant = b * b - 4.0 * a * c;
+    if discriminant < 0.0 {
+        return Roots::default();
+    }
+    // q shares the sign of b so the two terms never cancel
+    let q = -0.5 * (b + b.signum() * discriminant.sqrt());
+    if is_zero(q) {
+        [0.0].into_iter().collect()
+    } else {
+        [q / a, c / q].into_iter().collect()
+    }
+}
+
+/// real roots of `x^3 + a x^2 + b x + c`, after Schwarze in Graphics Gems I
+fn normalised_cubic(a: f64, b: f64, c: f64) -> Roots<f64> {
+    // substitute x = y - a/3 to remove the quadratic term
+    let sq_a = a * a;
+    let p = (-sq_a / 3.0 + b) / 3.0;
+    let q = (2.0 / 27.0 * a * sq_a - a * b / 3.0 + c) / 2.0;
+    let cb_p = p * p * p;
+    let discriminant = q * q + cb_p;
+
+    let roots: Roots<f64> = if is_zero(discriminant) {
+        if is_zero(q) {
+            [0.0].into_iter().collect()
+        } else {
+            let u = (-q).cbrt();
+            [2.0 * u, -u].into_iter().collect()
+        }
+    } else if discriminant < 0.0 {
+        // three real roots, use the trigonometric form
+        let phi = (-q / (-cb_p).sqrt()).clamp(-1.0, 1.0).acos() / 3.0;
+        let t = 2.0 * (-p).sqrt();
+        let third = std::f64::consts::FRAC_PI_3;
+        [t * phi.cos(), -t * (phi + third).cos(), -t * (phi - third).cos()].into_iter().collect()
+    } else {
+        let root = discriminant.sqrt();
+        [(root - q).cbrt() - (root + q).cbrt()].into_iter().collect()
+    };
+
+    roots.as_slice().iter().map(|y| y - a / 3.0).collect()
+}
+
+/// real roots of `x^4 + a x^3 + b x^2 + c x + d`, after Schwarze in Graphics Gems I
+fn normalised_quartic(a: f64, b: f64, c: f64, d: f64) -> Roots<f64> {
+    // substitute x = y - a/4 to remove the cubic term
+    let sq_a = a * a;
+    let p = -3.0 / 8.0 * sq_a + b;
+    let q = sq_a * a / 8.0 - a * b / 2.0 + c;
+    let r = -3.0 / 256.0 * sq_a * sq_a + sq_a * b / 16.0 - a * c / 4.0 + d;
+
+    let roots = if is_zero(r) {
+        // no absolute term, y(y^3 + py + q) = 0
+        let mut roots = normalised_cubic(0.0, p, q);
+        roots.push(0.0);
+        roots
+    } else {
+        // take one root of the resolvent cubic and factor into two quadratics
+        let z = normalised_cubic(-p / 2.0, -r, r * p / 2.0 - q * q / 8.0).as_slice()[0];
+        let u = z * z - r;
+        let v = 2.0 * z - p;
+        if u < -EPSILON || v < -EPSILON {
+            return Roots::default();
+        }
+        let u = u.max(0.0).sqrt();
+        let v = if q < 0.0 { -v.max(0.0).sqrt() } else { v.max(0.0).sqrt() };
+        let mut roots = quadratic(1.0, v, z - u);
+        quadratic(1.0, -v, z + u).as_slice().iter().for_each(|root| roots.push(*root));
+        roots
+    };
+
+    roots.as_slice().iter().map(|y| y - a / 4.0).collect()
+}
+
+/// real roots of `c4 x^4 + c3 x^3 + c2 x^2 + c1 x + c0`, polished with a couple of
+/// newton iterations since the closed form can be off by a surprising amount
+pub fn solve_quartic<T: Scalar>(c4: T, c3: T, c2: T, c1: T, c0: T) -> Roots<T> {
+    let [c4, c3, c2, c1, c0] = [c4, c3, c2, c1, c0].map(|c| c.to_float());
+    if is_zero(c4) {
+        return normalised_cubic_or_lower(c3, c2, c1, c0).as_slice().iter().map(|root| T::from_float(*root)).collect();
+    }
+    let evaluate = |x: f64| (((c4 * x + c3) * x + c2) * x + c1) * x + c0;
+    let derivative = |x: f64| ((4.0 * c4 * x + 3.0 * c3) * x + 2.0 * c2) * x + c1;
+    normalised_quartic(c3 / c4, c2 / c4, c1 / c4, c0 / c4)
+        .as_slice()
+        .iter()
+        .map(|root| {
+            let mut x = *root;
+            for _ in 0..2 {
+                let slope = derivative(x);
+                if slope != 0.0 {
+                    x -= evaluate(x) / slope;
+                }
+            }
+            T::from_float(x)
+        })
+        .collect()
+}
+
+fn normalised_cubic_or_lower(c3: f64, c2: f64, c1: f64, c0: f64) -> Roots<f64> {
+    if is_zero(c3) {
+        quadratic(c2, c1, c0)
+    } else {
+        normalised_cubic(c2 / c3, c1 / c3, c0 / c3)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    pub fn test_quadratic_is_stable_for_tiny_roots() {
+        // roots 1e-8 and 1e8, the naive formula returns 0 for the small one
+        let roots = solve_quadratic(1.0_f64, -(1e8 + 1e-8), 1.0);
+        assert_eq!(roots.len(), 2);
+        assert!((roots.as_slice()[0] - 1e-8).abs() < 1e-20);
+        assert!((roots.as_slice()[1] - 1e8).abs() < 1e-6);
+    }
+
+    #[test]
+    pub fn test_quartic_finds_all_real_roots() {
+        // (x - 1)(x - 2)(x - 3)(x - 4)
+        let roots = solve_quartic(1.0_f64, -10.0, 35.0, -50.0, 24.0);
+        assert_eq!(roots.len(), 4);
+        for (root, expected) in roots.as_slice().iter().zip([1.0, 2.0, 3.0, 4.0]) {
+            assert!((root - expected).abs() < 1e-9, "{root} != {expected}");
+        }
+        // (x^2 + 1)(x - 2)(x + 3) only has two real roots
+        let roots = solve_quartic(1.0_f64, 1.0, -5.0, 1.0, -6.0);
+        assert_eq!(roots.len(), 2);
+        assert!((roots.as_slice()[0] + 3.0).abs() < 1e-9);
+        assert!((roots.as_slice()[1] - 2.0).abs() < 1e-9);
+    }
+}
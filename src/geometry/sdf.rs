@@ -0,0 +1,426 @@
+use nalgebra::{point, vector, Point3, Unit, Vector3};
+
+use crate::{
+    Scalar,
+    Material,
+    fingerprint::{Fingerprint, Fingerprinter},
+    geometry::{Aabb, Intersectable, Intersection, Ray},
+};
+
+const MAX_STEPS: usize = 256;
+const EPSILON: f64 = 1e-4;
+
+/// a tree of distance functions, the leaves are shapes centred on the origin and the
+/// branches move or combine them
+#[derive(Debug, Clone)]
+pub enum SdfShape<T>
+where
+    T: Scalar
+{
+    Sphere { radius: T },
+    Box { half_extents: Vector3<T> },
+    /// a ring lying flat around the y axis
+    Torus { major_radius: T, minor_radius: T },
+    /// the power 8 bulb by default, `iterations` trades detail for speed
+    Mandelbulb { power: T, iterations: usize },
+    Translate(Box<SdfShape<T>>, Vector3<T>),
+    Union(Box<SdfShape<T>>, Box<SdfShape<T>>),
+    /// union with the seam blended over roughly `k` units
+    SmoothUnion(Box<SdfShape<T>>, Box<SdfShape<T>>, T),
+    /// rotates slices of the shape around the y axis by `k` radians per unit of height
+    Twist(Box<SdfShape<T>>, T),
+    /// tiles space with copies of the shape, a period of zero leaves that axis alone
+    Repeat(Box<SdfShape<T>>, Vector3<T>),
+}
+
+impl<T> SdfShape<T>
+where
+    T: Scalar
+{
+    pub fn sphere(radius: T) -> Self {
+        SdfShape::Sphere { radius }
+    }
+
+    pub fn cuboid(half_extents: Vector3<T>) -> Self {
+        SdfShape::Box { half_extents }
+    }
+
+    pub fn torus(major_radius: T, minor_radius: T) -> Self {
+        SdfShape::Torus { major_radius, minor_radius }
+    }
+
+    pub fn mandelbulb() -> Self {
+        SdfShape::Mandelbulb { power: T::from_float(8.0), iterations: 12 }
+    }
+
+    pub fn translate(self, offset: Vector3<T>) -> Self {
+        SdfShape::Translate(Box::new(self), offset)
+    }
+
+    pub fn union(self, other: Self) -> Self {
+        SdfShape::Union(Box::new(self), Box::new(other))
+    }
+
+    pub fn smooth_union(self, other: Self, k: T) -> Self {
+        SdfShape::SmoothUnion(Box::new(self), Box::new(other), k)
+    }
+
+    pub fn twist(self, k: T) -> Self {
+        SdfShape::Twist(Box::new(self), k)
+    }
+
+    pub fn repeat(self, period: Vector3<T>) -> Self {
+        SdfShape::Repeat(Box::new(self), period)
+    }
+
+    /// distance from `p` to the surface, negative inside. combinators other than
+    /// translate and union only give a bound on the distance, not the exact value
+    pub fn distance(&self, p: Point3<T>) -> T {
+        match self {
+            SdfShape::Sphere { radius } => p.coords.norm() - *radius,
+            SdfShape::Box { half_extents } => {
+                let q = p.coords.abs() - half_extents;
+                q.sup(&Vector3::zeros()).norm() + q.max().min(T::zero())
+            },
+            SdfShape::Torus { major_radius, minor_radius } => {
+                let ring = vector![p.x, p.z].norm() - *major_radius;
+                vector![ring, p.y].norm() - *minor_radius
+            },
+            SdfShape::Mandelbulb { power, iterations } => mandelbulb(p, *power, *iterations),
+            SdfShape::Translate(shape, offset) => shape.distance(p - offset),
+            SdfShape::Union(a, b) => a.distance(p).min(b.distance(p)),
+            SdfShape::SmoothUnion(a, b, k) => {
+                // polynomial smooth minimum
+                let (da, db) = (a.distance(p), b.distance(p));
+                let h = (T::HALF + T::HALF * (db - da) / *k).clamp(T::zero(), T::one());
+                db + (da - db) * h - *k * h * (T::one() - h)
+            },
+            SdfShape::Twist(shape, k) => {
+                let (sin, cos) = (*k * p.y).sin_cos();
+                shape.distance(point![cos * p.x - sin * p.z, p.y, sin * p.x + cos * p.z])
+            },
+            SdfShape::Repeat(shape, period) => {
+                let wrapped = p.coords.zip_map(period, |x, c| {
+                    if c > T::zero() { x - c * (x / c).round() } else { x }
+                });
+                shape.distance(Point3::from(wrapped))
+            },
+        }
+    }
+
+    /// a box around the shape, or None if it goes on forever
+    pub fn bounds(&self) -> Option<Aabb<T>> {
+        match self {
+            SdfShape::Sphere { radius } => Some(cube(*radius)),
+            SdfShape::Box { half_extents } => Some(Aabb::new(Point3::from(-half_extents), Point3::from(*half_extents))),
+            SdfShape::Torus { major_radius, minor_radius } => {
+                let (outer, tube) = (*major_radius + *minor_radius, *minor_radius);
+                Some(Aabb::new(point![-outer, -tube, -outer], point![outer, tube, outer]))
+            },
+            // the whole set sits inside a radius of about 1.2 for the usual powers
+            SdfShape::Mandelbulb { .. } => Some(cube(T::from_float(1.25))),
+            SdfShape::Translate(shape, offset) => shape
+                .bounds()
+                .map(|b| Aabb::new(b.min() + offset, b.max() + offset)),
+            SdfShape::Union(a, b) => Some(a.bounds()?.union(&b.bounds()?)),
+            SdfShape::SmoothUnion(a, b, k) => Some(a.bounds()?.union(&b.bounds()?).inflate(*k)),
+            SdfShape::Twist(shape, _) => {
+                // any point can be swung round to any angle, so bound the swept cylinder
+                let b = shape.bounds()?;
+                let reach = b.corners()
+                    .iter()
+                    .map(|c| vector![c.x, c.z].norm())
+                    .fold(T::zero(), |acc, r| acc.max(r));
+                Some(Aabb::new(point![-reach, b.min().y, -reach], point![reach, b.max().y, reach]))
+            },
+            SdfShape::Repeat(..) => None,
+        }
+    }
+}
+
+fn cube<T: Scalar>(half_width: T) -> Aabb<T> {
+    let corner = Point3::from(Vector3::repeat(half_width));
+    Aabb::new(-corner, corner)
+}
+
+/// distance estimate for the mandelbulb fractal, see Hart's "Ray Tracing Deterministic 3-D Fractals"
+fn mandelbulb<T: Scalar>(p: Point3<T>, power: T, iterations: usize) -> T {
+    let mut z = p.coords;
+    let mut dr = T::one();
+    let mut r = z.norm();
+    for _ in 0..iterations {
+        r = z.norm();
+        if r > T::TWO || r == T::zero() {
+            break;
+        }
+        // raise z to the power in spherical coordinates
+        let theta = (z.z / r).acos() * power;
+        let phi = z.y.atan2(z.x) * power;
+        dr = r.powf(power - T::one()) * power * dr + T::one();
+        let zr = r.powf(power);
+        z = vector![theta.sin() * phi.cos(), theta.sin() * phi.sin(), theta.cos()] * zr + p.coords;
+    }
+    if r == T::zero() {
+        // the origin is deep inside the bulb
+        return -T::one();
+    }
+    T::HALF * r.ln() * r / dr
+}
+
+/// geometry defined implicitly by a distance function and found by sphere tracing
+#[derive(Debug, Clone)]
+pub struct Sdf<T>
+where
+    T: Scalar
+{
+    shape: SdfShape<T>,
+    material: Material<T>,
+    bounds: Option<Aabb<T>>,
+    /// fraction of the estimated distance to actually step, below one for shapes
+    /// whose distances are only loose estimates such as heavy twists
+    step_scale: T,
+    max_steps: usize,
+    epsilon: T,
+}
+
+impl<T> Sdf<T>
+where
+    T: Scalar
+{
+    pub fn new(shape: SdfShape<T>, material: Material<T>) -> Self {
+        let bounds = shape.bounds();
+        Self {
+            shape,
+            material,
+            bounds,
+            step_scale: T::one(),
+            max_steps: MAX_STEPS,
+            epsilon: T::from_float(EPSILON),
+        }
+    }
+
+    /// overrides the computed bounds, needed to render repeated shapes efficiently
+    pub fn with_bounds(self, bounds: Aabb<T>) -> Self {
+        Self { bounds: Some(bounds), ..self }
+    }
+
+    pub fn with_step_scale(self, step_scale: T) -> Self {
+        Self { step_scale, ..self }
+    }
+
+    pub fn with_max_steps(self, max_steps: usize) -> Self {
+        Self { max_steps, ..self }
+    }
+
+    pub fn with_epsilon(self, epsilon: T) -> Self {
+        Self { epsilon, ..self }
+    }
+
+    pub fn shape(&self) -> &SdfShape<T> {
+        &self.shape
+    }
+
+    pub fn material(&self) -> Material<T> {
+        self.material
+    }
+
+    /// gradient of the distance field by central differences
+    pub fn normal(&self, p: Point3<T>) -> Unit<Vector3<T>> {
+        let h = self.epsilon;
+        let gradient = Vector3::from_fn(|axis, _| {
+            let mut offset = Vector3::zeros();
+            offset[axis] = h;
+            self.shape.distance(p + offset) - self.shape.distance(p - offset)
+        });
+        Unit::new_normalize(gradient)
+    }
+}
+
+impl<T> Intersectable<T> for Sdf<T>
+where
+    T: Scalar
+{
+    fn intersect(&self, ray: Ray<T>, min_depth: T, max_depth: T) -> Option<Intersection<T>> {
+        let (start, end) = match &self.bounds {
+            Some(bounds) => bounds.clip(ray, min_depth, max_depth)?,
+            None => (min_depth, max_depth),
+        };
+        let start = start.max(min_depth);
+
+        let mut depth = start;
+        // rays leaving the surface after a bounce start right on it, so ignore hits until
+        // the march has got clear of wherever it started
+        let mut clear = self.shape.distance(ray.project(min_depth)).abs() >= self.epsilon;
+        for _ in 0..self.max_steps {
+            if depth > end {
+                return None;
+            }
+            let point = ray.project(depth);
+            let distance = self.shape.distance(point).abs();
+            if distance < self.epsilon {
+                if clear {
+                    return Some(Intersection::new(point, ray, self.normal(point), self.material));
+                }
+                depth += self.epsilon;
+            } else {
+                clear = true;
+                depth += distance * self.step_scale;
+            }
+        }
+        None
+    }
+
+    fn bounding_box(&self) -> Option<Aabb<T>> {
+        self.bounds
+    }
+}
+
+impl<T> Fingerprint for SdfShape<T>
+where
+    T: Scalar
+{
+    fn fingerprint(&self, state: &mut Fingerprinter) {
+        match self {
+            SdfShape::Sphere { radius } => {
+                state.usize(0);
+                state.scalar(*radius);
+            },
+            SdfShape::Box { half_extents } => {
+                state.usize(1);
+                state.add(half_extents);
+            },
+            SdfShape::Torus { major_radius, minor_radius } => {
+                state.usize(2);
+                state.scalar(*major_radius);
+                state.scalar(*minor_radius);
+            },
+            SdfShape::Mandelbulb { power, iterations } => {
+                state.usize(3);
+                state.scalar(*power);
+                state.usize(*iterations);
+            },
+            SdfShape::Translate(shape, offset) => {
+                state.usize(4);
+                state.add(shape).add(offset);
+            },
+            SdfShape::Union(a, b) => {
+                state.usize(5);
+                state.add(a).add(b);
+            },
+            SdfShape::SmoothUnion(a, b, k) => {
+                state.usize(6);
+                state.add(a).add(b);
+                state.scalar(*k);
+            },
+            SdfShape::Twist(shape, k) => {
+                state.usize(7);
+                state.add(shape);
+                state.scalar(*k);
+            },
+            SdfShape::Repeat(shape, period) => {
+                state.usize(8);
+                state.add(shape).add(period);
+            },
+        }
+    }
+}
+
+impl<T> Fingerprint for Sdf<T>
+where
+    T: Scalar
+{
+    fn fingerprint(&self, state: &mut Fingerprinter) {
+        state.add(&self.shape).add(&self.material).add(&self.bounds);
+        state.scalar(self.step_scale);
+        state.usize(self.max_steps);
+        state.scalar(self.epsilon);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    pub fn test_sphere_tracing_matches_analytic_sphere() {
+        let sdf = Sdf::new(SdfShape::sphere(0.5).translate(vector![0.0, 0.0, -2.0]), Material::default());
+        let ray = Ray::from_orientation(Point3::origin(), vector![0.0, 0.0, -1.0]);
+        let hit = sdf.intersect(ray, 1e-5, f64::INFINITY).expect("should hit the sphere");
+        assert!((hit.depth() - 1.5).abs() < 1e-3);
+        assert!((hit.normal().into_inner() - Vector3::z()).norm() < 1e-3);
+
+        // a ray leaving the surface shouldn't immediately hit it again
+        let bounce = Ray::from_orientation(hit.point(), vector![0.0, 1.0, 1.0]);
+        assert!(sdf.intersect(bounce, 1e-5, f64::INFINITY).is_none());
+    }
+
+    #[test]
+    pub fn test_smooth_union_fills_the_gap() {
+        let a = SdfShape::sphere(0.5).translate(vector![-0.55, 0.0, 0.0]);
+        let b = SdfShape::sphere(0.5).translate(vector![0.55, 0.0, 0.0]);
+        let point = Point3::origin();
+        assert!(a.clone().union(b.clone()).distance(point) > 0.0);
+        assert!(a.smooth_union(b, 0.3).distance(point) < 0.0);
+    }
+
+    fn contains(bounds: &Aabb<f64>, p: Point3<f64>) -> bool {
+        (0..3).all(|axis| bounds.min()[axis] - 1e-9 <= p[axis] && p[axis] <= bounds.max()[axis] + 1e-9)
+    }
+
+    #[test]
+    pub fn test_twist_turns_each_slice_by_its_height() {
+        let slab = SdfShape::cuboid(vector![1.0, 2.0, 0.2]);
+        let k = std::f64::consts::FRAC_PI_4;
+        let twisted = slab.clone().twist(k);
+        let bounds = twisted.bounds().unwrap();
+        // points on the untwisted slab, turned back by the angle at their height
+        let faces = [point![1.0, 0.0, 0.0], point![1.0, 1.0, 0.1], point![-0.5, -1.5, 0.2], point![0.3, 2.0, -0.1], point![1.0, -2.0, 0.2]];
+        for q in faces {
+            assert!(slab.distance(q).abs() < 1e-12);
+            let (sin, cos) = (k * q.y).sin_cos();
+            let p = point![cos * q.x + sin * q.z, q.y, cos * q.z - sin * q.x];
+            assert!(twisted.distance(p).abs() < 1e-12, "{p:?}");
+            assert!(contains(&bounds, p), "{p:?} is outside {bounds:?}");
+        }
+        // a quarter turn two units up swaps the long and short sides
+        assert!((twisted.distance(point![0.0, 2.0, 1.0])).abs() < 1e-12);
+        assert!(twisted.distance(point![1.0, 2.0, 0.0]) > 0.5);
+    }
+
+    #[test]
+    pub fn test_repeat_tiles_copies_along_each_periodic_axis() {
+        let spheres = SdfShape::<f64>::sphere(0.25).repeat(vector![1.0, 0.0, 2.0]);
+        assert!(spheres.bounds().is_none());
+        // a point on a copy three along x and one back along z
+        assert!(spheres.distance(point![3.25, 0.0, -2.0]).abs() < 1e-12);
+        assert!(spheres.distance(point![-4.0, 0.0, 4.25]).abs() < 1e-12);
+        // halfway between copies is as far as it gets
+        assert!((spheres.distance(point![3.5, 0.0, 0.0]) - 0.25).abs() < 1e-12);
+        // y has no period, so there's only the one row
+        assert!((spheres.distance(point![2.0, 1.25, 0.0]) - 1.0).abs() < 1e-12);
+    }
+
+    #[test]
+    pub fn test_mandelbulb_is_traced_inside_its_bounds() {
+        let bulb = SdfShape::<f64>::mandelbulb();
+        let bounds = bulb.bounds().unwrap();
+        // nothing of the set reaches the faces of its bounds
+        for i in 0..9 {
+            for j in 0..9 {
+                let (a, b) = (-1.25 + 2.5 * i as f64 / 8.0, -1.25 + 2.5 * j as f64 / 8.0);
+                for p in [point![1.25, a, b], point![a, -1.25, b], point![a, b, 1.25]] {
+                    assert!(bulb.distance(p) > 0.0, "{p:?} is inside the bulb");
+                }
+            }
+        }
+
+        let sdf = Sdf::new(bulb.clone(), Material::default()).with_step_scale(0.5);
+        let directions = [vector![0.0, 0.0, -1.0], vector![-1.0, 0.0, 0.0], vector![0.0, -1.0, 0.0], vector![-1.0, -1.0, -1.0]];
+        for direction in directions {
+            let ray = Ray::from_orientation(Point3::origin() - direction.normalize() * 3.0, direction);
+            let hit = sdf.intersect(ray, 1e-5, f64::INFINITY).expect("a ray through the middle should hit the bulb");
+            assert!(contains(&bounds, hit.point()), "{:?} is outside {bounds:?}", hit.point());
+            assert!(bulb.distance(hit.point()).abs() < 1e-3);
+        }
+    }
+}
@@ -1,6 +1,6 @@
 use std::option::Option;
 
-use nalgebra::{Vector3, Point3, Unit, point};
+use nalgebra::{Vector3, Point2, Point3, Unit, point, vector};
 
 use crate::{
     colour,
@@ -8,10 +8,16 @@ use crate::{
         Aabb,
         Intersectable,
         Intersection,
-        Ray
+        Ray,
+        csg::{pair_spans, Solid, Span},
+        motion::Keyframes,
+        roots::solve_quadratic,
+        Surface,
+        SurfaceSample,
     },
     Material,
     Scalar,
+    fingerprint::{Fingerprint, Fingerprinter},
 };
 
 #[derive(Debug, Clone)]
@@ -22,6 +28,8 @@ where
     center: Point3<T>,
     radius: T,
     material: Material<T>,
+    /// overrides `center` for spheres which move while the shutter is open
+    path: Option<Keyframes<T, Point3<T>>>,
 }
 
 impl<T> Default for Sphere<T>
@@ -33,6 +41,7 @@ where
             center: point![T::zero(), T::zero(), -T::one()],
             radius: T::from_float(0.5),
             material: Material::simple_diffuse_colour(colour::red()),
+            path: None,
         }
     }
 }
@@ -42,20 +51,47 @@ where
     T: Scalar
 {
     pub fn new(center: Point3<T>, radius: T, material: Material<T>) -> Self {
-        Self { center, radius, material }
+        Self { center, radius, material, path: None }
+    }
+
+    /// animates the center along `path`, the original center is ignored from then on
+    pub fn with_path(self, path: Keyframes<T, Point3<T>>) -> Self {
+        Self { path: Some(path), ..self }
+    }
+
+    pub fn center_at(&self, time: T) -> Point3<T> {
+        match &self.path {
+            Some(path) => path.at(time),
+            None => self.center,
+        }
     }
 
     pub fn material(&self) -> Material<T> {
         self.material
     }
 
-    pub fn normal(&self, point: Point3<T>) -> Unit<Vector3<T>> {
-        Unit::new_normalize(point - self.center)
+    /// the outward normal through `point` on the sphere as it is at `time`
+    pub fn normal(&self, point: Point3<T>, time: T) -> Unit<Vector3<T>> {
+        Unit::new_normalize(point - self.center_at(time))
     }
 
     pub fn with_material(self, material: Material<T>) -> Self {
         Self { material, ..Default::default() }
     }
+
+    /// the intersection `depth` along `ray`
+    fn hit(&self, ray: Ray<T>, depth: T) -> Intersection<T> {
+        let point = ray.project(depth);
+        let normal = self.normal(point, ray.time());
+        Intersection::new(point, ray, normal, self.material()).with_uv(Self::uv(&normal))
+    }
+
+    /// longitude and latitude of the point on the sphere in the direction of `normal`, both in [0, 1]
+    pub fn uv(normal: &Unit<Vector3<T>>) -> Point2<T> {
+        let theta = (-normal.y).acos();
+        let phi = (-normal.z).atan2(normal.x) + T::pi();
+        Point2::new(phi / T::two_pi(), theta / T::pi())
+    }
 }
 
 impl<T> Intersectable<T> for Sphere<T>
@@ -65,7 +101,7 @@ where
     #[cfg(not(feature = "optimised_intersection"))]
     fn intersect(&self, ray: Ray<T>, min_depth: T, max_depth: T) -> Option<Intersection<T>> {
         // trace!("interesecting ray {ray:?} with sphere {self:?}");
-        let origin_to_center = ray.origin() - self.center;
+        let origin_to_center = ray.origin() - self.center_at(ray.time());
         // quadratic method
         let a = ray.orientation().dot(&ray.orientation());
         let b = T::from_float(2.0) * origin_to_center.dot(&ray.orientation());
@@ -81,11 +117,7 @@ where
             let root_high = (-b + descriminant_root) / divisor;
             let root = if root_low > T::zero() {root_low} else {root_high};
             if root >= min_depth && root <= max_depth {   
-                let point = ray.project(root);
-                // let incident = ray.orientation();
-                let normal = self.normal(point);
-                let material = self.material();
-                Some(Intersection::new(point, ray, normal, material))
+                Some(self.hit(ray, root))
             } else {
                 None
             }
@@ -99,7 +131,7 @@ where
         // TODO: Fix this stupid shit
         // debug!("Optimised Intersection");
         // simplified using the known constant factor in b and that the ray is unit length
-        let origin_to_center = ray.origin - self.center;
+        let origin_to_center = ray.origin - self.center_at(ray.time());
         let half_b = origin_to_center.dot(&ray.orientation); //common factors cancel out
         let c = origin_to_center.norm() - self.radius * self.radius; //self dot is equivalent to norm/length
         let descriminant = half_b*half_b - c;
@@ -109,14 +141,63 @@ where
             let root = -(half_b + descriminant.sqrt());
             let point = ray.project(root);
             let incident = ray.orientation;
-            let normal = self.normal(point);
+            let normal = self.normal(point, ray.time());
             let material = self.material();
             Some(Intersection::new(point, incident, normal, material))
         }
     }
 
     fn bounding_box(&self) -> Option<Aabb<T>> {
-        None
+        // linear motion between keys stays inside the hull of the key positions
+        let radius = Vector3::repeat(self.radius);
+        let centers = match &self.path {
+            Some(path) => path.frames().iter().map(|(_, center)| *center).collect(),
+            None => vec![self.center],
+        };
+        Aabb::from_points(centers.iter().flat_map(|c| [c - radius, c + radius]))
+    }
+}
+
+impl<T> Solid<T> for Sphere<T>
+where
+    T: Scalar
+{
+    fn spans(&self, ray: Ray<T>) -> Vec<Span<T>> {
+        let origin_to_center = ray.origin() - self.center_at(ray.time());
+        let roots = solve_quadratic(
+            T::one(),
+            origin_to_center.dot(&ray.orientation()) * T::TWO,
+            origin_to_center.norm_squared() - self.radius * self.radius,
+        );
+        pair_spans(roots.as_slice(), |depth| self.hit(ray, depth))
+    }
+}
+
+impl<T> Surface<T> for Sphere<T>
+where
+    T: Scalar
+{
+    fn area(&self) -> T {
+        T::from_float(4.0) * T::pi() * self.radius * self.radius
+    }
+
+    fn sample_surface(&self, u: T, v: T, time: T) -> SurfaceSample<T> {
+        let z = T::one() - T::TWO * u;
+        let r = (T::one() - z * z).max(T::zero()).sqrt();
+        let phi = T::two_pi() * v;
+        let normal = Unit::new_unchecked(vector![r * phi.cos(), r * phi.sin(), z]);
+        SurfaceSample { point: self.center_at(time) + normal.into_inner() * self.radius, normal }
+    }
+}
+
+impl<T> Fingerprint for Sphere<T>
+where
+    T: Scalar
+{
+    fn fingerprint(&self, state: &mut Fingerprinter) {
+        state.add(&self.center);
+        state.scalar(self.radius);
+        state.add(&self.material).add(&self.path);
     }
 }
 
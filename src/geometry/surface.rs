@@ -0,0 +1,54 @@
+use nalgebra::{Point2, Point3, Unit, Vector3, point};
+
+use crate::Scalar;
+
+/// a point chosen on the surface of a shape
+#[derive(Debug, Clone, Copy)]
+pub struct SurfaceSample<T>
+where
+    T: Scalar
+{
+    pub point: Point3<T>,
+    pub normal: Unit<Vector3<T>>,
+}
+
+/// shapes with a finite surface which can be sampled uniformly, needed for anything
+/// that wants to be used as an area light
+pub trait Surface<T>
+where
+    T: Scalar
+{
+    fn area(&self) -> T;
+
+    /// maps a pair of uniform values in [0, 1) to a point uniformly distributed over the surface
+    /// as it is at `time`, which only matters for shapes that move while the shutter is open
+    fn sample_surface(&self, u: T, v: T, time: T) -> SurfaceSample<T>;
+
+    /// probability density of `sample` with respect to solid angle as seen from `from`
+    fn solid_angle_pdf(&self, sample: &SurfaceSample<T>, from: Point3<T>) -> T {
+        let to_sample = sample.point - from;
+        let distance_squared = to_sample.norm_squared();
+        let cosine = sample.normal.dot(&to_sample).abs() / distance_squared.sqrt();
+        if cosine <= T::zero() {
+            T::zero()
+        } else {
+            distance_squared / (cosine * self.area())
+        }
+    }
+}
+
+/// maps the unit square onto the unit disk, preserving stratification
+/// (Shirley and Chiu, "A Low Distortion Map Between Disk and Square")
+pub fn concentric_disk<T: Scalar>(u: T, v: T) -> Point2<T> {
+    let (a, b) = (u * T::TWO - T::one(), v * T::TWO - T::one());
+    if a == T::zero() && b == T::zero() {
+        return Point2::origin();
+    }
+    let quarter_pi = T::frac_pi_4();
+    let (radius, theta) = if a.abs() > b.abs() {
+        (a, quarter_pi * (b / a))
+    } else {
+        (b, T::frac_pi_2() - quarter_pi * (a / b))
+    };
+    point![radius * theta.cos(), radius * theta.sin()]
+}
@@ -0,0 +1,161 @@
+use nalgebra::{Point2, Point3, Unit, Vector3, vector};
+
+use crate::{
+    Scalar,
+    Material,
+    fingerprint::{Fingerprint, Fingerprinter},
+    geometry::{
+        csg::{pair_spans, Solid, Span},
+        roots::{solve_quartic, Roots},
+        Aabb,
+        Frame,
+        Intersectable,
+        Intersection,
+        Ray,
+    },
+};
+
+/// a ring around `axis`, `major_radius` from the center to the middle of the tube
+/// and `minor_radius` for the tube itself
+#[derive(Debug, Clone)]
+pub struct Torus<T>
+where
+    T: Scalar
+{
+    frame: Frame<T>,
+    major_radius: T,
+    minor_radius: T,
+    material: Material<T>,
+}
+
+impl<T> Torus<T>
+where
+    T: Scalar
+{
+    pub fn new(center: Point3<T>, axis: Unit<Vector3<T>>, major_radius: T, minor_radius: T, material: Material<T>) -> Self {
+        Self { frame: Frame::from_axis(center, axis), major_radius, minor_radius, material }
+    }
+
+    pub fn material(&self) -> Material<T> {
+        self.material
+    }
+
+    pub fn with_material(self, material: Material<T>) -> Self {
+        Self { material, ..self }
+    }
+
+    fn local_ray(&self, ray: &Ray<T>) -> (Point3<T>, Vector3<T>) {
+        (self.frame.to_local_point(&ray.origin()), self.frame.to_local_vector(&ray.orientation()))
+    }
+
+    /// the intersection `depth` along the world space `ray`
+    fn hit(&self, ray: Ray<T>, depth: T) -> Intersection<T> {
+        let (origin, direction) = self.local_ray(&ray);
+        let (normal, uv) = self.local_surface(&(origin + direction * depth));
+        let normal = Unit::new_normalize(self.frame.to_world_vector(&normal));
+        Intersection::new(ray.project(depth), ray, normal, self.material).with_uv(uv)
+    }
+
+    /// every depth at which `ray` crosses the surface, in local space
+    pub(crate) fn local_hits(&self, origin: &Point3<T>, direction: &Vector3<T>) -> Roots<T> {
+        // the quartic gets badly conditioned far from the torus, so start the
+        // ray at the edge of the bounding sphere and add the distance back on
+        let reach = self.major_radius + self.minor_radius;
+        let skip = (-origin.coords.dot(direction) - reach).max(T::zero());
+        let o = origin + direction * skip;
+
+        // (|p|^2 + R^2 - r^2)^2 = 4 R^2 (x^2 + y^2), expanded in terms of depth
+        let (major2, minor2) = (self.major_radius * self.major_radius, self.minor_radius * self.minor_radius);
+        let four_major2 = major2 * T::from_float(4.0);
+        let e = o.coords.norm_squared() - major2 - minor2;
+        let f = o.coords.dot(direction);
+        let c4 = T::one();
+        let c3 = f * T::from_float(4.0);
+        let c2 = e * T::TWO + f * f * T::from_float(4.0) + four_major2 * direction.z * direction.z;
+        let c1 = f * e * T::from_float(4.0) + four_major2 * o.z * direction.z * T::TWO;
+        let c0 = e * e - four_major2 * (minor2 - o.z * o.z);
+
+        solve_quartic(c4, c3, c2, c1, c0)
+            .as_slice()
+            .iter()
+            .map(|depth| *depth + skip)
+            .collect()
+    }
+
+    /// outward normal and uv at a local point on the surface
+    pub(crate) fn local_surface(&self, point: &Point3<T>) -> (Vector3<T>, Point2<T>) {
+        let radial = point.coords.xy();
+        let ring = radial.norm();
+        // nearest point on the circle running through the middle of the tube
+        let spine = if ring > T::zero() { radial * (self.major_radius / ring) } else { radial };
+        let normal = vector![point.x - spine.x, point.y - spine.y, point.z];
+        let around = point.y.atan2(point.x) + T::pi();
+        let through = point.z.atan2(ring - self.major_radius) + T::pi();
+        (normal, Point2::new(around / T::two_pi(), through / T::two_pi()))
+    }
+}
+
+impl<T> Intersectable<T> for Torus<T>
+where
+    T: Scalar
+{
+    fn intersect(&self, ray: Ray<T>, min_depth: T, max_depth: T) -> Option<Intersection<T>> {
+        let (origin, direction) = self.local_ray(&ray);
+        let depth = self.local_hits(&origin, &direction).first_within(min_depth, max_depth)?;
+        Some(self.hit(ray, depth))
+    }
+
+    fn bounding_box(&self) -> Option<Aabb<T>> {
+        let (axis, center) = (self.frame.w(), self.frame.origin());
+        let tube = Vector3::repeat(self.minor_radius);
+        let ring = Aabb::around_circle(center, &axis, self.major_radius);
+        Some(Aabb::new(ring.min() - tube, ring.max() + tube))
+    }
+}
+
+impl<T> Solid<T> for Torus<T>
+where
+    T: Scalar
+{
+    fn spans(&self, ray: Ray<T>) -> Vec<Span<T>> {
+        let (origin, direction) = self.local_ray(&ray);
+        pair_spans(self.local_hits(&origin, &direction).as_slice(), |depth| self.hit(ray, depth))
+    }
+}
+
+impl<T> Fingerprint for Torus<T>
+where
+    T: Scalar
+{
+    fn fingerprint(&self, state: &mut Fingerprinter) {
+        state.add(&self.frame);
+        state.scalar(self.major_radius);
+        state.scalar(self.minor_radius);
+        state.add(&self.material);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use nalgebra::point;
+
+    use super::*;
+
+    #[test]
+    pub fn test_torus_hits_through_both_sides_of_the_ring() {
+        let torus = Torus::<f64>::new(Point3::origin(), Vector3::z_axis(), 1.0, 0.25, Material::default());
+        let origin = point![-50.0, 0.0, 0.0];
+        let direction = vector![1.0, 0.0, 0.0];
+        let hits = torus.local_hits(&torus.frame.to_local_point(&origin), &torus.frame.to_local_vector(&direction));
+        assert_eq!(hits.len(), 4);
+        for (hit, expected) in hits.as_slice().iter().zip([48.75, 49.25, 50.75, 51.25]) {
+            assert!((hit - expected).abs() < 1e-9, "{hit} != {expected}");
+        }
+
+        let hit = torus.intersect(Ray::from_orientation(origin, direction), 1e-5, f64::INFINITY).unwrap();
+        assert!((hit.normal().into_inner() + Vector3::x()).norm() < 1e-9);
+        // straight down the hole misses entirely
+        let ray = Ray::from_orientation(point![0.0, 0.0, 5.0], vector![0.0, 0.0, -1.0]);
+        assert!(torus.intersect(ray, 1e-5, f64::INFINITY).is_none());
+    }
+}
@@ -1,10 +1,12 @@
-use nalgebra::{point, Vector3, vector, Unit};
+use std::sync::Arc;
+
+use nalgebra::{point, vector, Point3, Unit, Similarity3, Vector3};
 use tracing::trace;
 
-use crate::{Scalar, Material, colour};
-use crate::geometry::{Ray, Intersectable, Intersection, Geometry, Aabb};
+use crate::{Scalar, Material, colour, fingerprint::{Fingerprint, Fingerprinter}};
+use crate::geometry::{Ray, Intersectable, Intersection, Geometry, Aabb, Blas, Bvh, Instance, Keyframes};
 
-use super::{Sphere, Plane};
+use super::{Sphere, Plane, Quad, Disk, Cuboid, Cylinder, Cone, Torus, Csg, Sdf, SdfShape};
 
 // #[derive(Debug, Clone)]
 // pub struct DynWorld<T>
@@ -30,7 +32,7 @@ use super::{Sphere, Plane};
 //     }
 // }
 
-// #[derive(Debug, Clone)]
+#[derive(Debug, Clone)]
 pub struct StaticWorld<T>
 where
     T: Scalar
@@ -62,6 +64,56 @@ where
     pub fn push_plane(&mut self, new_plane: super::Plane<T>) {
         self.objects.push(Geometry::Plane(new_plane))
     }
+
+    pub fn push_instance(&mut self, new_instance: Instance<T>) {
+        self.objects.push(Geometry::Instance(new_instance))
+    }
+
+    pub fn push_quad(&mut self, new_quad: Quad<T>) {
+        self.objects.push(Geometry::Quad(new_quad))
+    }
+
+    pub fn push_disk(&mut self, new_disk: Disk<T>) {
+        self.objects.push(Geometry::Disk(new_disk))
+    }
+
+    pub fn push_cuboid(&mut self, new_cuboid: Cuboid<T>) {
+        self.objects.push(Geometry::Cuboid(new_cuboid))
+    }
+
+    pub fn push_cylinder(&mut self, new_cylinder: Cylinder<T>) {
+        self.objects.push(Geometry::Cylinder(new_cylinder))
+    }
+
+    pub fn push_cone(&mut self, new_cone: Cone<T>) {
+        self.objects.push(Geometry::Cone(new_cone))
+    }
+
+    pub fn push_torus(&mut self, new_torus: Torus<T>) {
+        self.objects.push(Geometry::Torus(new_torus))
+    }
+
+    pub fn push_csg(&mut self, new_csg: Csg<T>) {
+        self.objects.push(Geometry::Csg(new_csg))
+    }
+
+    pub fn push_sdf(&mut self, new_sdf: Sdf<T>) {
+        self.objects.push(Geometry::Sdf(new_sdf))
+    }
+
+    /// the box around every object that has one. infinite planes are left out, so a scene
+    /// standing on a ground plane is bounded by what stands on it. None if nothing is bounded
+    pub fn bounds(&self) -> Option<Aabb<T>> {
+        self.objects
+            .iter()
+            .filter_map(|object| object.bounding_box())
+            .reduce(|acc, next| acc.union(&next))
+    }
+
+    /// consumes the world into an acceleration structure over the same objects
+    pub fn into_bvh(self) -> Bvh<T, Geometry<T>> {
+        Bvh::new(self.objects)
+    }
 }
 
 impl<T> Intersectable<T> for StaticWorld<T>
@@ -73,14 +125,9 @@ where
             .iter()
             .filter_map(|geom| {
                 // trace!("Searching: {ray:?} with {geom:?}");
-                match geom {
-                    Geometry::Sphere(s) => s.intersect(ray, min_depth, max_depth),
-                    Geometry::Plane(p) => p.intersect(ray, min_depth, max_depth),
-                }
+                geom.intersect(ray, min_depth, max_depth)
             }).reduce(|acc, next| {
-                let prev_length = (acc.point() - ray.origin()).norm();
-                let next_length = (next.point() - ray.origin()).norm();
-                if prev_length > next_length { next } else { acc }
+                if acc.depth() > next.depth() { next } else { acc }
             });
         trace!("Found: {res:?}");
         res
@@ -237,7 +284,7 @@ pub fn glass_sphere_scene<T: Scalar>() -> StaticWorld<T> {
 }
 
 pub fn plane_scene<T: Scalar>() -> StaticWorld<T> {
-    let mut world = StaticWorld::default();;
+    let mut world = StaticWorld::default();
     world.push_plane(Plane::default());
     world.push_plane(
         Plane::new(
@@ -261,4 +308,201 @@ pub fn plane_scene<T: Scalar>() -> StaticWorld<T> {
         )
     );
     world
-}
\ No newline at end of file
+}
+/// an open fronted box with coloured side walls, a table and a disk on the floor
+pub fn cornell_box_scene<T: Scalar>() -> StaticWorld<T> {
+    let mut world = StaticWorld::default();
+    let (white, red, green) = (
+        Material::simple_diffuse_colour(colour::white()),
+        Material::simple_diffuse_colour(colour::bright_red()),
+        Material::simple_diffuse_colour(colour::bright_green()),
+    );
+    let (lo, hi, depth) = (-T::one(), T::one(), -T::from_float(3.0));
+    let (width, height) = (vector![T::TWO, T::zero(), T::zero()], vector![T::zero(), T::TWO, T::zero()]);
+    let back = vector![T::zero(), T::zero(), depth];
+    // all walls face into the box
+    world.push_quad(Quad::new(point![lo, lo, T::zero()], back, height, red).expect("the walls span an area"));
+    world.push_quad(Quad::new(point![hi, lo, T::zero()], height, back, green).expect("the walls span an area"));
+    world.push_quad(Quad::new(point![lo, lo, T::zero()], width, back, white).expect("the walls span an area"));
+    world.push_quad(Quad::new(point![lo, hi, T::zero()], back, width, white).expect("the walls span an area"));
+    world.push_quad(Quad::new(point![lo, lo, depth], width, height, white).expect("the walls span an area"));
+    world.push_cuboid(Cuboid::new(
+        point![-T::from_float(0.6), lo, -T::from_float(2.2)],
+        point![T::zero(), -T::from_float(0.2), -T::from_float(1.6)],
+        white,
+    ).expect("the table isn't flat"));
+    world.push_cuboid(Cuboid::new(
+        point![T::from_float(0.2), lo, -T::from_float(1.8)],
+        point![T::from_float(0.7), -T::from_float(0.6), -T::from_float(1.3)],
+        Material::mirror(),
+    ).expect("the mirror block isn't flat"));
+    world.push_disk(Disk::new(
+        point![T::zero(), hi - T::from_float(0.01), -T::from_float(1.5)],
+        -Vector3::y_axis(),
+        T::from_float(0.3),
+        Material::simple_diffuse_colour(colour::white()),
+    ).expect("the disk has a radius"));
+    world
+}
+
+/// a pipe, a bottle and a ring sat on the checkerboard
+pub fn product_scene<T: Scalar>() -> StaticWorld<T> {
+    let mut world = StaticWorld::default();
+    let floor = -T::HALF;
+    world.push_cylinder(
+        Cylinder::new(
+            point![-T::from_float(0.7), floor, -T::from_float(1.5)],
+            Unit::new_normalize(vector![T::one(), T::from_float(0.2), T::HALF]),
+            T::from_float(0.1),
+            T::from_float(0.8),
+            Material::mirror(),
+        ).without_caps()
+    );
+    // bottle body with a cone for the shoulders and a thin cylinder for the neck
+    let up = Vector3::y_axis();
+    world.push_cylinder(Cylinder::new(point![T::zero(), floor, -T::from_float(1.4)], up, T::from_float(0.15), T::from_float(0.4), Material::glass()));
+    world.push_cone(Cone::new(point![T::zero(), floor + T::from_float(0.4), -T::from_float(1.4)], up, T::from_float(0.15), T::from_float(0.2), Material::glass()).expect("the shoulders have a size"));
+    world.push_cylinder(Cylinder::new(point![T::zero(), floor + T::from_float(0.5), -T::from_float(1.4)], up, T::from_float(0.05), T::from_float(0.2), Material::glass()));
+    world.push_torus(
+        Torus::new(
+            point![T::from_float(0.6), floor + T::from_float(0.05), -T::from_float(1.1)],
+            up,
+            T::from_float(0.2),
+            T::from_float(0.05),
+            Material::simple_diffuse_colour(colour::bright_orange()),
+        )
+    );
+    world.push_plane(Plane::default());
+    world
+}
+
+/// a sphere with a cylinder drilled through it next to a lens made from two overlapping spheres
+pub fn csg_scene<T: Scalar>() -> StaticWorld<T> {
+    let mut world = StaticWorld::default();
+    let center = point![-T::from_float(0.5), T::zero(), -T::from_float(1.5)];
+    let bead = Sphere::new(center, T::from_float(0.4), Material::simple_diffuse_colour(colour::light_blue()));
+    let drill = Cylinder::new(
+        center - Vector3::x() * T::one(),
+        Vector3::x_axis(),
+        T::from_float(0.15),
+        T::TWO,
+        Material::simple_diffuse_colour(colour::yellow()),
+    );
+    world.push_csg(Csg::difference(Geometry::Sphere(bead), Geometry::Cylinder(drill)));
+
+    let lens_center = point![T::from_float(0.5), T::zero(), -T::from_float(1.5)];
+    let offset = Vector3::z() * T::from_float(0.6);
+    world.push_csg(Csg::intersection(
+        Geometry::Sphere(Sphere::new(lens_center + offset, T::from_float(0.7), Material::glass())),
+        Geometry::Sphere(Sphere::new(lens_center - offset, T::from_float(0.7), Material::glass())),
+    ));
+    world.push_plane(Plane::default());
+    world
+}
+
+/// a mandelbulb, a twisted box and a blobby smooth union alongside an ordinary sphere
+pub fn sdf_scene<T: Scalar>() -> StaticWorld<T> {
+    let mut world = StaticWorld::default();
+    world.push_sdf(Sdf::new(
+        SdfShape::mandelbulb().translate(vector![T::zero(), T::zero(), -T::from_float(2.5)]),
+        Material::simple_diffuse_colour(colour::bright_purple()),
+    ));
+    world.push_sdf(
+        Sdf::new(
+            SdfShape::cuboid(vector![T::from_float(0.15), T::from_float(0.4), T::from_float(0.15)])
+                .twist(T::from_float(3.0))
+                .translate(vector![-T::from_float(1.2), -T::from_float(0.1), -T::from_float(1.8)]),
+            Material::simple_diffuse_colour(colour::bright_orange()),
+        ).with_step_scale(T::HALF)
+    );
+    let blob = SdfShape::sphere(T::from_float(0.2)).translate(vector![T::from_float(1.0), -T::from_float(0.3), -T::from_float(1.5)])
+        .smooth_union(
+            SdfShape::sphere(T::from_float(0.15)).translate(vector![T::from_float(1.2), -T::from_float(0.1), -T::from_float(1.4)]),
+            T::from_float(0.15),
+        )
+        .smooth_union(
+            SdfShape::torus(T::from_float(0.2), T::from_float(0.05)).translate(vector![T::from_float(1.0), -T::from_float(0.45), -T::from_float(1.5)]),
+            T::from_float(0.1),
+        );
+    world.push_sdf(Sdf::new(blob, Material::simple_diffuse_colour(colour::light_green())));
+    world.push_sphere(Sphere::new(point![T::from_float(1.1), T::from_float(0.3), -T::from_float(2.0)], T::from_float(0.3), Material::mirror()));
+    world.push_plane(Plane::default());
+    world
+}
+
+/// bouncing and spinning objects to be rendered with the shutter open over [0, 1]
+pub fn motion_blur_scene<T: Scalar>() -> StaticWorld<T> {
+    let mut world = StaticWorld::default();
+    world.push_sphere(
+        Sphere::new(Point3::origin(), T::from_float(0.2), Material::simple_diffuse_colour(colour::bright_red()))
+            .with_path(Keyframes::new(vec![
+                (T::zero(), point![-T::from_float(0.8), -T::from_float(0.3), -T::from_float(1.5)]),
+                (T::HALF, point![-T::from_float(0.6), T::from_float(0.1), -T::from_float(1.5)]),
+                (T::one(), point![-T::from_float(0.4), -T::from_float(0.3), -T::from_float(1.5)]),
+            ]))
+    );
+    let mut spinner = StaticWorld::default();
+    spinner.push_cuboid(Cuboid::new(
+        point![-T::from_float(0.3), -T::from_float(0.05), -T::from_float(0.05)],
+        point![T::from_float(0.3), T::from_float(0.05), T::from_float(0.05)],
+        Material::simple_diffuse_colour(colour::bright_blue()),
+    ).expect("the spinner isn't flat"));
+    let position = vector![T::from_float(0.5), T::zero(), -T::from_float(1.5)];
+    let spin = |angle: f64| Similarity3::new(position, Vector3::z() * T::from_float(angle), T::one());
+    world.push_instance(
+        Instance::new(Arc::new(spinner.into_bvh()), spin(0.0))
+            .with_motion(Keyframes::new(vec![(T::zero(), spin(0.0)), (T::HALF, spin(0.75)), (T::one(), spin(1.5))]))
+    );
+    world.push_plane(Plane::default());
+    world
+}
+
+/// a single tree built from spheres, shared by every instance in [`forest_scene`]
+fn tree<T: Scalar>() -> Blas<T> {
+    let trunk = Material::simple_diffuse_colour(colour::rgb(T::from_float(0.4), T::from_float(0.25), T::from_float(0.1)));
+    let leaves = Material::simple_diffuse_colour(colour::light_green());
+    let mut tree = StaticWorld::default();
+    for i in 0..4 {
+        tree.push_sphere(Sphere::new(
+            point![T::zero(), T::from_float(0.1 + 0.15 * i as f64), T::zero()],
+            T::from_float(0.08),
+            trunk,
+        ));
+    }
+    tree.push_sphere(Sphere::new(point![T::zero(), T::from_float(0.8), T::zero()], T::from_float(0.3), leaves));
+    tree.push_sphere(Sphere::new(point![T::from_float(0.15), T::from_float(0.65), T::zero()], T::from_float(0.2), leaves));
+    tree.push_sphere(Sphere::new(point![-T::from_float(0.15), T::from_float(0.65), T::from_float(0.1)], T::from_float(0.2), leaves));
+    tree.into_bvh()
+}
+
+/// a `rows` by `rows` grid of instanced trees with some variation in scale and rotation,
+/// every instance shares a single copy of the tree geometry
+pub fn forest_scene<T: Scalar>(rows: usize) -> Bvh<T, Geometry<T>> {
+    let tree = Arc::new(tree());
+    let mut world = StaticWorld::default();
+    for row in 0..rows {
+        for col in 0..rows {
+            // cheap deterministic hash so the forest looks the same every render
+            let jitter = ((row * 7919 + col * 104729) % 1000) as f64 / 1000.0;
+            let translation = vector![
+                T::from_float(col as f64 - rows as f64 / 2.0 + jitter * 0.5),
+                T::from_float(-0.5),
+                -T::from_float(1.0 + row as f64 + jitter * 0.5)
+            ];
+            let rotation = Vector3::y() * T::from_float(jitter * std::f64::consts::TAU);
+            let scale = T::from_float(0.7 + jitter * 0.6);
+            world.push_instance(Instance::new(tree.clone(), Similarity3::new(translation, rotation, scale)));
+        }
+    }
+    world.push_plane(Plane::default());
+    world.into_bvh()
+}
+
+impl<T> Fingerprint for StaticWorld<T>
+where
+    T: Scalar
+{
+    fn fingerprint(&self, state: &mut Fingerprinter) {
+        state.add(&self.objects);
+    }
+}
@@ -1,17 +1,37 @@
+pub mod adaptive;
 pub mod aspect;
 pub mod buffer;
+mod checkpoint;
+pub mod film;
+pub mod filter;
 pub mod ppm;
-
+pub mod preview;
+pub mod progressive;
+pub mod region;
+pub mod sequence;
+pub mod settings;
+pub mod tiles;
+
+pub use adaptive::{AdaptiveSampling, PixelStats, SampleCounts};
 pub use aspect::{AspectRatio, Rectangle};
 pub use buffer::PixelBuffer;
+pub use film::Film;
+pub use filter::Filter;
 use nalgebra::Vector3;
 pub use ppm::AsPPM;
-use tracing::{debug, trace};
-
+pub use preview::{Channel, Preview, PreviewOutcome};
+pub use progressive::{BudgetReport, ProgressiveRender};
+pub use region::Region;
+pub use sequence::FrameSequence;
+pub use settings::RenderSettings;
+pub use tiles::TileOrder;
+use tracing::trace;
+
+use std::{fs, io::{self, Write}, path::Path, sync::{Mutex, atomic::{AtomicUsize, Ordering}}};
 use rayon::prelude::*;
-use rand::Rng;
 use indicatif::{ProgressBar, ProgressStyle};
-use crate::{Scalar, Camera, colour};
+use crate::{Scalar, Camera, Sampler, colour};
+use crate::camera::{Eye, StereoRig};
 use crate::geometry::Intersectable;
 
 const SAMPLES: usize = 100;
@@ -63,7 +83,7 @@ pub enum SphereTest {
 //     buf
 // }
 
-fn make_render_progress_bar(width: usize, height: usize) -> ProgressBar {
+pub(crate) fn make_render_progress_bar(width: usize, height: usize) -> ProgressBar {
     ProgressBar::new((height * width) as u64)
         .with_style(
             ProgressStyle::with_template("[{elapsed_precise}] {bar:40.cyan/blue} ({percent}%) [{eta}] {msg}")
@@ -72,46 +92,149 @@ fn make_render_progress_bar(width: usize, height: usize) -> ProgressBar {
         )
 }
 
-/// applies jitter [0.0, 1.0) to the input and scales by the given factor
-fn jitter_scale<T: Scalar>(value: usize, scale: T) -> T {
-    T::from_float(value as f64 + rand::thread_rng().gen_range(0.0..1.0_f64)) * scale
+/// writes `bytes` to `path` without ever leaving a half written file there. they go to a
+/// `.partial` file next to it first, which is flushed to disk and then renamed into place
+pub(crate) fn write_replacing(path: &Path, bytes: &[u8]) -> io::Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let mut partial = path.as_os_str().to_owned();
+    partial.push(".partial");
+    let mut file = fs::File::create(&partial)?;
+    file.write_all(bytes)?;
+    file.sync_all()?;
+    fs::rename(&partial, path)
 }
 
-pub fn render_scene<T: Scalar, Scene: Intersectable<T>>(camera: Camera<T>, scene: Scene) -> PixelBuffer<T> {
-    let mut buf = PixelBuffer::new_from_vertical_ratio(720, AspectRatio::default());
-    let (width, height) = Rectangle::as_tuple(&buf);
-    // scale factors for converting pixel indicies to camera coordinates
-    let width_scale = T::one() / T::from_float(width as f64);
-    let height_scale = T::one() / T::from_float(height as f64);
-
-    let render_progress = make_render_progress_bar(width, height);
-    for row in 0..height {
-        for col in 0..width {
-            render_progress.inc(1);
-
-            let mut sample_acc = colour::black();
-            for _ in 0..SAMPLES {
-                let u = jitter_scale(col, width_scale);
-                let v = jitter_scale(row, height_scale);
-                trace!("Sampling pixel: (col: {col:?}, row: {row:?}), coord: (u: {u:?} v: {v:?}), rect: (width: {width:?}, height: {height:?})");
-
-                let mut ray = camera.ray(u, v)
-                    .expect("camera coordinates should be in range [0,1)");
-                for _ in 0..BOUNCES {
-                    if let Some(intersection) = scene.intersect(ray, T::from_float(0.00001), T::INF) {
-                        ray = intersection.scatter();
-                        trace!("Scatter: {ray:?}");  
-                    } else {
-                        sample_acc += ray.colour();
-                        break;
-                    }
-                }
+/// everything needed to trace samples of one frame, splatting them onto a film over `target`
+pub(crate) struct Tracer<'a, T, Scene>
+where
+    T: Scalar
+{
+    camera: &'a Camera<T>,
+    scene: &'a Scene,
+    settings: &'a RenderSettings,
+    frame: (usize, usize),
+    target: Region,
+}
+
+impl<'a, T, Scene> Tracer<'a, T, Scene>
+where
+    T: Scalar,
+    Scene: Intersectable<T>
+{
+    pub(crate) fn new(camera: &'a Camera<T>, scene: &'a Scene, settings: &'a RenderSettings, target: Region) -> Self {
+        let frame = (camera.aspect_ratio().width_from_height(settings.vertical_resolution()), settings.vertical_resolution());
+        assert!(target.fits_within(frame.0, frame.1), "{target:?} is outside the {}x{} frame", frame.0, frame.1);
+        Self { camera, scene, settings, frame, target }
+    }
+
+    /// the pixels whose samples can land on the target, which is the target plus however far the filter reaches
+    pub(crate) fn source(&self) -> Region {
+        self.target.expanded(self.settings.filter().margin(), self.frame.0, self.frame.1)
+    }
+
+    /// traces one sample of the pixel at (`col`, `row`), returning where on the frame it landed
+    /// and the radiance arriving there, or None if the camera sees nothing there. the sampler
+    /// must already have been started on this sample
+    fn sample(&self, sampler: &mut dyn Sampler<T>, (col, row): (usize, usize)) -> ((f64, f64), Option<Vector3<T>>) {
+        let (width, height) = self.frame;
+        let jitter = sampler.next_2d();
+        let position = (col as f64 + jitter.x.to_float(), row as f64 + jitter.y.to_float());
+        let u = (T::from_float(col as f64) + jitter.x) / T::from_float(width as f64);
+        let v = (T::from_float(row as f64) + jitter.y) / T::from_float(height as f64);
+        trace!("Sampling pixel: (col: {col:?}, row: {row:?}), coord: (u: {u:?} v: {v:?}), rect: (width: {width:?}, height: {height:?})");
+
+        let Some(mut ray) = self.camera.sample_ray(u, v, sampler) else {
+            return (position, None);
+        };
+        for _ in 0..self.settings.bounces() {
+            if let Some(intersection) = self.scene.intersect(ray, T::from_float(0.00001), T::INF) {
+                ray = intersection.scatter(sampler);
+                trace!("Scatter: {ray:?}");
+            } else {
+                return (position, Some(ray.colour()));
             }
-            buf[row * width + col] = sample_acc / T::from_float(SAMPLES as f64);
         }
+        // paths that never escape are taken to have been absorbed
+        (position, Some(colour::black()))
+    }
+
+    /// adds up to `samples` more samples of the pixel at (`col`, `row`) to `stats` and splats
+    /// them onto `film`, stopping early if it converges when sampling adaptively. samples carry
+    /// on numbering from those already taken, so a pixel rendered in several goes gets the same
+    /// samples as one rendered at once
+    fn render_pixel(&self, sampler: &mut dyn Sampler<T>, pixel: (usize, usize), stats: &mut PixelStats<T>, samples: usize, film: &mut Film<T>) {
+        let start = stats.count();
+        for index in start..start + samples {
+            if self.settings.adaptive().is_some_and(|adaptive| adaptive.converged(stats)) {
+                break;
+            }
+            sampler.start_pixel_sample(pixel, index);
+            // some projections don't cover the whole frame, which leaves those samples black
+            let (position, radiance) = self.sample(sampler, pixel);
+            let radiance = radiance.unwrap_or_else(colour::black);
+            stats.add(radiance);
+            film.add_sample(&self.settings.filter(), position, radiance);
+        }
+    }
+
+    /// samples each pixel of `tile` once, adding up to `samples` more to its entry in `stats`,
+    /// which are in the tile's row order, without taking any past `cap`. returns the film of
+    /// the part of the target the samples can reach, which goes past the tile with wide filters
+    /// so neighbouring tiles' films have to be merged to finish the pixels along their borders
+    pub(crate) fn render_tile(&self, tile: Region, stats: &mut [PixelStats<T>], samples: usize, cap: usize) -> Film<T> {
+        let reach = tile.expanded(self.settings.filter().margin(), self.frame.0, self.frame.1);
+        let mut film = Film::new(reach.intersection(&self.target).unwrap_or(Region::new(self.target.x, self.target.y, 0, 0)));
+        // the samplers hash the pixel and sample index with the seed rather than keeping any
+        // running state, so it doesn't matter which thread renders which tile
+        let mut sampler = self.settings.sampler().build(self.settings.samples(), self.settings.seed());
+        for (pixel, stats) in tile.pixels().zip(stats.iter_mut()) {
+            let samples = samples.min(cap.saturating_sub(stats.count()));
+            self.render_pixel(sampler.as_mut(), pixel, stats, samples, &mut film);
+        }
+        film
+    }
+}
+
+/// renders `tiles` across the thread pool with `render`, each thread taking the next tile in
+/// order as it frees up so they start in that order. the results come back in tile order
+/// whichever thread made them, so anything added up from them in turn is the same whatever
+/// the thread count
+pub(crate) fn schedule_tiles<R, F>(tiles: &[Region], render: F) -> Vec<(Region, R)>
+where
+    R: Send,
+    F: Fn(Region) -> R + Sync
+{
+    let next = AtomicUsize::new(0);
+    let finished = Mutex::new(Vec::with_capacity(tiles.len()));
+    (0..rayon::current_num_threads()).into_par_iter().for_each(|_| {
+        loop {
+            let index = next.fetch_add(1, Ordering::Relaxed);
+            let Some(tile) = tiles.get(index) else { break };
+            let result = render(*tile);
+            finished.lock().expect("a tile panicked").push((index, *tile, result));
+        }
+    });
+    let mut finished = finished.into_inner().expect("a tile panicked");
+    finished.sort_by_key(|(index, _, _)| *index);
+    finished.into_iter().map(|(_, tile, result)| (tile, result)).collect()
+}
+
+pub fn render_scene<T: Scalar, Scene: Intersectable<T>>(camera: Camera<T>, scene: Scene) -> PixelBuffer<T> {
+    let settings = RenderSettings::default();
+    let frame = Region::full(camera.aspect_ratio().width_from_height(settings.vertical_resolution()), settings.vertical_resolution());
+    let tracer = Tracer::new(&camera, &scene, &settings, frame);
+    let mut film = Film::new(frame);
+
+    let render_progress = make_render_progress_bar(frame.width, frame.height);
+    for row in 0..frame.height {
+        let mut stats = vec![PixelStats::default(); frame.width];
+        film.merge(&tracer.render_tile(Region::new(0, row, frame.width, 1), &mut stats, settings.samples(), usize::MAX));
+        render_progress.inc(frame.width as u64);
     }
 
-    buf
+    film.resolve(camera.exposure_scale())
 }
 
 
@@ -129,43 +252,129 @@ where
     T: Scalar,
     Scene: Intersectable<T> + Sync
 {
-    let mut buf = PixelBuffer::new_from_vertical_ratio(vertical_resolution, AspectRatio::default());
-    let (width, height) = Rectangle::as_tuple(&buf);
-    // scale factors for converting pixel indicies to camera coordinates
-    let width_scale = T::one() / T::from_float(width as f64);
-    let height_scale = T::one() / T::from_float(height as f64);
-
-    let render_progress = make_render_progress_bar(width, height);
-    buf.pixels = (0..height).into_par_iter().flat_map(|row| {
-        let mut row_buf = vec![Vector3::zeros(); width];
-        for col in 0..width {
-            render_progress.inc(1);
-
-            let mut sample_acc = colour::black();
-            for _ in 0..samples {
-                let u = jitter_scale(col, width_scale);
-                let v = jitter_scale(row, height_scale);
-                trace!("Sampling pixel: (col: {col:?}, row: {row:?}), coord: (u: {u:?} v: {v:?}), rect: (width: {width:?}, height: {height:?})");
-
-                let mut ray = camera.ray(u, v)
-                    .expect("camera coordinates should be in range [0,1)");
-                for _ in 0..BOUNCES {
-                    if let Some(intersection) = scene.intersect(ray, T::from_float(0.00001), T::INF) {
-                        ray = intersection.scatter();
-                        trace!("Scatter: {ray:?}");  
-                    } else {
-                        sample_acc += ray.colour();
-                        break;
-                    }
-                }
+    render(camera, scene, &RenderSettings::default().with_resolution(vertical_resolution).with_samples(samples))
+}
+
+/// renders the whole frame in parallel as described by `settings`
+pub fn render<T, Scene>(camera: Camera<T>, scene: Scene, settings: &RenderSettings) -> PixelBuffer<T>
+where
+    T: Scalar,
+    Scene: Intersectable<T> + Sync
+{
+    render_with_sample_counts(camera, scene, settings).0
+}
+
+/// renders the whole frame along with how many samples each pixel took, which is mostly
+/// interesting with adaptive sampling. see [`SampleCounts::heatmap`]
+pub fn render_with_sample_counts<T, Scene>(camera: Camera<T>, scene: Scene, settings: &RenderSettings) -> (PixelBuffer<T>, SampleCounts)
+where
+    T: Scalar,
+    Scene: Intersectable<T> + Sync
+{
+    let width = camera.aspect_ratio().width_from_height(settings.vertical_resolution());
+    render_scene_with_progress(camera, scene, settings, &make_render_progress_bar(width, settings.vertical_resolution()))
+}
+
+/// renders into a new buffer, reporting each finished pixel to `render_progress`
+pub(crate) fn render_scene_with_progress<T, Scene>(camera: Camera<T>, scene: Scene, settings: &RenderSettings, render_progress: &ProgressBar) -> (PixelBuffer<T>, SampleCounts)
+where
+    T: Scalar,
+    Scene: Intersectable<T> + Sync
+{
+    let width = camera.aspect_ratio().width_from_height(settings.vertical_resolution());
+    render_region_with_progress(camera, scene, settings, Region::full(width, settings.vertical_resolution()), render_progress)
+}
+
+/// renders just `region` of the full frame into a buffer the size of the region, which can
+/// be put back into the full frame with [`PixelBuffer::merge_region`]
+pub fn render_region_parallel_quality<T, Scene>(camera: Camera<T>, scene: Scene, vertical_resolution: usize, samples: usize, region: Region) -> PixelBuffer<T>
+where
+    T: Scalar,
+    Scene: Intersectable<T> + Sync
+{
+    render_region(camera, scene, &RenderSettings::default().with_resolution(vertical_resolution).with_samples(samples), region)
+}
+
+/// renders just `region` of the full frame as described by `settings`
+pub fn render_region<T, Scene>(camera: Camera<T>, scene: Scene, settings: &RenderSettings, region: Region) -> PixelBuffer<T>
+where
+    T: Scalar,
+    Scene: Intersectable<T> + Sync
+{
+    render_region_with_progress(camera, scene, settings, region, &make_render_progress_bar(region.width, region.height)).0
+}
+
+pub(crate) fn render_region_with_progress<T, Scene>(camera: Camera<T>, scene: Scene, settings: &RenderSettings, region: Region, render_progress: &ProgressBar) -> (PixelBuffer<T>, SampleCounts)
+where
+    T: Scalar,
+    Scene: Intersectable<T> + Sync
+{
+    render_tiles(&camera, &scene, settings, region, render_progress, |_, _| {})
+}
+
+/// renders the whole frame, handing each tile to `on_tile` as soon as it's finished along with
+/// where it goes in the frame. tiles finish in roughly the settings' tile order, and `on_tile`
+/// is called from whichever thread rendered the tile. a filter wider than a pixel spreads
+/// samples across tile borders, so a tile's edge pixels only have their own tile's samples
+/// until the final image, which has its neighbours' too
+pub fn render_with_preview<T, Scene, F>(camera: Camera<T>, scene: Scene, settings: &RenderSettings, on_tile: F) -> PixelBuffer<T>
+where
+    T: Scalar,
+    Scene: Intersectable<T> + Sync,
+    F: Fn(Region, &PixelBuffer<T>) + Sync
+{
+    let width = camera.aspect_ratio().width_from_height(settings.vertical_resolution());
+    let frame = Region::full(width, settings.vertical_resolution());
+    render_tiles(&camera, &scene, settings, frame, &make_render_progress_bar(width, frame.height), on_tile).0
+}
+
+/// renders `region` a tile at a time, in the settings' tile order. the tiles cover the region
+/// plus the margin a wide filter reaches in from around it, and each pixel is only traced once,
+/// its samples splatting onto the films of whichever tiles they reach. since each pixel gets
+/// the same samples wherever it's rendered, and the films are merged in tile order, the image
+/// doesn't depend on which thread took which tile
+fn render_tiles<T, Scene, F>(camera: &Camera<T>, scene: &Scene, settings: &RenderSettings, region: Region, render_progress: &ProgressBar, on_tile: F) -> (PixelBuffer<T>, SampleCounts)
+where
+    T: Scalar,
+    Scene: Intersectable<T> + Sync,
+    F: Fn(Region, &PixelBuffer<T>) + Sync
+{
+    let tracer = Tracer::new(camera, scene, settings, region);
+    let tiles = settings.tile_order().tiles(tracer.source(), settings.tile_size());
+    let finished = schedule_tiles(&tiles, |tile| {
+        let mut stats = vec![PixelStats::default(); tile.area()];
+        let film = tracer.render_tile(tile, &mut stats, settings.samples(), usize::MAX);
+        if let Some(within) = tile.intersection(&region) {
+            render_progress.inc(within.area() as u64);
+            let mut preview = Film::new(within);
+            preview.merge(&film);
+            on_tile(within, &preview.resolve(camera.exposure_scale()));
+        }
+        (film, stats)
+    });
+
+    let mut film = Film::new(region);
+    let mut counts = SampleCounts::new(region.width, region.height, vec![0; region.area()]);
+    for (tile, (tile_film, stats)) in &finished {
+        film.merge(tile_film);
+        for ((col, row), stats) in tile.pixels().zip(stats) {
+            if region.contains(col, row) {
+                counts.counts[(row - region.y) * region.width + col - region.x] = stats.count();
             }
-            row_buf[col] = sample_acc / T::from_float(samples as f64);
         }
-        row_buf.into_par_iter()
-    })
-    .collect();
+    }
+    (film.resolve(camera.exposure_scale()), counts)
+}
 
-    buf
+/// renders the left and right eyes of a stereo rig, returned in that order
+pub fn render_stereo_parallel_quality<T, Scene>(rig: &StereoRig<T>, scene: Scene, vertical_resolution: usize, samples: usize) -> (PixelBuffer<T>, PixelBuffer<T>)
+where
+    T: Scalar,
+    Scene: Intersectable<T> + Sync
+{
+    let left = render_scene_parallel_quality(rig.eye(Eye::Left), &scene, vertical_resolution, samples);
+    let right = render_scene_parallel_quality(rig.eye(Eye::Right), &scene, vertical_resolution, samples);
+    (left, right)
 }
 
 // pub fn render_scene<T: Scalar, Scene: Intersectable<T>>(camera: Camera<T>, scene: Scene) -> PixelBuffer<T> {
@@ -267,10 +476,10 @@ where
 #[cfg(test)]
 mod test {
     use rand::Rng;
-    use nalgebra::{Vector3, vector, Unit};
+    use nalgebra::{Vector3, point, vector};
 
     use super::*;
-    use crate::image::AspectRatio;
+    use crate::{Material, geometry::Sphere, image::AspectRatio};
     
     // fn normal_to_rgb<T: Scalar>(normal: Unit<Vector3<T>>) -> Vector3<T> {
     //     (normal.into_inner() + vector![T::one(), T::one(), T::one()]) * T::from_float(0.5)
@@ -294,6 +503,11 @@ mod test {
         buf
     }
     
+    /// a unit sphere two units in front of the default camera, filling the middle of the frame
+    pub(crate) fn sphere_in_view<T: Scalar>() -> Sphere<T> {
+        Sphere::new(point![T::zero(), T::zero(), T::from_float(-2.0)], T::one(), Material::default())
+    }
+
     fn generate_gradient<T: Scalar>(width: usize, height: usize) -> PixelBuffer<T> {
         let mut pixels = vec![Vector3::zeros(); width * height];
     
@@ -373,6 +587,145 @@ mod test {
     //         .expect("Failed to write sphere_gamma__half_f32.ppm")
     // }
 
+    #[test]
+    pub fn test_region_render_merges_into_full_frame() {
+        // nothing in view, so every pixel is the sky behind it
+        let scene = Sphere::new(point![0.0, 0.0, 10.0], 1.0, Material::default());
+        let full = render_scene_parallel_quality(Camera::<f64>::default(), &scene, 18, 4);
+        let region = Region::new(5, 3, 7, 4);
+        let crop = render_region_parallel_quality(Camera::default(), &scene, 18, 4, region);
+        assert_eq!(crop.as_tuple(), (7, 4));
+        // the same seed gives the same samples for each pixel wherever it's rendered from
+        assert_eq!(crop.pixels, full.crop(region).pixels);
+
+        let mut merged = PixelBuffer::new(full.width(), full.height());
+        merged.merge_region(region, &crop);
+        assert_eq!(merged.crop(region).pixels, crop.pixels);
+        assert_eq!(merged[0], Vector3::zeros());
+    }
+
+    #[test]
+    pub fn test_filtered_regions_gather_samples_from_around_them() {
+        let scene = sphere_in_view();
+        let settings = RenderSettings::default().with_resolution(12).with_samples(2).with_filter(Filter::mitchell(2.0));
+        let full = render(Camera::<f64>::default(), &scene, &settings);
+        let region = Region::new(4, 3, 6, 5);
+        let crop = render_region(Camera::default(), &scene, &settings, region);
+        // the same samples, merged from differently cut up tiles
+        assert!(crop.pixels.iter().zip(&full.crop(region).pixels).all(|(a, b)| (a - b).norm() < 1e-12));
+
+        // the wider filter blends in the neighbours the box filter ignores
+        let boxed = render(Camera::default(), &scene, &settings.clone().with_filter(Filter::default()));
+        assert_ne!(boxed.pixels, full.pixels);
+    }
+
+    #[test]
+    pub fn test_preview_sees_every_tile_of_the_final_image() {
+        let scene = sphere_in_view();
+        let settings = RenderSettings::default()
+            .with_resolution(20)
+            .with_samples(2)
+            .with_filter(Filter::tent(1.5))
+            .with_tiles(8, TileOrder::Hilbert);
+        let preview = Mutex::new(PixelBuffer::new(35, 20));
+        let image = render_with_preview(Camera::<f64>::default(), &scene, &settings, |tile, pixels| {
+            preview.lock().unwrap().merge_region(tile, pixels);
+        });
+        let preview = preview.into_inner().unwrap();
+        // the tent reaches across tile borders, so only the middle of each tile is final
+        let interior = (0..20).flat_map(|row| (0..35).map(move |col| (col, row))).filter(|(col, row)| (2..6).contains(&(col % 8)) && (2..6).contains(&(row % 8)));
+        for (col, row) in interior {
+            assert_eq!(preview[row * 35 + col], image[row * 35 + col]);
+        }
+        assert_ne!(preview.pixels, image.pixels);
+
+        // tiles don't change the picture, only the order it's made in, though merging the
+        // films of different tiles adds the splats up in a different order
+        let scanline = render(Camera::default(), &scene, &settings.clone().with_tiles(5, TileOrder::Scanline));
+        assert!(scanline.pixels.iter().zip(&image.pixels).all(|(a, b)| (a - b).norm() < 1e-12));
+
+        // with a box filter nothing crosses a border and the preview is the image
+        let boxed = settings.clone().with_filter(Filter::default());
+        let preview = Mutex::new(PixelBuffer::new(35, 20));
+        let image = render_with_preview(Camera::<f64>::default(), &scene, &boxed, |tile, pixels| {
+            preview.lock().unwrap().merge_region(tile, pixels);
+        });
+        assert_eq!(preview.into_inner().unwrap().pixels, image.pixels);
+    }
+
+    #[test]
+    pub fn test_tiles_trace_each_pixel_once() {
+        use crate::geometry::{Aabb, Intersection, Ray};
+
+        /// an empty scene that counts the rays sent into it
+        struct Counting(AtomicUsize);
+
+        impl Intersectable<f64> for Counting {
+            fn intersect(&self, _: Ray<f64>, _: f64, _: f64) -> Option<Intersection<f64>> {
+                self.0.fetch_add(1, Ordering::Relaxed);
+                None
+            }
+
+            fn bounding_box(&self) -> Option<Aabb<f64>> {
+                None
+            }
+        }
+
+        // small tiles and a wide filter, where re-tracing the margins would cost the most
+        let settings = RenderSettings::default()
+            .with_resolution(16)
+            .with_samples(2)
+            .with_filter(Filter::mitchell(2.0))
+            .with_tiles(4, TileOrder::Scanline);
+        let scene = Counting(AtomicUsize::new(0));
+        render(Camera::<f64>::default(), &scene, &settings);
+        assert_eq!(scene.0.load(Ordering::Relaxed), 28 * 16 * 2);
+
+        let mut progressive = ProgressiveRender::new(Camera::<f64>::default(), Counting(AtomicUsize::new(0)), settings);
+        progressive.pass(1);
+        assert_eq!(progressive.scene().0.load(Ordering::Relaxed), 28 * 16);
+    }
+
+    #[test]
+    pub fn test_renders_are_identical_whatever_the_thread_count() {
+        let scene = sphere_in_view();
+        let camera = Camera::<f64>::default().with_aperture(0.1).with_shutter(0.0, 1.0);
+        let settings = RenderSettings::default().with_resolution(12).with_samples(3);
+        let render_on = |threads: usize, settings: &RenderSettings| {
+            let pool = rayon::ThreadPoolBuilder::new().num_threads(threads).build().unwrap();
+            pool.install(|| render(camera.clone(), &scene, settings))
+        };
+
+        let single = render_on(1, &settings);
+        assert_eq!(single.pixels, render_on(4, &settings).pixels);
+        assert_eq!(single.pixels, render_scene_with_progress(camera.clone(), &scene, &settings, &ProgressBar::hidden()).0.pixels);
+        assert_ne!(single.pixels, render_on(4, &settings.clone().with_seed(1)).pixels);
+    }
+
+    #[test]
+    pub fn test_adaptive_sampling_spends_samples_on_noisy_pixels() {
+        use crate::geometry::{Plane, world::StaticWorld};
+
+        // light bouncing between the sphere and the ground is noisy, the sky above is smooth
+        let mut scene = StaticWorld::default();
+        scene.push_sphere(Sphere::new(point![0.0, 0.0, -2.0], 0.8, Material::default()));
+        scene.push_plane(Plane::new(point![0.0, -0.8, 0.0], Vector3::y_axis(), Material::default()));
+        let settings = RenderSettings::default()
+            .with_resolution(12)
+            .with_samples(64)
+            .with_adaptive(AdaptiveSampling::new(8, 0.05));
+        let (image, counts) = render_with_sample_counts(Camera::<f64>::default(), &scene, &settings);
+        assert_eq!(counts.as_tuple(), image.as_tuple());
+
+        let (width, height) = counts.as_tuple();
+        let centre = counts.counts[height / 2 * width + width / 2];
+        let corner = counts.counts[(height - 1) * width];
+        assert!(counts.counts.iter().all(|count| (8..=64).contains(count)));
+        assert!(centre > corner, "centre took {centre} samples, corner {corner}");
+        assert!(counts.average() < 64.0);
+        assert_eq!(counts.heatmap::<f64>().as_tuple(), (width, height));
+    }
+
     #[test]
     pub fn test_random_f32_ppm() {
         let mut rng = rand::thread_rng();
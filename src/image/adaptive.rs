@@ -0,0 +1,212 @@
+use nalgebra::Vector3;
+
+use crate::{Scalar, colour, image::{PixelBuffer, Rectangle}};
+
+/// luminances below this are treated as this bright when judging relative error, so that
+/// nearly black pixels don't soak up samples refining noise nobody can see
+const MIN_LUMINANCE: f64 = 1e-3;
+
+/// the fewest samples any pixel gets when sampling adaptively. a pixel whose first few
+/// samples all happened to miss a small or thin object in it looks smooth, with little or no
+/// variance, and would stop there with the object missing, so enough are taken that this
+/// only happens to objects covering a small fraction of a pixel
+pub const MIN_SAMPLES: usize = 8;
+
+/// the running mean of a pixel's samples along with the variance of their luminance,
+/// updated a sample at a time with Welford's algorithm so nothing needs storing per sample
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PixelStats<T>
+where
+    T: Scalar
+{
+    count: usize,
+    mean: Vector3<T>,
+    luminance_mean: T,
+    /// sum of squared differences from the mean luminance
+    luminance_m2: T,
+}
+
+impl<T> Default for PixelStats<T>
+where
+    T: Scalar
+{
+    fn default() -> Self {
+        Self { count: 0, mean: colour::black(), luminance_mean: T::zero(), luminance_m2: T::zero() }
+    }
+}
+
+impl<T> PixelStats<T>
+where
+    T: Scalar
+{
+    pub fn add(&mut self, sample: Vector3<T>) {
+        self.count += 1;
+        let n = T::from_float(self.count as f64);
+        self.mean += (sample - self.mean) / n;
+        let luminance = colour::luminance(&sample);
+        let delta = luminance - self.luminance_mean;
+        self.luminance_mean += delta / n;
+        self.luminance_m2 += delta * (luminance - self.luminance_mean);
+    }
+
+    pub fn count(&self) -> usize {
+        self.count
+    }
+
+    /// the raw running sums, (count, mean, mean luminance, sum of squared luminance differences)
+    pub(crate) fn parts(&self) -> (usize, Vector3<T>, T, T) {
+        (self.count, self.mean, self.luminance_mean, self.luminance_m2)
+    }
+
+    pub(crate) fn from_parts((count, mean, luminance_mean, luminance_m2): (usize, Vector3<T>, T, T)) -> Self {
+        Self { count, mean, luminance_mean, luminance_m2 }
+    }
+
+    pub fn mean(&self) -> Vector3<T> {
+        self.mean
+    }
+
+    /// the sample variance of the luminance, zero until there are two samples
+    pub fn variance(&self) -> T {
+        if self.count < 2 {
+            return T::zero();
+        }
+        self.luminance_m2 / T::from_float((self.count - 1) as f64)
+    }
+
+    /// the standard error of the mean luminance as a fraction of the mean
+    pub fn relative_error(&self) -> T {
+        if self.count == 0 {
+            return T::INF;
+        }
+        let standard_error = (self.variance() / T::from_float(self.count as f64)).sqrt();
+        standard_error / self.luminance_mean.max(T::from_float(MIN_LUMINANCE))
+    }
+}
+
+/// spends samples where they're needed. every pixel gets `min_samples`, then carries on
+/// only while the relative error of its mean luminance is above `threshold`, up to the
+/// render's sample count. the error is only estimated from the samples taken, so a pixel can
+/// converge early when its first samples all agree by chance, see [`MIN_SAMPLES`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AdaptiveSampling {
+    min_samples: usize,
+    threshold: f64,
+}
+
+impl AdaptiveSampling {
+    /// `threshold` is the acceptable standard error as a fraction of the pixel's brightness,
+    /// around 0.01 gives clean images. `min_samples` is raised to at least [`MIN_SAMPLES`] so
+    /// the variance estimate has a chance of seeing everything in the pixel
+    pub fn new(min_samples: usize, threshold: f64) -> Self {
+        Self { min_samples: min_samples.max(MIN_SAMPLES), threshold }
+    }
+
+    pub fn min_samples(&self) -> usize {
+        self.min_samples
+    }
+
+    pub fn threshold(&self) -> f64 {
+        self.threshold
+    }
+
+    /// whether a pixel with these stats has been sampled enough
+    pub fn converged<T: Scalar>(&self, stats: &PixelStats<T>) -> bool {
+        stats.count() >= self.min_samples && stats.relative_error() <= T::from_float(self.threshold)
+    }
+}
+
+/// how many samples each pixel of a render took, laid out like a [`PixelBuffer`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SampleCounts {
+    width: usize,
+    height: usize,
+    pub counts: Vec<usize>,
+}
+
+impl SampleCounts {
+    pub fn new(width: usize, height: usize, counts: Vec<usize>) -> Self {
+        assert_eq!(counts.len(), width * height, "sample counts don't match a {width}x{height} image");
+        Self { width, height, counts }
+    }
+
+    pub fn total(&self) -> usize {
+        self.counts.iter().sum()
+    }
+
+    pub fn max(&self) -> usize {
+        self.counts.iter().copied().max().unwrap_or(0)
+    }
+
+    /// mean samples per pixel across the whole image
+    pub fn average(&self) -> f64 {
+        if self.counts.is_empty() {
+            return 0.0;
+        }
+        self.total() as f64 / self.counts.len() as f64
+    }
+
+    /// an image of the counts running from black through blue, red and yellow to white
+    /// for the most sampled pixel
+    pub fn heatmap<T: Scalar>(&self) -> PixelBuffer<T> {
+        let stops = [colour::black(), colour::blue(), colour::red(), colour::yellow(), colour::white()];
+        let max = self.max().max(1) as f64;
+        let pixels = self.counts
+            .iter()
+            .map(|count| {
+                let position = *count as f64 / max * (stops.len() - 1) as f64;
+                let lower = (position as usize).min(stops.len() - 2);
+                let t = T::from_float(position - lower as f64);
+                stops[lower] * (T::one() - t) + stops[lower + 1] * t
+            })
+            .collect();
+        PixelBuffer::new_from_pixels(self.width, self.height, pixels)
+    }
+}
+
+impl Rectangle for SampleCounts {
+    fn width(&self) -> usize {
+        self.width
+    }
+
+    fn height(&self) -> usize {
+        self.height
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use nalgebra::vector;
+
+    use super::*;
+
+    #[test]
+    pub fn test_welford_matches_the_two_pass_variance() {
+        let samples = [0.2_f64, 0.9, 0.4, 0.4, 1.3, 0.0];
+        let mut stats = PixelStats::default();
+        for s in samples {
+            stats.add(vector![s, s, s]);
+        }
+        let mean = samples.iter().sum::<f64>() / 6.0;
+        let variance = samples.iter().map(|s| (s - mean).powi(2)).sum::<f64>() / 5.0;
+        assert_eq!(stats.count(), 6);
+        assert!((stats.mean().x - mean).abs() < 1e-12);
+        assert!((stats.variance() - variance).abs() < 1e-12);
+
+        let adaptive = AdaptiveSampling::new(4, 0.01);
+        assert_eq!(adaptive.min_samples(), MIN_SAMPLES);
+        assert!(!adaptive.converged(&stats));
+        // a few samples that all agree aren't enough to trust a variance of zero
+        let mut flat = PixelStats::default();
+        for _ in 0..4 {
+            flat.add(vector![0.5_f64, 0.5, 0.5]);
+        }
+        assert_eq!(flat.variance(), 0.0);
+        assert!(!adaptive.converged(&flat));
+        for _ in 4..MIN_SAMPLES {
+            flat.add(vector![0.5_f64, 0.5, 0.5]);
+        }
+        assert!(adaptive.converged(&flat));
+        assert!(!AdaptiveSampling::new(12, 0.01).converged(&flat));
+    }
+}
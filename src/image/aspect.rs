@@ -1,4 +1,6 @@
-#[derive(Debug, PartialEq, Eq)]
+use crate::fingerprint::{Fingerprint, Fingerprinter};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct AspectRatio {
     pub horizontal: usize,
     pub vertical: usize,
@@ -64,4 +66,11 @@ pub trait Rectangle {
     fn as_tuple(&self) -> (usize, usize) {
         (self.width(), self.height())
     }
-}
\ No newline at end of file
+}
+
+impl Fingerprint for AspectRatio {
+    fn fingerprint(&self, state: &mut Fingerprinter) {
+        state.usize(self.horizontal);
+        state.usize(self.vertical);
+    }
+}
@@ -2,13 +2,13 @@ use std::ops::{Index, IndexMut};
 use std::{fs, io, path};
 use std::io::Write;
 
-use nalgebra::{Vector3, vector};
+use nalgebra::Vector3;
 use tracing::debug;
 
 use crate::Scalar;
-use crate::image::{AspectRatio, Rectangle, AsPPM};
+use crate::image::{AspectRatio, Rectangle, AsPPM, Region};
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct PixelBuffer<T>
 where
     T: Scalar
@@ -46,6 +46,33 @@ where
         Self { width, height, aspect, pixels }
     }
 
+    /// packs two images of the same width into one, `over` on top of `under`.
+    /// stereo pairs are conventionally packed with the left eye over the right
+    pub fn new_over_under(over: &Self, under: &Self) -> Self {
+        assert_eq!(over.width, under.width, "over/under images must be the same width");
+        // rows run from the bottom of the image up
+        let pixels = under.pixels.iter().chain(over.pixels.iter()).copied().collect();
+        Self::new_from_pixels(over.width, over.height + under.height, pixels)
+    }
+
+    /// copies `region` out into a buffer of its own
+    pub fn crop(&self, region: Region) -> Self {
+        assert!(region.fits_within(self.width, self.height), "{region:?} is outside the {}x{} buffer", self.width, self.height);
+        let pixels = region.pixels().map(|(col, row)| self.pixels[row * self.width + col]).collect();
+        Self::new_from_pixels(region.width, region.height, pixels)
+    }
+
+    /// writes `crop`, a render of `region`, back into its place in this buffer
+    pub fn merge_region(&mut self, region: Region, crop: &Self) {
+        assert_eq!((crop.width, crop.height), (region.width, region.height), "crop doesn't match the size of its region");
+        assert!(region.fits_within(self.width, self.height), "{region:?} is outside the {}x{} buffer", self.width, self.height);
+        for row in 0..region.height {
+            let start = (region.y + row) * self.width + region.x;
+            self.pixels[start..start + region.width]
+                .copy_from_slice(&crop.pixels[row * crop.width..(row + 1) * crop.width]);
+        }
+    }
+
     pub fn aspect_ratio(&self) -> &AspectRatio {
         &self.aspect
     }
@@ -70,6 +97,12 @@ where
             }).collect()
     }
 
+    /// packed rgb like [`PixelBuffer::as_u32`] but with the top row first, the way windows want it
+    pub fn as_u32_top_down(&self) -> Vec<u32> {
+        let packed = self.as_u32();
+        packed.chunks(self.width.max(1)).rev().flatten().copied().collect()
+    }
+
     pub fn width(&self) -> usize {
         self.width
     }
@@ -119,7 +152,7 @@ where
                     &format!("{} {} {} ", pixel[0], pixel[1], pixel[2])
                 );
             }
-            buffer.push_str("\n");
+            buffer.push('\n');
         }
         buffer
     }
@@ -152,7 +185,7 @@ where
     T: Scalar
 {
     type Output = Vector3<T>;
-    fn index<'a>(&'a self, i: usize) -> &'a Self::Output {
+    fn index(&self, i: usize) -> &Self::Output {
         &self.pixels[i]
     }
 }
@@ -161,7 +194,7 @@ impl<T> IndexMut<usize> for PixelBuffer<T>
 where
     T: Scalar
 {
-    fn index_mut<'a>(&'a mut self, i: usize) -> &'a mut Self::Output {
+    fn index_mut(&mut self, i: usize) -> &mut Self::Output {
         &mut self.pixels[i]
     }
 }
\ No newline at end of file
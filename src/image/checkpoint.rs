@@ -0,0 +1,208 @@
+//! the binary format progressive renders are checkpointed in. everything is little endian,
+//! with floats widened to f64 so nothing is lost whatever precision the render used
+
+use std::io;
+
+use nalgebra::{vector, Vector3};
+
+use crate::{
+    Scalar,
+    image::{AdaptiveSampling, Filter, RenderSettings, TileOrder},
+    sampler::SamplerKind,
+};
+
+/// identifies a checkpoint file and the version of its layout
+pub(crate) const MAGIC: &[u8; 8] = b"RTCKPT\0\x03";
+
+/// bytes stored for each pixel, its statistics (a count, the mean, the luminance mean and the
+/// luminance m2) followed by its film weighted sum, weight and sum of weight magnitudes
+pub(crate) const PIXEL_BYTES: usize = (1 + 3 + 1 + 1 + 3 + 1 + 1) * 8;
+
+pub(crate) fn invalid(message: impl Into<String>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.into())
+}
+
+#[derive(Debug, Default)]
+pub(crate) struct Writer {
+    pub bytes: Vec<u8>,
+}
+
+impl Writer {
+    pub fn u64(&mut self, value: u64) {
+        self.bytes.extend_from_slice(&value.to_le_bytes());
+    }
+
+    pub fn usize(&mut self, value: usize) {
+        self.u64(value as u64);
+    }
+
+    pub fn f64(&mut self, value: f64) {
+        self.bytes.extend_from_slice(&value.to_le_bytes());
+    }
+
+    pub fn scalar<T: Scalar>(&mut self, value: T) {
+        self.f64(value.to_float());
+    }
+
+    pub fn vector<T: Scalar>(&mut self, value: &Vector3<T>) {
+        value.iter().for_each(|v| self.scalar(*v));
+    }
+
+    pub fn settings(&mut self, settings: &RenderSettings) {
+        self.usize(settings.vertical_resolution());
+        self.usize(settings.samples());
+        self.usize(settings.bounces());
+        self.u64(match settings.sampler() {
+            SamplerKind::Independent => 0,
+            SamplerKind::Stratified => 1,
+            SamplerKind::Halton => 2,
+            SamplerKind::Sobol => 3,
+        });
+        self.u64(settings.seed());
+        match settings.adaptive() {
+            Some(adaptive) => {
+                self.u64(1);
+                self.usize(adaptive.min_samples());
+                self.f64(adaptive.threshold());
+            },
+            None => self.u64(0),
+        }
+        let (kind, parameters) = match settings.filter() {
+            Filter::Box { radius } => (0, [radius, 0.0, 0.0]),
+            Filter::Tent { radius } => (1, [radius, 0.0, 0.0]),
+            Filter::Gaussian { radius, sigma } => (2, [radius, sigma, 0.0]),
+            Filter::Mitchell { radius, b, c } => (3, [radius, b, c]),
+            Filter::Lanczos { radius } => (4, [radius, 0.0, 0.0]),
+        };
+        self.u64(kind);
+        parameters.iter().for_each(|p| self.f64(*p));
+        self.usize(settings.tile_size());
+        self.u64(match settings.tile_order() {
+            TileOrder::Scanline => 0,
+            TileOrder::Spiral => 1,
+            TileOrder::Hilbert => 2,
+        });
+    }
+}
+
+pub(crate) struct Reader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    pub fn new(bytes: &'a [u8]) -> Self {
+        Self { bytes }
+    }
+
+    fn take<const N: usize>(&mut self) -> io::Result<[u8; N]> {
+        if self.bytes.len() < N {
+            return Err(invalid("checkpoint is truncated"));
+        }
+        let (head, rest) = self.bytes.split_at(N);
+        self.bytes = rest;
+        Ok(head.try_into().expect("split at the right length"))
+    }
+
+    pub fn magic(&mut self) -> io::Result<()> {
+        if &self.take::<8>()? != MAGIC {
+            return Err(invalid("not a checkpoint, or one from another version"));
+        }
+        Ok(())
+    }
+
+    pub fn u64(&mut self) -> io::Result<u64> {
+        self.take().map(u64::from_le_bytes)
+    }
+
+    pub fn usize(&mut self) -> io::Result<usize> {
+        let value = self.u64()?;
+        usize::try_from(value).map_err(|_| invalid(format!("{value} is too big for this machine")))
+    }
+
+    pub fn f64(&mut self) -> io::Result<f64> {
+        self.take().map(f64::from_le_bytes)
+    }
+
+    pub fn scalar<T: Scalar>(&mut self) -> io::Result<T> {
+        self.f64().map(T::from_float)
+    }
+
+    pub fn vector<T: Scalar>(&mut self) -> io::Result<Vector3<T>> {
+        Ok(vector![self.scalar()?, self.scalar()?, self.scalar()?])
+    }
+
+    pub fn settings(&mut self) -> io::Result<RenderSettings> {
+        let settings = RenderSettings::default()
+            .with_resolution(self.usize()?)
+            .with_samples(self.usize()?)
+            .with_bounces(self.usize()?)
+            .with_sampler(match self.u64()? {
+                0 => SamplerKind::Independent,
+                1 => SamplerKind::Stratified,
+                2 => SamplerKind::Halton,
+                3 => SamplerKind::Sobol,
+                other => return Err(invalid(format!("unknown sampler {other}"))),
+            })
+            .with_seed(self.u64()?);
+        let settings = match self.u64()? {
+            0 => settings,
+            _ => settings.with_adaptive(AdaptiveSampling::new(self.usize()?, self.f64()?)),
+        };
+        let kind = self.u64()?;
+        let [radius, a, b] = [self.f64()?, self.f64()?, self.f64()?];
+        let filter = match kind {
+            0 => Filter::Box { radius },
+            1 => Filter::Tent { radius },
+            2 => Filter::Gaussian { radius, sigma: a },
+            3 => Filter::Mitchell { radius, b: a, c: b },
+            4 => Filter::Lanczos { radius },
+            other => return Err(invalid(format!("unknown filter {other}"))),
+        };
+        if !filter.is_valid() {
+            return Err(invalid(format!("{filter:?} is too narrow")));
+        }
+        let tile_size = self.usize()?;
+        let tile_order = match self.u64()? {
+            0 => TileOrder::Scanline,
+            1 => TileOrder::Spiral,
+            2 => TileOrder::Hilbert,
+            other => return Err(invalid(format!("unknown tile order {other}"))),
+        };
+        Ok(settings.with_filter(filter).with_tiles(tile_size, tile_order))
+    }
+
+    /// whether everything has been read
+    pub fn finished(&self) -> bool {
+        self.bytes.is_empty()
+    }
+
+    /// how many bytes are left to read
+    pub fn remaining(&self) -> usize {
+        self.bytes.len()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    pub fn test_settings_survive_a_round_trip() {
+        let settings = RenderSettings::default()
+            .with_resolution(480)
+            .with_samples(256)
+            .with_bounces(12)
+            .with_sampler(SamplerKind::Halton)
+            .with_seed(42)
+            .with_adaptive(AdaptiveSampling::new(16, 0.02))
+            .with_filter(Filter::mitchell(2.0))
+            .with_tiles(24, TileOrder::Spiral);
+        let mut writer = Writer::default();
+        writer.settings(&settings);
+        let mut reader = Reader::new(&writer.bytes);
+        assert_eq!(reader.settings().unwrap(), settings);
+        assert!(reader.finished());
+
+        assert!(Reader::new(&writer.bytes[..10]).settings().is_err());
+    }
+}
@@ -0,0 +1,195 @@
+use nalgebra::Vector3;
+
+use crate::{Scalar, colour, image::{Filter, PixelBuffer, Region}};
+
+/// the smallest a pixel's total weight is taken to be when resolving, as a fraction of the
+/// sum of the sizes of its weights. only positive and negative weights cancelling can bring it
+/// under that, and capping how far they can cancel caps the pixel at ten times its brightest
+/// sample
+pub const MIN_NET_WEIGHT: f64 = 0.1;
+
+/// where samples are splatted onto the image. each pixel keeps the filter weighted sum of
+/// every sample that reached it and the sum of those weights, and its value is their ratio
+#[derive(Debug, Clone, PartialEq)]
+pub struct Film<T>
+where
+    T: Scalar
+{
+    region: Region,
+    pub sums: Vec<Vector3<T>>,
+    pub weights: Vec<T>,
+    /// the sum of the absolute values of the weights, the same as `weights` for filters
+    /// without negative lobes
+    pub magnitudes: Vec<T>,
+}
+
+impl<T> Film<T>
+where
+    T: Scalar
+{
+    /// an empty film covering `region` of the frame
+    pub fn new(region: Region) -> Self {
+        Self {
+            region,
+            sums: vec![colour::black(); region.area()],
+            weights: vec![T::zero(); region.area()],
+            magnitudes: vec![T::zero(); region.area()],
+        }
+    }
+
+    pub fn region(&self) -> Region {
+        self.region
+    }
+
+    /// adds a sample that landed at `position`, measured in pixels from the bottom left of the
+    /// frame, to every pixel of the film the filter reaches
+    pub fn add_sample(&mut self, filter: &Filter, position: (f64, f64), radiance: Vector3<T>) {
+        if self.region.area() == 0 {
+            return;
+        }
+        let radius = filter.radius();
+        // pixel centres sit half a pixel in from their corners
+        let (x, y) = (position.0 - 0.5, position.1 - 0.5);
+        let reach = |centre: f64, start: usize, length: usize| {
+            let low = (centre - radius).floor().max(start as f64) as usize;
+            let high = ((centre + radius).ceil().max(0.0) as usize).min(start + length - 1);
+            low..=high
+        };
+        for row in reach(y, self.region.y, self.region.height) {
+            for col in reach(x, self.region.x, self.region.width) {
+                let weight = filter.weight(x - col as f64, y - row as f64);
+                if weight != 0.0 {
+                    let index = (row - self.region.y) * self.region.width + col - self.region.x;
+                    let weight = T::from_float(weight);
+                    self.sums[index] += radiance * weight;
+                    self.weights[index] += weight;
+                    self.magnitudes[index] += weight.abs();
+                }
+            }
+        }
+    }
+
+    /// adds in everything splatted onto `other` where it overlaps this film
+    pub fn merge(&mut self, other: &Self) {
+        let Some(overlap) = self.region.intersection(&other.region) else {
+            return;
+        };
+        for (col, row) in overlap.pixels() {
+            let to = (row - self.region.y) * self.region.width + col - self.region.x;
+            let from = (row - other.region.y) * other.region.width + col - other.region.x;
+            self.sums[to] += other.sums[from];
+            self.weights[to] += other.weights[from];
+            self.magnitudes[to] += other.magnitudes[from];
+        }
+    }
+
+    /// the image so far multiplied by `scale`, pixels nothing has reached are left black.
+    /// filters with negative lobes can leave a pixel with only a few samples weighing next to
+    /// nothing, or less than nothing, and dividing by that would turn it into a firefly or
+    /// flip its sign. so weights are kept to at least [`MIN_NET_WEIGHT`] of their magnitudes,
+    /// keeping their sign, and anything still negative is clamped to black. weights that don't
+    /// cancel are never touched, whatever the filter's scale, so a pixel of samples that all
+    /// agree resolves to exactly their value
+    pub fn resolve(&self, scale: T) -> PixelBuffer<T> {
+        let min_net_weight = T::from_float(MIN_NET_WEIGHT);
+        let pixels = self.sums
+            .iter()
+            .zip(&self.weights)
+            .zip(&self.magnitudes)
+            .map(|((sum, weight), magnitude)| {
+                if *magnitude == T::zero() {
+                    return colour::black();
+                }
+                let floor = *magnitude * min_net_weight;
+                let weight = if *weight < T::zero() { weight.min(-floor) } else { weight.max(floor) };
+                (sum * scale / weight).map(|channel| channel.max(T::zero()))
+            })
+            .collect();
+        PixelBuffer::new_from_pixels(self.region.width, self.region.height, pixels)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use nalgebra::vector;
+
+    use super::*;
+
+    #[test]
+    pub fn test_wide_filters_spread_samples_to_neighbours() {
+        let mut boxed = Film::<f64>::new(Region::full(3, 3));
+        boxed.add_sample(&Filter::default(), (1.2, 1.9), vector![1.0, 1.0, 1.0]);
+        // a half pixel box only ever reaches the pixel the sample is in
+        assert_eq!(boxed.weights.iter().filter(|w| **w > 0.0).count(), 1);
+        assert_eq!(boxed.weights[4], 1.0);
+
+        let mut tent = Film::<f64>::new(Region::full(3, 3));
+        tent.add_sample(&Filter::tent(1.5), (1.5, 1.5), vector![1.0, 1.0, 1.0]);
+        assert_eq!(tent.weights.iter().filter(|w| **w > 0.0).count(), 9);
+        assert!(tent.weights[4] > tent.weights[0]);
+
+        // a film over part of the frame only keeps its share
+        let mut part = Film::<f64>::new(Region::new(0, 0, 1, 3));
+        part.merge(&tent);
+        assert_eq!(part.weights, vec![tent.weights[0], tent.weights[3], tent.weights[6]]);
+    }
+
+    #[test]
+    pub fn test_a_lone_sample_resolves_to_itself_under_every_filter() {
+        let filters = [
+            Filter::boxed(0.5),
+            Filter::tent(0.5),
+            Filter::tent(1.5),
+            Filter::gaussian(0.5),
+            Filter::gaussian(1.5),
+            Filter::mitchell(0.5),
+            Filter::mitchell(2.0),
+            Filter::lanczos(0.5),
+            Filter::lanczos(3.0),
+        ];
+        for filter in filters {
+            for step in 0..100 {
+                let position = ((step % 10) as f64 / 10.0 + 0.05, (step / 10) as f64 / 10.0 + 0.05);
+                let mut film = Film::<f64>::new(Region::full(1, 1));
+                film.add_sample(&filter, position, vector![0.25, 0.5, 1.0]);
+                let pixel = film.resolve(1.0)[0];
+                assert!((pixel - vector![0.25, 0.5, 1.0]).norm() < 1e-12, "{filter:?} at {position:?} gave {pixel:?}");
+            }
+        }
+    }
+
+    #[test]
+    pub fn test_cancelling_weights_are_capped() {
+        // a sample in the pixel and one in the negative lobe that all but cancels it
+        let filter = Filter::mitchell(2.0);
+        let mut film = Film::<f64>::new(Region::full(1, 1));
+        film.add_sample(&filter, (0.5, 0.5), vector![1.0, 1.0, 1.0]);
+        let inside = film.weights[0];
+        let lobe = filter.weight(1.5, 0.0);
+        let mut lobe_samples = 0;
+        while film.weights[0] + lobe > inside * 0.01 {
+            film.add_sample(&filter, (2.0, 0.5), vector![0.0, 0.0, 0.0]);
+            lobe_samples += 1;
+        }
+        assert!(lobe_samples > 0);
+        let pixel = film.resolve(1.0)[0];
+        assert!(pixel.iter().all(|channel| *channel <= 1.0 / MIN_NET_WEIGHT + 1e-9), "{pixel:?}");
+    }
+
+    #[test]
+    pub fn test_resolving_never_amplifies_or_negates_a_sample() {
+        // a lone sample of white swept out across the lobes of the sharper filters, where the
+        // pixel's weight passes through zero
+        for filter in [Filter::mitchell(2.0), Filter::lanczos(3.0)] {
+            let mut crossed_zero = false;
+            for step in 0..300 {
+                let mut film = Film::<f64>::new(Region::full(1, 1));
+                film.add_sample(&filter, (0.5 + step as f64 / 100.0, 0.5), vector![1.0, 1.0, 1.0]);
+                crossed_zero |= film.weights[0] < 0.0;
+                let pixel = film.resolve(1.0)[0];
+                assert!(pixel.iter().all(|channel| (0.0..=1.0).contains(channel)), "{filter:?} gave {pixel:?}");
+            }
+            assert!(crossed_zero, "{filter:?}");
+        }
+    }
+}
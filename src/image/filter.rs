@@ -0,0 +1,162 @@
+use std::f64::consts::PI;
+
+/// how samples are spread over the pixels around where they land. each filter is separable,
+/// the weight of a sample offset (x, y) pixels from a pixel's centre being `f(x) * f(y)`,
+/// and samples only reach pixels whose centres are within `radius` on both axes
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Filter {
+    /// every sample within the radius counts equally. a radius of half a pixel is a plain
+    /// average of the samples taken for each pixel
+    Box { radius: f64 },
+    /// weights fall off linearly to zero at the radius
+    Tent { radius: f64 },
+    /// a gaussian with standard deviation `sigma`, shifted down to reach zero at the radius
+    Gaussian { radius: f64, sigma: f64 },
+    /// the Mitchell-Netravali cubic stretched over the radius. `b` and `c` trade blurring
+    /// against ringing, a third each is the usual balance
+    Mitchell { radius: f64, b: f64, c: f64 },
+    /// a sinc windowed by a wider sinc, with as many lobes as the radius. the sharpest of
+    /// these, but it rings around hard edges
+    Lanczos { radius: f64 },
+}
+
+/// the narrowest any filter can be. anything under half a pixel leaves gaps along the edges
+/// of each pixel where samples land in no pixel at all
+pub const MIN_RADIUS: f64 = 0.5;
+
+impl Default for Filter {
+    fn default() -> Self {
+        Filter::Box { radius: 0.5 }
+    }
+}
+
+impl Filter {
+    pub fn boxed(radius: f64) -> Self {
+        Filter::Box { radius }.checked()
+    }
+
+    pub fn tent(radius: f64) -> Self {
+        Filter::Tent { radius }.checked()
+    }
+
+    /// a gaussian that falls to about 1% of its peak at the radius
+    pub fn gaussian(radius: f64) -> Self {
+        Filter::Gaussian { radius, sigma: radius / 3.0 }.checked()
+    }
+
+    /// the Mitchell-Netravali filter with the recommended b = c = 1/3
+    pub fn mitchell(radius: f64) -> Self {
+        Filter::Mitchell { radius, b: 1.0 / 3.0, c: 1.0 / 3.0 }.checked()
+    }
+
+    pub fn lanczos(radius: f64) -> Self {
+        Filter::Lanczos { radius }.checked()
+    }
+
+    /// whether the radius is at least [`MIN_RADIUS`], so every sample lands on some pixel
+    pub fn is_valid(&self) -> bool {
+        self.radius() >= MIN_RADIUS
+    }
+
+    fn checked(self) -> Self {
+        assert!(self.is_valid(), "{self:?} is narrower than the minimum radius of {MIN_RADIUS}");
+        self
+    }
+
+    pub fn radius(&self) -> f64 {
+        match *self {
+            Filter::Box { radius }
+            | Filter::Tent { radius }
+            | Filter::Gaussian { radius, .. }
+            | Filter::Mitchell { radius, .. }
+            | Filter::Lanczos { radius } => radius,
+        }
+    }
+
+    /// how many pixels beyond its own a sample can reach
+    pub fn margin(&self) -> usize {
+        (self.radius() - 0.5).max(0.0).ceil() as usize
+    }
+
+    /// the weight of a sample offset (`x`, `y`) pixels from a pixel's centre, which can be
+    /// negative for the filters with ringing lobes
+    pub fn weight(&self, x: f64, y: f64) -> f64 {
+        self.weight_1d(x) * self.weight_1d(y)
+    }
+
+    fn weight_1d(&self, x: f64) -> f64 {
+        // half open, so a sample exactly between two pixels only lands in one of them
+        let radius = self.radius();
+        if x < -radius || x >= radius {
+            return 0.0;
+        }
+        match *self {
+            Filter::Box { .. } => 1.0,
+            Filter::Tent { radius } => radius - x.abs(),
+            Filter::Gaussian { radius, sigma } => {
+                let gaussian = |x: f64| (-x * x / (2.0 * sigma * sigma)).exp();
+                (gaussian(x) - gaussian(radius)).max(0.0)
+            },
+            Filter::Mitchell { radius, b, c } => mitchell_netravali(2.0 * x / radius, b, c),
+            Filter::Lanczos { radius } => sinc(x) * sinc(x / radius),
+        }
+    }
+}
+
+/// the Mitchell-Netravali cubic over -2..2
+fn mitchell_netravali(x: f64, b: f64, c: f64) -> f64 {
+    let x = x.abs();
+    let (x2, x3) = (x * x, x * x * x);
+    if x < 1.0 {
+        ((12.0 - 9.0 * b - 6.0 * c) * x3 + (-18.0 + 12.0 * b + 6.0 * c) * x2 + (6.0 - 2.0 * b)) / 6.0
+    } else if x < 2.0 {
+        ((-b - 6.0 * c) * x3 + (6.0 * b + 30.0 * c) * x2 + (-12.0 * b - 48.0 * c) * x + (8.0 * b + 24.0 * c)) / 6.0
+    } else {
+        0.0
+    }
+}
+
+fn sinc(x: f64) -> f64 {
+    if x.abs() < 1e-5 {
+        return 1.0;
+    }
+    (PI * x).sin() / (PI * x)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    pub fn test_filters_peak_at_the_centre_and_vanish_at_the_radius() {
+        let filters = [
+            Filter::Box { radius: 0.5 },
+            Filter::tent(1.0),
+            Filter::gaussian(1.5),
+            Filter::mitchell(2.0),
+            Filter::lanczos(3.0),
+        ];
+        for filter in filters {
+            let radius = filter.radius();
+            let centre = filter.weight(0.0, 0.0);
+            assert!(centre > 0.0, "{filter:?}");
+            assert!(filter.weight(0.3, -0.2) <= centre, "{filter:?}");
+            assert_eq!(filter.weight(radius, 0.0), 0.0, "{filter:?}");
+            assert_eq!(filter.weight(0.0, -radius - 0.1), 0.0, "{filter:?}");
+        }
+        // the sharper filters dip below zero on their way out
+        assert!(Filter::mitchell(2.0).weight(1.5, 0.0) < 0.0);
+        assert!(Filter::lanczos(3.0).weight(1.5, 0.0) < 0.0);
+        assert_eq!(Filter::default().margin(), 0);
+        assert_eq!(Filter::mitchell(2.0).margin(), 2);
+        assert!(Filter::boxed(0.5).is_valid());
+        assert!(!Filter::Box { radius: 0.4 }.is_valid());
+        assert!(!Filter::Tent { radius: f64::NAN }.is_valid());
+    }
+
+    #[test]
+    #[should_panic]
+    pub fn test_narrow_boxes_are_rejected() {
+        Filter::boxed(0.25);
+    }
+}
@@ -0,0 +1,289 @@
+use std::{
+    io,
+    ops::ControlFlow,
+    path::{Path, PathBuf},
+    sync::{atomic::{AtomicBool, Ordering}, mpsc},
+    thread,
+    time::Duration,
+};
+
+use minifb::{Key, KeyRepeat, Window, WindowOptions};
+use rayon::prelude::*;
+use tracing::{info, warn};
+
+use crate::{
+    Scalar,
+    Camera,
+    colour,
+    geometry::Intersectable,
+    image::{AsPPM, PixelBuffer, ProgressiveRender, RenderSettings},
+    sampler::{IndependentSampler, Sampler},
+};
+
+/// what the preview window shows
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Channel {
+    /// the render itself
+    #[default]
+    Beauty,
+    /// the surface normal at the first hit, mapped from -1..1 to 0..1
+    Normals,
+    /// the colour of the first surface hit, without any lighting
+    Albedo,
+}
+
+impl Channel {
+    /// the channel after this one, wrapping back round to the render
+    pub fn next(&self) -> Self {
+        match self {
+            Channel::Beauty => Channel::Normals,
+            Channel::Normals => Channel::Albedo,
+            Channel::Albedo => Channel::Beauty,
+        }
+    }
+}
+
+/// one ray through the centre of each pixel, recording `channel` for whatever it hits first.
+/// None for [`Channel::Beauty`], which needs a proper render
+pub fn render_guide<T, Scene>(camera: &Camera<T>, scene: &Scene, settings: &RenderSettings, channel: Channel) -> Option<PixelBuffer<T>>
+where
+    T: Scalar,
+    Scene: Intersectable<T> + Sync
+{
+    if channel == Channel::Beauty {
+        return None;
+    }
+    let height = settings.vertical_resolution();
+    let width = camera.aspect_ratio().width_from_height(height);
+    let pixels = (0..height).into_par_iter().flat_map_iter(|row| {
+        let mut sampler = IndependentSampler::new(settings.seed());
+        (0..width).map(move |col| {
+            Sampler::<T>::start_pixel_sample(&mut sampler, (col, row), 0);
+            let u = T::from_float((col as f64 + 0.5) / width as f64);
+            let v = T::from_float((row as f64 + 0.5) / height as f64);
+            let hit = camera
+                .sample_ray(u, v, &mut sampler)
+                .and_then(|ray| scene.intersect(ray, T::from_float(0.00001), T::INF));
+            match (hit, channel) {
+                (Some(hit), Channel::Normals) => (hit.normal().into_inner() + colour::white()) * T::HALF,
+                (Some(hit), _) => hit.material().albedo(),
+                (None, _) => colour::black(),
+            }
+        })
+    })
+    .collect();
+    Some(PixelBuffer::new_from_pixels(width, height, pixels))
+}
+
+/// whether there's a display to open a window on. minifb aborts the whole process rather
+/// than returning an error when it can't reach an X server, so check before asking it
+fn display_available() -> bool {
+    if cfg!(all(unix, not(target_os = "macos"))) {
+        ["DISPLAY", "WAYLAND_DISPLAY"].iter().any(|var| std::env::var_os(var).is_some_and(|value| !value.is_empty()))
+    } else {
+        true
+    }
+}
+
+/// how a [`Preview`] ended
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PreviewOutcome {
+    /// passes rendered while the preview was up
+    pub passes: usize,
+    /// whether the render was stopped before it finished
+    pub aborted: bool,
+}
+
+/// a window showing a progressive render as it refines. `s` saves what's on screen, `c` or
+/// tab flips between the render, normals and albedo, and escape or `q` stops the render
+/// after the pass it's on. when there's no display to open the window on, the render
+/// carries on without it, saving the image after every pass instead
+#[derive(Debug, Clone)]
+pub struct Preview<T>
+where
+    T: Scalar
+{
+    title: String,
+    save_path: PathBuf,
+    gamma: T,
+    samples_per_pass: usize,
+    headless: bool,
+}
+
+impl<T> Preview<T>
+where
+    T: Scalar
+{
+    /// a preview saving to `save_path`. other channels are saved next to it with the channel
+    /// added to the name
+    pub fn new<P: AsRef<Path>>(save_path: P) -> Self {
+        Self {
+            title: String::from("raytracing"),
+            save_path: save_path.as_ref().to_path_buf(),
+            gamma: T::HALF,
+            samples_per_pass: 4,
+            headless: false,
+        }
+    }
+
+    pub fn with_title(self, title: &str) -> Self {
+        Self { title: title.to_string(), ..self }
+    }
+
+    /// exponent applied to the render before it's shown or saved, see [`PixelBuffer::apply_gamma`]
+    pub fn with_gamma(self, gamma: T) -> Self {
+        Self { gamma, ..self }
+    }
+
+    pub fn with_samples_per_pass(self, samples_per_pass: usize) -> Self {
+        Self { samples_per_pass, ..self }
+    }
+
+    /// never open a window, as if there were no display
+    pub fn with_headless(self, headless: bool) -> Self {
+        Self { headless, ..self }
+    }
+
+    /// where `channel` is saved
+    pub fn channel_path(&self, channel: Channel) -> PathBuf {
+        let suffix = match channel {
+            Channel::Beauty => return self.save_path.clone(),
+            Channel::Normals => "normals",
+            Channel::Albedo => "albedo",
+        };
+        let stem = self.save_path.file_stem().and_then(|stem| stem.to_str()).unwrap_or("render");
+        self.save_path.with_file_name(format!("{stem}_{suffix}.ppm"))
+    }
+
+    /// runs `render` to completion, or until it's aborted, showing it as it goes. the window
+    /// stays open on the finished image until it's closed. the render is saved to the save
+    /// path once it stops either way
+    pub fn show<Scene>(&self, render: &mut ProgressiveRender<T, Scene>) -> io::Result<PreviewOutcome>
+    where
+        Scene: Intersectable<T> + Sync + Send
+    {
+        let (width, height) = (render.image().width(), render.image().height());
+        let window = if self.headless {
+            None
+        } else if !display_available() {
+            warn!("no display for a preview window, carrying on without one");
+            None
+        } else {
+            Window::new(&self.title, width, height, WindowOptions::default())
+                .map_err(|err| warn!("couldn't open a preview window, carrying on without one: {err}"))
+                .ok()
+        };
+        let Some(mut window) = window else {
+            let passes = render.run_with_snapshots(self.samples_per_pass, &self.save_path, self.gamma)?;
+            info!("saved {} at {:.1} samples per pixel", self.save_path.display(), render.samples_per_pixel());
+            return Ok(PreviewOutcome { passes, aborted: false });
+        };
+        window.limit_update_rate(Some(Duration::from_millis(33)));
+
+        let normals = render_guide(render.camera(), render.scene(), render.settings(), Channel::Normals).expect("normals are a guide");
+        let albedo = render_guide(render.camera(), render.scene(), render.settings(), Channel::Albedo).expect("albedo is a guide");
+        let mut beauty = render.image();
+        let mut channel = Channel::Beauty;
+        let abort = AtomicBool::new(false);
+        let (sender, receiver) = mpsc::channel();
+
+        thread::scope(|scope| {
+            let abort = &abort;
+            let (samples_per_pass, save_path, gamma) = (self.samples_per_pass, &self.save_path, self.gamma);
+            let worker = scope.spawn(move || -> io::Result<usize> {
+                let passes = render.run(samples_per_pass, |render| {
+                    // the window may already have gone, in which case nobody needs the image
+                    let _ = sender.send(render.image());
+                    if abort.load(Ordering::Relaxed) { ControlFlow::Break(()) } else { ControlFlow::Continue(()) }
+                });
+                // the render is saved however it ended, whether or not anyone pressed save
+                render.write_snapshot(save_path, gamma)?;
+                info!("saved {} at {:.1} samples per pixel", save_path.display(), render.samples_per_pixel());
+                Ok(passes)
+            });
+
+            let mut result = Ok(());
+            while window.is_open() {
+                if let Some(latest) = receiver.try_iter().last() {
+                    beauty = latest;
+                }
+                if window.is_key_pressed(Key::Escape, KeyRepeat::No) || window.is_key_pressed(Key::Q, KeyRepeat::No) {
+                    break;
+                }
+                if window.is_key_pressed(Key::C, KeyRepeat::No) || window.is_key_pressed(Key::Tab, KeyRepeat::No) {
+                    channel = channel.next();
+                    info!("showing {channel:?}");
+                }
+                let shown = match channel {
+                    Channel::Beauty => beauty.clone().apply_gamma(self.gamma),
+                    Channel::Normals => normals.clone(),
+                    Channel::Albedo => albedo.clone().apply_gamma(self.gamma),
+                };
+                if window.is_key_pressed(Key::S, KeyRepeat::No) {
+                    result = self.save(&shown, channel);
+                    if result.is_err() {
+                        break;
+                    }
+                }
+                if let Err(err) = window.update_with_buffer(&shown.as_u32_top_down(), width, height) {
+                    warn!("preview window stopped updating: {err}");
+                    break;
+                }
+            }
+
+            // leaving the window early stops the render once the current pass is done
+            let aborted = !worker.is_finished();
+            abort.store(true, Ordering::Relaxed);
+            let passes = worker.join().expect("render thread panicked")?;
+            result.map(|_| PreviewOutcome { passes, aborted })
+        })
+    }
+
+    fn save(&self, image: &PixelBuffer<T>, channel: Channel) -> io::Result<()> {
+        let path = self.channel_path(channel);
+        let filename = path.to_str()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "save path isn't valid unicode"))?;
+        image.write_ppm(filename)?;
+        info!("saved {}", path.display());
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use nalgebra::point;
+
+    use super::*;
+    use crate::{Material, image::test::sphere_in_view};
+
+    #[test]
+    pub fn test_guides_see_the_first_surface() {
+        let scene = sphere_in_view();
+        let settings = RenderSettings::default().with_resolution(9);
+        let camera = Camera::<f64>::default();
+        let normals = render_guide(&camera, &scene, &settings, Channel::Normals).unwrap();
+        let albedo = render_guide(&camera, &scene, &settings, Channel::Albedo).unwrap();
+        assert!(render_guide(&camera, &scene, &settings, Channel::Beauty).is_none());
+
+        // the middle of the sphere faces straight back at the camera
+        let centre = 4 * normals.width() + normals.width() / 2;
+        assert!((normals.pixels[centre] - point![0.5, 0.5, 1.0].coords).norm() < 0.1);
+        assert_eq!(albedo.pixels[centre], Material::default().albedo());
+        assert_eq!(albedo.pixels[0], colour::black());
+        assert_eq!(Channel::Albedo.next(), Channel::Beauty);
+    }
+
+    #[test]
+    pub fn test_headless_preview_saves_each_pass() {
+        let path = std::env::temp_dir().join(format!("rt_preview_{}.ppm", std::process::id()));
+        let scene = sphere_in_view();
+        let settings = RenderSettings::default().with_resolution(6).with_samples(4);
+        let mut render = ProgressiveRender::new(Camera::<f64>::default(), &scene, settings);
+
+        let preview = Preview::new(&path).with_samples_per_pass(2).with_headless(true);
+        assert_eq!(preview.show(&mut render).unwrap(), PreviewOutcome { passes: 2, aborted: false });
+        assert!(path.exists());
+        assert!(preview.channel_path(Channel::Normals).ends_with(format!("rt_preview_{}_normals.ppm", std::process::id())));
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,416 @@
+use std::{fs, io, ops::ControlFlow, path::Path, time::{Duration, Instant}};
+
+use tracing::{debug, info};
+
+use crate::{
+    Scalar,
+    Camera,
+    fingerprint::{Fingerprint, Fingerprinter},
+    geometry::Intersectable,
+    image::{AsPPM, Film, PixelBuffer, PixelStats, Region, RenderSettings, SampleCounts, Tracer, schedule_tiles, write_replacing},
+    image::checkpoint::{MAGIC, PIXEL_BYTES, Reader, Writer, invalid},
+};
+
+/// what a time budgeted render managed, from [`ProgressiveRender::run_for`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BudgetReport {
+    pub passes: usize,
+    /// the average samples per pixel reached
+    pub samples_per_pixel: f64,
+    pub elapsed: Duration,
+}
+
+/// renders in passes over the whole image, each adding a few more samples to every pixel,
+/// so there's a usable image to look at long before the render finishes
+#[derive(Debug)]
+pub struct ProgressiveRender<T, Scene>
+where
+    T: Scalar
+{
+    camera: Camera<T>,
+    scene: Scene,
+    settings: RenderSettings,
+    width: usize,
+    height: usize,
+    /// the statistics of every pixel's own samples, bottom row first like [`PixelBuffer`]
+    pixels: Vec<PixelStats<T>>,
+    /// every sample so far, splatted through the settings' filter
+    film: Film<T>,
+    passes: usize,
+}
+
+impl<T, Scene> ProgressiveRender<T, Scene>
+where
+    T: Scalar,
+    Scene: Intersectable<T> + Sync
+{
+    /// an empty render of `scene`, with `settings` giving the resolution and the total samples
+    /// per pixel that [`ProgressiveRender::run`] works towards
+    pub fn new(camera: Camera<T>, scene: Scene, settings: RenderSettings) -> Self {
+        let height = settings.vertical_resolution();
+        let width = camera.aspect_ratio().width_from_height(height);
+        let pixels = vec![PixelStats::default(); width * height];
+        Self { camera, scene, settings, width, height, pixels, film: Film::new(Region::full(width, height)), passes: 0 }
+    }
+
+    /// adds up to `samples` samples to every pixel, fewer to pixels that have converged
+    /// when sampling adaptively
+    pub fn pass(&mut self, samples: usize) {
+        self.pass_up_to(samples, usize::MAX, || true);
+    }
+
+    /// a pass that leaves pixels that already have `cap` samples alone. `keep_going` is asked
+    /// before each tile is started, and once it says no the rest of the pass is skipped.
+    /// returns whether the whole pass was made
+    fn pass_up_to<K>(&mut self, samples: usize, cap: usize, keep_going: K) -> bool
+    where
+        K: Fn() -> bool + Sync
+    {
+        let frame = Region::full(self.width, self.height);
+        let tracer = Tracer::new(&self.camera, &self.scene, &self.settings, frame);
+        let tiles = self.settings.tile_order().tiles(frame, self.settings.tile_size());
+        let (pixels, width) = (&self.pixels, self.width);
+        let finished = schedule_tiles(&tiles, |tile| {
+            keep_going().then(|| {
+                let mut stats: Vec<_> = tile.pixels().map(|(col, row)| pixels[row * width + col]).collect();
+                let film = tracer.render_tile(tile, &mut stats, samples, cap);
+                (film, stats)
+            })
+        });
+        // merging in tile order keeps the image the same whatever the thread count
+        let mut complete = true;
+        for (tile, result) in finished {
+            let Some((film, stats)) = result else {
+                complete = false;
+                continue;
+            };
+            self.film.merge(&film);
+            for ((col, row), stats) in tile.pixels().zip(stats) {
+                self.pixels[row * self.width + col] = stats;
+            }
+        }
+        self.passes += 1;
+        debug!("finished pass {} at {:.1} samples per pixel", self.passes, self.samples_per_pixel());
+        complete
+    }
+
+    /// makes passes of `samples_per_pass` until every pixel has had the settings' full sample
+    /// count, calling `on_pass` after each. `on_pass` can stop the render early by returning
+    /// [`ControlFlow::Break`]. returns the number of passes made
+    pub fn run<F>(&mut self, samples_per_pass: usize, mut on_pass: F) -> usize
+    where
+        F: FnMut(&Self) -> ControlFlow<()>
+    {
+        assert!(samples_per_pass > 0, "progressive passes need at least one sample");
+        let start = self.passes;
+        while !self.finished() {
+            self.pass_up_to(samples_per_pass, self.settings.samples(), || true);
+            if on_pass(self).is_break() {
+                break;
+            }
+        }
+        self.passes - start
+    }
+
+    /// makes passes of `samples_per_pass` for as long as `budget` allows, ignoring the
+    /// settings' sample count. the budget is checked before each tile, so the render stops
+    /// at most a tile's time late, with the last pass cut short where the budget ran out.
+    /// `on_pass` is called after each pass, including a cut short one, as in
+    /// [`ProgressiveRender::run`]
+    pub fn run_for<F>(&mut self, budget: Duration, samples_per_pass: usize, on_pass: F) -> BudgetReport
+    where
+        F: FnMut(&Self) -> ControlFlow<()>
+    {
+        let start = Instant::now();
+        self.run_for_on(budget, samples_per_pass, || start.elapsed(), on_pass)
+    }
+
+    /// [`ProgressiveRender::run_for`] timed by `elapsed`, which gives the time since the render
+    /// started, so tests can run on a clock of their own
+    pub(crate) fn run_for_on<C, F>(&mut self, budget: Duration, samples_per_pass: usize, elapsed: C, mut on_pass: F) -> BudgetReport
+    where
+        C: Fn() -> Duration + Sync,
+        F: FnMut(&Self) -> ControlFlow<()>
+    {
+        assert!(samples_per_pass > 0, "progressive passes need at least one sample");
+        let passes = self.passes;
+        loop {
+            let complete = self.pass_up_to(samples_per_pass, usize::MAX, || elapsed() < budget);
+            if on_pass(self).is_break() || !complete || self.converged() || elapsed() >= budget {
+                break;
+            }
+        }
+        let report = BudgetReport { passes: self.passes - passes, samples_per_pixel: self.samples_per_pixel(), elapsed: elapsed() };
+        info!("reached {:.1} samples per pixel in {} passes taking {:.1?}", report.samples_per_pixel, report.passes, report.elapsed);
+        report
+    }
+
+    /// like [`ProgressiveRender::run`], overwriting `path` with the image so far after every pass
+    pub fn run_with_snapshots<P: AsRef<Path>>(&mut self, samples_per_pass: usize, path: P, gamma: T) -> io::Result<usize> {
+        let mut result = Ok(());
+        let passes = self.run(samples_per_pass, |render| {
+            result = render.write_snapshot(path.as_ref(), gamma);
+            if result.is_ok() { ControlFlow::Continue(()) } else { ControlFlow::Break(()) }
+        });
+        result.map(|_| passes)
+    }
+
+    /// writes the image so far to `path` as a ppm, see [`PixelBuffer::apply_gamma`]
+    pub fn write_snapshot<P: AsRef<Path>>(&self, path: P, gamma: T) -> io::Result<()> {
+        let filename = path.as_ref().to_str()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "snapshot path isn't valid unicode"))?;
+        self.image().apply_gamma(gamma).write_ppm(filename)
+    }
+
+    /// whether every pixel has either had the settings' full sample count or converged
+    pub fn finished(&self) -> bool {
+        let adaptive = self.settings.adaptive();
+        self.pixels.iter().all(|stats| {
+            stats.count() >= self.settings.samples() || adaptive.is_some_and(|adaptive| adaptive.converged(stats))
+        })
+    }
+
+    /// whether adaptive sampling has decided every pixel is clean enough
+    fn converged(&self) -> bool {
+        self.settings.adaptive().is_some_and(|adaptive| self.pixels.iter().all(|stats| adaptive.converged(stats)))
+    }
+
+    /// the exposed image so far
+    pub fn image(&self) -> PixelBuffer<T> {
+        self.film.resolve(self.camera.exposure_scale())
+    }
+
+    pub fn sample_counts(&self) -> SampleCounts {
+        SampleCounts::new(self.width, self.height, self.pixels.iter().map(PixelStats::count).collect())
+    }
+
+    /// the average samples per pixel so far
+    pub fn samples_per_pixel(&self) -> f64 {
+        self.pixels.iter().map(|stats| stats.count() as f64).sum::<f64>() / self.pixels.len().max(1) as f64
+    }
+
+    /// how many passes have been made
+    pub fn passes(&self) -> usize {
+        self.passes
+    }
+
+    pub fn settings(&self) -> &RenderSettings {
+        &self.settings
+    }
+
+    pub fn camera(&self) -> &Camera<T> {
+        &self.camera
+    }
+
+    pub fn scene(&self) -> &Scene {
+        &self.scene
+    }
+}
+
+impl<T, Scene> ProgressiveRender<T, Scene>
+where
+    T: Scalar,
+    Scene: Intersectable<T> + Sync + Fingerprint
+{
+    /// picks up a render from a checkpoint written by [`ProgressiveRender::write_checkpoint`],
+    /// with the settings it was started with. carrying on gives exactly the image an
+    /// uninterrupted render would have. checkpoints made with a different camera or scene
+    /// are refused, as are files whose size doesn't match the image they describe
+    pub fn resume<P: AsRef<Path>>(camera: Camera<T>, scene: Scene, path: P) -> io::Result<Self> {
+        let bytes = fs::read(path)?;
+        let mut reader = Reader::new(&bytes);
+        reader.magic()?;
+        if reader.u64()? != Self::fingerprint(&camera, &scene) {
+            return Err(invalid("checkpoint was made with a different camera or scene"));
+        }
+        let settings = reader.settings()?;
+        // everything is checked against the file before a damaged header gets to size anything
+        let expected_height = settings.vertical_resolution();
+        let expected_width = camera.aspect_ratio().width_from_height(expected_height);
+        let (width, height) = (reader.usize()?, reader.usize()?);
+        if (width, height) != (expected_width, expected_height) {
+            return Err(invalid(format!("checkpoint is {width}x{height} but the render is {expected_width}x{expected_height}")));
+        }
+        let passes = reader.usize()?;
+        let expected_bytes = width.checked_mul(height).and_then(|area| area.checked_mul(PIXEL_BYTES));
+        if expected_bytes != Some(reader.remaining()) {
+            return Err(invalid(format!("checkpoint should hold {width}x{height} pixels but has {} bytes of them", reader.remaining())));
+        }
+
+        let mut render = Self::new(camera, scene, settings);
+        render.passes = passes;
+        for stats in render.pixels.iter_mut() {
+            *stats = PixelStats::from_parts((reader.usize()?, reader.vector()?, reader.scalar()?, reader.scalar()?));
+        }
+        let film = &mut render.film;
+        for ((sum, weight), magnitude) in film.sums.iter_mut().zip(film.weights.iter_mut()).zip(film.magnitudes.iter_mut()) {
+            *sum = reader.vector()?;
+            *weight = reader.scalar()?;
+            *magnitude = reader.scalar()?;
+        }
+        debug_assert!(reader.finished());
+        debug!("resumed after {} passes at {:.1} samples per pixel", render.passes, render.samples_per_pixel());
+        Ok(render)
+    }
+
+    /// saves the accumulated samples, the settings and seed, and a fingerprint of the camera
+    /// and scene to `path`. the file is written alongside, flushed to disk and then moved into
+    /// place, so a crash or power cut while writing leaves the previous checkpoint intact
+    pub fn write_checkpoint<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let mut writer = Writer::default();
+        writer.bytes.extend_from_slice(MAGIC);
+        writer.u64(Self::fingerprint(&self.camera, &self.scene));
+        writer.settings(&self.settings);
+        writer.usize(self.width);
+        writer.usize(self.height);
+        writer.usize(self.passes);
+        for stats in &self.pixels {
+            let (count, mean, luminance_mean, luminance_m2) = stats.parts();
+            writer.usize(count);
+            writer.vector(&mean);
+            writer.scalar(luminance_mean);
+            writer.scalar(luminance_m2);
+        }
+        for ((sum, weight), magnitude) in self.film.sums.iter().zip(&self.film.weights).zip(&self.film.magnitudes) {
+            writer.vector(sum);
+            writer.scalar(*weight);
+            writer.scalar(*magnitude);
+        }
+
+        write_replacing(path.as_ref(), &writer.bytes)
+    }
+
+    /// like [`ProgressiveRender::run`], checkpointing to `path` after any pass that ends at
+    /// least `interval` after the last checkpoint, and once more when the render stops
+    pub fn run_with_checkpoints<P: AsRef<Path>>(&mut self, samples_per_pass: usize, path: P, interval: Duration) -> io::Result<usize> {
+        let mut result = Ok(());
+        let mut last_checkpoint = Instant::now();
+        let passes = self.run(samples_per_pass, |render| {
+            if last_checkpoint.elapsed() >= interval {
+                result = render.write_checkpoint(path.as_ref());
+                last_checkpoint = Instant::now();
+            }
+            if result.is_ok() { ControlFlow::Continue(()) } else { ControlFlow::Break(()) }
+        });
+        result?;
+        self.write_checkpoint(path)?;
+        Ok(passes)
+    }
+
+    /// identifies the camera, the scene and the precision they're rendered in
+    fn fingerprint(camera: &Camera<T>, scene: &Scene) -> u64 {
+        let mut state = Fingerprinter::default();
+        state.precision::<T>();
+        state.add(camera).add(scene);
+        state.finish()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use nalgebra::point;
+
+    use super::*;
+    use crate::{geometry::Sphere, image::{Rectangle, TileOrder, render, test::sphere_in_view}, Material};
+
+    #[test]
+    pub fn test_passes_add_up_to_a_single_render() {
+        let scene = sphere_in_view();
+        let settings = RenderSettings::default().with_resolution(10).with_samples(7);
+        let mut progressive = ProgressiveRender::new(Camera::<f64>::default(), &scene, settings.clone());
+
+        let mut snapshots = Vec::new();
+        let passes = progressive.run(3, |render| {
+            snapshots.push(render.samples_per_pixel());
+            ControlFlow::Continue(())
+        });
+        assert_eq!(passes, 3);
+        assert_eq!(snapshots, vec![3.0, 6.0, 7.0]);
+
+        // same seed and the same samples in the same order, just split up differently
+        let expected = render(Camera::default(), &scene, &settings);
+        for (progressive, single) in progressive.image().pixels.iter().zip(&expected.pixels) {
+            assert!((progressive - single).norm() < 1e-12);
+        }
+
+        // stopping early after the first pass
+        let mut stopped = ProgressiveRender::new(Camera::<f64>::default(), &scene, settings);
+        assert_eq!(stopped.run(2, |_| ControlFlow::Break(())), 1);
+        assert_eq!(stopped.sample_counts().max(), 2);
+    }
+
+    #[test]
+    pub fn test_resuming_a_checkpoint_finishes_the_same_image() {
+        let path = std::env::temp_dir().join(format!("rt_checkpoint_{}.ckpt", std::process::id()));
+        let scene = sphere_in_view();
+        let settings = RenderSettings::default().with_resolution(8).with_samples(6).with_seed(9);
+
+        let mut uninterrupted = ProgressiveRender::new(Camera::<f64>::default(), &scene, settings.clone());
+        uninterrupted.run(2, |_| ControlFlow::Continue(()));
+
+        // stop after the first pass, as if the machine went down
+        let mut interrupted = ProgressiveRender::new(Camera::<f64>::default(), &scene, settings);
+        interrupted.run(2, |_| ControlFlow::Break(()));
+        interrupted.write_checkpoint(&path).unwrap();
+
+        let mut resumed = ProgressiveRender::resume(Camera::<f64>::default(), &scene, &path).unwrap();
+        assert_eq!(resumed.sample_counts(), interrupted.sample_counts());
+        assert_eq!(resumed.run(2, |_| ControlFlow::Continue(())), 2);
+        assert_eq!(resumed.passes(), 3);
+        assert_eq!(resumed.image().pixels, uninterrupted.image().pixels);
+
+        let moved = Sphere::new(point![0.0, 0.5, -2.0], 1.0, Material::default());
+        let error = ProgressiveRender::resume(Camera::<f64>::default(), &moved, &path).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+
+        // a damaged header claiming an enormous image is refused before anything is allocated
+        let bytes = std::fs::read(&path).unwrap();
+        let header = bytes.len() - interrupted.pixels.len() * PIXEL_BYTES;
+        let huge = 1_u64 << 40;
+        let mut damaged = bytes[..header].to_vec();
+        damaged[16..24].copy_from_slice(&huge.to_le_bytes());
+        let width = Camera::<f64>::default().aspect_ratio().width_from_height(huge as usize) as u64;
+        damaged[header - 24..header - 16].copy_from_slice(&width.to_le_bytes());
+        damaged[header - 16..header - 8].copy_from_slice(&huge.to_le_bytes());
+        std::fs::write(&path, &damaged).unwrap();
+        let error = ProgressiveRender::resume(Camera::<f64>::default(), &scene, &path).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        std::fs::write(&path, &bytes[..bytes.len() - 1]).unwrap();
+        assert!(ProgressiveRender::resume(Camera::<f64>::default(), &scene, &path).is_err());
+
+        // the same scene rendered in single precision isn't the same render
+        let single = sphere_in_view::<f32>();
+        assert_ne!(
+            ProgressiveRender::<f32, _>::fingerprint(&Camera::default(), &&single),
+            ProgressiveRender::<f64, _>::fingerprint(&Camera::default(), &&scene),
+        );
+        assert_ne!(
+            ProgressiveRender::<f64, _>::fingerprint(&Camera::default().with_aperture(0.1), &&scene),
+            ProgressiveRender::<f64, _>::fingerprint(&Camera::default(), &&scene),
+        );
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    pub fn test_budgeted_render_stops_in_time() {
+        use std::sync::atomic::{AtomicU64, Ordering};
+
+        let scene = sphere_in_view();
+        let settings = RenderSettings::default().with_resolution(8).with_samples(1).with_tiles(2, TileOrder::Scanline);
+        let mut progressive = ProgressiveRender::new(Camera::<f64>::default(), &scene, settings);
+        assert_eq!(progressive.sample_counts().as_tuple(), (14, 8));
+
+        // a clock that moves on a millisecond each time it's read, which happens once before
+        // each of the 28 tiles and once after each pass, so the third pass runs out after 5 tiles
+        let ticks = AtomicU64::new(0);
+        let clock = || Duration::from_millis(ticks.fetch_add(1, Ordering::Relaxed));
+        let report = progressive.run_for_on(Duration::from_millis(63), 2, clock, |_| ControlFlow::Continue(()));
+        // the sample count in the settings doesn't cap a budgeted render
+        assert_eq!(report.passes, 3);
+        let counts = progressive.sample_counts().counts;
+        assert_eq!(counts.iter().filter(|count| **count == 6).count(), 5 * 4);
+        assert_eq!(counts.iter().filter(|count| **count == 4).count(), 112 - 5 * 4);
+        assert_eq!(report.samples_per_pixel, (4 * 112 + 2 * 5 * 4) as f64 / 112.0);
+    }
+}
@@ -0,0 +1,82 @@
+use crate::image::Rectangle;
+
+/// a rectangle of pixels within a larger image. columns count from the left and rows from
+/// the bottom, the same way [`PixelBuffer`](crate::image::PixelBuffer) is indexed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Region {
+    pub x: usize,
+    pub y: usize,
+    pub width: usize,
+    pub height: usize,
+}
+
+impl Region {
+    pub fn new(x: usize, y: usize, width: usize, height: usize) -> Self {
+        Self { x, y, width, height }
+    }
+
+    /// the whole of an image `width` by `height`
+    pub fn full(width: usize, height: usize) -> Self {
+        Self::new(0, 0, width, height)
+    }
+
+    /// whether every pixel of the region lies inside an image `width` by `height`
+    pub fn fits_within(&self, width: usize, height: usize) -> bool {
+        self.x + self.width <= width && self.y + self.height <= height
+    }
+
+    /// the part of this region that falls inside an image `width` by `height`, None if none of it does
+    pub fn clamped(&self, width: usize, height: usize) -> Option<Self> {
+        let (right, top) = ((self.x + self.width).min(width), (self.y + self.height).min(height));
+        if self.x >= right || self.y >= top {
+            None
+        } else {
+            Some(Self::new(self.x, self.y, right - self.x, top - self.y))
+        }
+    }
+
+    /// the pixels in both regions, None if they don't overlap
+    pub fn intersection(&self, other: &Self) -> Option<Self> {
+        let (left, bottom) = (self.x.max(other.x), self.y.max(other.y));
+        let right = (self.x + self.width).min(other.x + other.width);
+        let top = (self.y + self.height).min(other.y + other.height);
+        if left >= right || bottom >= top {
+            None
+        } else {
+            Some(Self::new(left, bottom, right - left, top - bottom))
+        }
+    }
+
+    /// the region grown by `margin` pixels on every side, as far as the edges of an image
+    /// `width` by `height`
+    pub fn expanded(&self, margin: usize, width: usize, height: usize) -> Self {
+        let (left, bottom) = (self.x.saturating_sub(margin), self.y.saturating_sub(margin));
+        let right = (self.x + self.width + margin).min(width);
+        let top = (self.y + self.height + margin).min(height);
+        Self::new(left, bottom, right.saturating_sub(left), top.saturating_sub(bottom))
+    }
+
+    /// whether (`col`, `row`) is inside the region
+    pub fn contains(&self, col: usize, row: usize) -> bool {
+        (self.x..self.x + self.width).contains(&col) && (self.y..self.y + self.height).contains(&row)
+    }
+
+    pub fn area(&self) -> usize {
+        self.width * self.height
+    }
+
+    /// every (column, row) in the region in the full image, row by row from the bottom
+    pub fn pixels(&self) -> impl Iterator<Item = (usize, usize)> + '_ {
+        (self.y..self.y + self.height).flat_map(move |row| (self.x..self.x + self.width).map(move |col| (col, row)))
+    }
+}
+
+impl Rectangle for Region {
+    fn width(&self) -> usize {
+        self.width
+    }
+
+    fn height(&self) -> usize {
+        self.height
+    }
+}
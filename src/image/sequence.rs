@@ -0,0 +1,197 @@
+use std::{io, path::{Path, PathBuf}};
+
+use indicatif::{MultiProgress, ProgressBar, ProgressStyle};
+use tracing::debug;
+
+use crate::{
+    Scalar,
+    camera::CameraPath,
+    sampler::hash,
+    geometry::Intersectable,
+    image::{AsPPM, RenderSettings, make_render_progress_bar, render_scene_with_progress, write_replacing},
+};
+
+/// renders a camera path to numbered ppm files, `frame_0001.ppm` onwards. frames that are
+/// already on disk are skipped so an interrupted sequence can be picked up again. frames are
+/// only ever moved into place once they're completely written
+#[derive(Debug, Clone)]
+pub struct FrameSequence<T>
+where
+    T: Scalar
+{
+    directory: PathBuf,
+    frames: usize,
+    /// defaults to the span of the camera path
+    time_range: Option<(T, T)>,
+    settings: RenderSettings,
+    gamma: T,
+    /// how much of the time between frames the shutter is open for, in degrees
+    shutter_angle: T,
+}
+
+impl<T> FrameSequence<T>
+where
+    T: Scalar
+{
+    pub fn new<P: AsRef<Path>>(directory: P, frames: usize) -> Self {
+        Self {
+            directory: directory.as_ref().to_path_buf(),
+            frames,
+            time_range: None,
+            settings: RenderSettings::default(),
+            gamma: T::HALF,
+            shutter_angle: T::from_float(180.0),
+        }
+    }
+
+    /// the time of the first and last frames, the rest are spaced evenly between them
+    pub fn with_time_range(self, start: T, end: T) -> Self {
+        Self { time_range: Some((start, end)), ..self }
+    }
+
+    /// how each frame is rendered
+    pub fn with_settings(self, settings: RenderSettings) -> Self {
+        Self { settings, ..self }
+    }
+
+    pub fn with_resolution(self, vertical_resolution: usize) -> Self {
+        Self { settings: self.settings.clone().with_resolution(vertical_resolution), ..self }
+    }
+
+    pub fn with_samples(self, samples: usize) -> Self {
+        Self { settings: self.settings.clone().with_samples(samples), ..self }
+    }
+
+    /// exponent applied to each frame before it's written, see [`PixelBuffer::apply_gamma`]
+    pub fn with_gamma(self, gamma: T) -> Self {
+        Self { gamma, ..self }
+    }
+
+    /// how long the shutter stays open as a fraction of a full turn, like a film camera's
+    /// rotary shutter. 180 degrees blurs motion over half the time between frames, and 0
+    /// freezes every frame
+    pub fn with_shutter_angle(self, shutter_angle: T) -> Self {
+        Self { shutter_angle, ..self }
+    }
+
+    /// the file frame `number` is written to, counting from one
+    pub fn frame_path(&self, number: usize) -> PathBuf {
+        self.directory.join(format!("frame_{number:04}.ppm"))
+    }
+
+    /// the scene time shown in frame `number`
+    pub fn frame_time(&self, path: &CameraPath<T>, number: usize) -> T {
+        let (start, end) = self.time_range.unwrap_or_else(|| (path.start_time(), path.end_time()));
+        if self.frames <= 1 {
+            return start;
+        }
+        let t = T::from_float((number - 1) as f64 / (self.frames - 1) as f64);
+        start + (end - start) * t
+    }
+
+    /// how long the shutter is open for each frame, centred on its time
+    pub fn shutter_time(&self, path: &CameraPath<T>) -> T {
+        if self.frames <= 1 {
+            return T::zero();
+        }
+        let interval = self.frame_time(path, 2) - self.frame_time(path, 1);
+        interval.abs() * self.shutter_angle / T::from_float(360.0)
+    }
+
+    /// the sampler seed frame `number` is rendered with, mixed from the settings' seed and the
+    /// frame number. each frame gets its own noise, which would otherwise stay fixed to the
+    /// screen while the picture moves, but rendering a frame again still gives the same image
+    pub fn frame_seed(&self, number: usize) -> u64 {
+        hash(&[self.settings.seed(), number as u64])
+    }
+
+    /// renders every missing frame, returning how many were actually rendered
+    pub fn render<Scene>(&self, path: &CameraPath<T>, scene: Scene) -> io::Result<usize>
+    where
+        Scene: Intersectable<T> + Sync
+    {
+        let progress = MultiProgress::new();
+        let overall = progress.add(
+            ProgressBar::new(self.frames as u64).with_style(
+                ProgressStyle::with_template("[{elapsed_precise}] frame {pos}/{len} {bar:40.green/white} [{eta}] {msg}")
+                    .expect("Failed to create sequence progress bar")
+                    .progress_chars("##-")
+            )
+        );
+
+        let mut rendered = 0;
+        for number in 1..=self.frames {
+            let file = self.frame_path(number);
+            if file.exists() {
+                debug!("skipping {}, it already exists", file.display());
+                overall.set_message(format!("skipped frame {number}"));
+                overall.inc(1);
+                continue;
+            }
+
+            let time = self.frame_time(path, number);
+            let camera = path
+                .camera_over(time, self.shutter_time(path))
+                .map_err(|err| io::Error::new(io::ErrorKind::InvalidInput, format!("frame {number}: {err}")))?;
+            overall.set_message(format!("rendering frame {number}"));
+
+            let height = self.settings.vertical_resolution();
+            let frame_progress = progress.add(make_render_progress_bar(camera.aspect_ratio().width_from_height(height), height));
+            let settings = self.settings.clone().with_seed(self.frame_seed(number));
+            let (frame, _) = render_scene_with_progress(camera, &scene, &settings, &frame_progress);
+            frame_progress.finish_and_clear();
+
+            // a frame interrupted part way through writing must not look finished next time
+            write_replacing(&file, frame.apply_gamma(self.gamma).as_ppm_string().as_bytes())?;
+            rendered += 1;
+            overall.inc(1);
+        }
+        overall.finish_with_message(format!("rendered {rendered} of {} frames", self.frames));
+        Ok(rendered)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use nalgebra::{point, Point3};
+
+    use super::*;
+    use crate::geometry::{Keyframes, Sphere};
+    use crate::Material;
+
+    #[test]
+    pub fn test_sequence_renders_numbered_frames_and_skips_existing() {
+        let directory = std::env::temp_dir().join(format!("rt_sequence_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&directory);
+
+        let positions = Keyframes::linear(point![0.0, 0.0, 3.0], point![1.0, 0.0, 3.0]);
+        let path = CameraPath::new(positions, Keyframes::new(vec![(0.0, Point3::origin())]));
+        let scene = Sphere::new(Point3::origin(), 1.0, Material::default());
+        let sequence = FrameSequence::<f64>::new(&directory, 3).with_resolution(8).with_samples(1);
+        assert_eq!(sequence.frame_time(&path, 2), 0.5);
+        assert_eq!(sequence.shutter_time(&path), 0.25);
+        assert_eq!(sequence.clone().with_shutter_angle(0.0).shutter_time(&path), 0.0);
+        assert_ne!(sequence.frame_seed(1), sequence.frame_seed(2));
+        assert_eq!(sequence.frame_seed(2), sequence.clone().with_samples(4).frame_seed(2));
+        assert_ne!(sequence.frame_seed(2), sequence.clone().with_settings(RenderSettings::default().with_seed(7)).frame_seed(2));
+
+        // pretend the second frame was rendered before an interruption
+        std::fs::create_dir_all(&directory).unwrap();
+        std::fs::write(sequence.frame_path(2), "placeholder").unwrap();
+
+        assert_eq!(sequence.render(&path, &scene).unwrap(), 2);
+        assert!(sequence.frame_path(1).ends_with("frame_0001.ppm"));
+        assert!(sequence.frame_path(1).exists() && sequence.frame_path(3).exists());
+        assert_eq!(std::fs::read_to_string(sequence.frame_path(2)).unwrap(), "placeholder");
+        assert_eq!(sequence.render(&path, &scene).unwrap(), 0);
+        assert!(std::fs::read_dir(&directory).unwrap().all(|entry| entry.unwrap().path().extension().unwrap() == "ppm"));
+
+        // a frame cut off while it was being written never made it into place
+        std::fs::remove_file(sequence.frame_path(3)).unwrap();
+        std::fs::write(directory.join("frame_0003.ppm.partial"), "P3\n8").unwrap();
+        assert_eq!(sequence.render(&path, &scene).unwrap(), 1);
+        assert!(std::fs::read_to_string(sequence.frame_path(3)).unwrap().starts_with("P3\n14 8\n"));
+
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+}
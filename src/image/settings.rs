@@ -0,0 +1,118 @@
+use crate::{image::{AdaptiveSampling, Filter, TileOrder, BOUNCES, SAMPLES}, sampler::SamplerKind};
+
+/// how a render is carried out, independent of the camera and scene being rendered
+#[derive(Debug, Clone, PartialEq)]
+pub struct RenderSettings {
+    vertical_resolution: usize,
+    samples: usize,
+    bounces: usize,
+    sampler: SamplerKind,
+    /// every random number in the render is derived from this, so the same seed, scene and
+    /// settings always give the same image however the work is split between threads
+    seed: u64,
+    /// with adaptive sampling `samples` is the most any pixel will take
+    adaptive: Option<AdaptiveSampling>,
+    filter: Filter,
+    /// renders are split into square tiles this many pixels across
+    tile_size: usize,
+    tile_order: TileOrder,
+}
+
+impl Default for RenderSettings {
+    /// 720 rows at 100 samples per pixel and up to 50 bounces, sampled with sobol points from seed 0
+    fn default() -> Self {
+        Self {
+            vertical_resolution: 720,
+            samples: SAMPLES,
+            bounces: BOUNCES,
+            sampler: SamplerKind::default(),
+            seed: 0,
+            adaptive: None,
+            filter: Filter::default(),
+            tile_size: 32,
+            tile_order: TileOrder::default(),
+        }
+    }
+}
+
+impl RenderSettings {
+    /// the height of the image in pixels, its width comes from the camera's aspect ratio
+    pub fn with_resolution(self, vertical_resolution: usize) -> Self {
+        Self { vertical_resolution, ..self }
+    }
+
+    /// samples taken for every pixel
+    pub fn with_samples(self, samples: usize) -> Self {
+        Self { samples, ..self }
+    }
+
+    /// the most times a path can scatter before it's given up on as black
+    pub fn with_bounces(self, bounces: usize) -> Self {
+        Self { bounces, ..self }
+    }
+
+    pub fn with_sampler(self, sampler: SamplerKind) -> Self {
+        Self { sampler, ..self }
+    }
+
+    /// renders with different seeds have independent noise, which is useful for averaging
+    /// several renders or checking that a change isn't just moving the noise around
+    pub fn with_seed(self, seed: u64) -> Self {
+        Self { seed, ..self }
+    }
+
+    /// stop sampling pixels once they're clean enough, with `samples` becoming the cap
+    pub fn with_adaptive(self, adaptive: AdaptiveSampling) -> Self {
+        Self { adaptive: Some(adaptive), ..self }
+    }
+
+    /// how samples are spread over the pixels near where they land, a plain average of each
+    /// pixel's own samples by default. panics if the filter is narrower than half a pixel,
+    /// see [`Filter::is_valid`]
+    pub fn with_filter(self, filter: Filter) -> Self {
+        assert!(filter.is_valid(), "{filter:?} is narrower than half a pixel");
+        Self { filter, ..self }
+    }
+
+    /// how the image is split up between threads. smaller tiles balance the work better,
+    /// larger ones waste less on the samples wide filters need from around each tile
+    pub fn with_tiles(self, tile_size: usize, tile_order: TileOrder) -> Self {
+        Self { tile_size, tile_order, ..self }
+    }
+
+    pub fn vertical_resolution(&self) -> usize {
+        self.vertical_resolution
+    }
+
+    pub fn samples(&self) -> usize {
+        self.samples
+    }
+
+    pub fn bounces(&self) -> usize {
+        self.bounces
+    }
+
+    pub fn sampler(&self) -> SamplerKind {
+        self.sampler
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    pub fn adaptive(&self) -> Option<AdaptiveSampling> {
+        self.adaptive
+    }
+
+    pub fn filter(&self) -> Filter {
+        self.filter
+    }
+
+    pub fn tile_size(&self) -> usize {
+        self.tile_size
+    }
+
+    pub fn tile_order(&self) -> TileOrder {
+        self.tile_order
+    }
+}
@@ -0,0 +1,122 @@
+use crate::image::Region;
+
+/// the order tiles of the image are handed out to be rendered in
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum TileOrder {
+    /// row by row from the bottom left
+    #[default]
+    Scanline,
+    /// outwards from the centre, so the subject usually shows up first
+    Spiral,
+    /// along a hilbert curve, which keeps consecutive tiles next to each other
+    Hilbert,
+}
+
+impl TileOrder {
+    /// splits `region` into tiles no bigger than `size` on a side, in this order. tiles along
+    /// the top and right edges are cut short to fit
+    pub fn tiles(&self, region: Region, size: usize) -> Vec<Region> {
+        let size = size.max(1);
+        let (columns, rows) = (region.width.div_ceil(size), region.height.div_ceil(size));
+        let tile = |(column, row): (usize, usize)| {
+            let (x, y) = (region.x + column * size, region.y + row * size);
+            Region::new(x, y, size.min(region.x + region.width - x), size.min(region.y + region.height - y))
+        };
+        let order: Vec<_> = match self {
+            TileOrder::Scanline => (0..rows).flat_map(|row| (0..columns).map(move |column| (column, row))).collect(),
+            TileOrder::Spiral => spiral(columns, rows),
+            TileOrder::Hilbert => hilbert(columns, rows),
+        };
+        order.into_iter().map(tile).collect()
+    }
+}
+
+/// every cell of a `columns` by `rows` grid, walking a square spiral out from the middle
+fn spiral(columns: usize, rows: usize) -> Vec<(usize, usize)> {
+    let mut cells = Vec::with_capacity(columns * rows);
+    let (mut x, mut y) = ((columns as isize - 1) / 2, (rows as isize - 1) / 2);
+    let directions = [(1, 0), (0, 1), (-1, 0), (0, -1)];
+    let visit = |x: isize, y: isize, cells: &mut Vec<_>| {
+        if (0..columns as isize).contains(&x) && (0..rows as isize).contains(&y) {
+            cells.push((x as usize, y as usize));
+        }
+    };
+    visit(x, y, &mut cells);
+    // legs of the spiral grow by one every second turn: 1, 1, 2, 2, 3, 3...
+    let mut leg = 1;
+    let mut turn = 0;
+    while cells.len() < columns * rows {
+        let (dx, dy) = directions[turn % 4];
+        for _ in 0..leg {
+            x += dx;
+            y += dy;
+            visit(x, y, &mut cells);
+        }
+        turn += 1;
+        if turn % 2 == 0 {
+            leg += 1;
+        }
+    }
+    cells
+}
+
+/// every cell of a `columns` by `rows` grid in the order a hilbert curve over the smallest
+/// power of two square covering it passes through them
+fn hilbert(columns: usize, rows: usize) -> Vec<(usize, usize)> {
+    let side = columns.max(rows).next_power_of_two();
+    (0..side * side)
+        .map(|d| hilbert_point(side, d))
+        .filter(|(x, y)| *x < columns && *y < rows)
+        .collect()
+}
+
+/// the cell `d` steps along a hilbert curve filling a `side` by `side` square
+fn hilbert_point(side: usize, d: usize) -> (usize, usize) {
+    let (mut x, mut y) = (0, 0);
+    let mut t = d;
+    let mut s = 1;
+    while s < side {
+        let rx = 1 & (t / 2);
+        let ry = 1 & (t ^ rx);
+        // rotate the quadrant so the curve joins up
+        if ry == 0 {
+            if rx == 1 {
+                x = s - 1 - x;
+                y = s - 1 - y;
+            }
+            std::mem::swap(&mut x, &mut y);
+        }
+        x += s * rx;
+        y += s * ry;
+        t /= 4;
+        s *= 2;
+    }
+    (x, y)
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashSet;
+
+    use super::*;
+
+    #[test]
+    pub fn test_every_order_covers_the_region_once() {
+        let region = Region::new(3, 2, 70, 45);
+        for order in [TileOrder::Scanline, TileOrder::Spiral, TileOrder::Hilbert] {
+            let tiles = order.tiles(region, 16);
+            assert_eq!(tiles.len(), 5 * 3, "{order:?}");
+            let pixels: HashSet<_> = tiles.iter().flat_map(|tile| tile.pixels()).collect();
+            assert_eq!(pixels.len(), region.area(), "{order:?}");
+            assert!(pixels.iter().all(|(col, row)| region.contains(*col, *row)), "{order:?}");
+        }
+
+        // the spiral starts in the middle and the hilbert curve only ever steps to a neighbour
+        assert_eq!(TileOrder::Spiral.tiles(region, 16)[0], Region::new(35, 18, 16, 16));
+        let hilbert = hilbert(4, 4);
+        for pair in hilbert.windows(2) {
+            let (a, b) = (pair[0], pair[1]);
+            assert_eq!(a.0.abs_diff(b.0) + a.1.abs_diff(b.1), 1);
+        }
+    }
+}
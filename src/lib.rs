@@ -4,7 +4,11 @@ pub mod material;
 pub mod geometry;
 pub mod colour;
 pub mod scalar;
+pub mod sampler;
+pub mod fingerprint;
 
 pub use scalar::Scalar;
 pub use material::Material;
 pub use camera::Camera;
+pub use sampler::{Sampler, SamplerKind};
+pub use fingerprint::Fingerprint;
@@ -1,14 +1,13 @@
 use std::io;
 
-use nalgebra::point;
 // use nalgebra::{vector, Point3, Vector3};
-use tracing::{debug, error, info, span, warn, Level};
+use tracing::{debug, info};
 
 use raytracing::{
     image::{
-        AsPPM,
-        render_scene_parallel_quality,
-        AspectRatio,
+        Preview,
+        ProgressiveRender,
+        RenderSettings,
     },
     geometry::world,
     camera::Camera,
@@ -32,29 +31,13 @@ fn main() -> io::Result<()> {
     let scene = world::ten_sphere_scene();
     // let scene = world::plane_scene();
     debug!("rendering scene");
-    render_scene_parallel_quality(camera, scene, 720, 200)
-        .apply_gamma(0.5)
-        .write_ppm("test_output/ten_sphere_scene.ppm")?;
-    // if let Ok(mut window) = minifb::Window::new(
-    //     "Test",
-    //     640,
-    //     400,
-    //     minifb::WindowOptions {
-    //         // resize: true,
-    //         // scale: true,
-    //         ..minifb::WindowOptions::default()
-    //     }
-    // ) {
-    //     window.update_with_buffer(
-    //         &buf.as_u32(),
-    //         buf.width(),
-    //         buf.height()
-    //     ).unwrap();
-    //     std::thread::sleep(std::time::Duration::new(10, 0));
-    // } else {
-    //     panic!("unable to create window");
-    // }
-
+    let mut render = ProgressiveRender::new(camera, &scene, RenderSettings::default().with_samples(200));
+    // the render is saved when it stops, and after every pass when there's no display
+    let outcome = Preview::new("test_output/ten_sphere_scene.ppm")
+        .with_title("ten sphere scene")
+        .with_gamma(0.5)
+        .show(&mut render)?;
+    info!("{} passes, {:.1} samples per pixel{}", outcome.passes, render.samples_per_pixel(), if outcome.aborted { ", aborted" } else { "" });
 
     Ok(())
 }
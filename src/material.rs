@@ -1,6 +1,6 @@
 use nalgebra::Vector3;
 
-use crate::{colour, Scalar};
+use crate::{colour, Scalar, fingerprint::{Fingerprint, Fingerprinter}};
 
 pub mod refractive_index {
     pub const AIR: f64 = 1.0;
@@ -112,4 +112,17 @@ where
     pub fn refractive_index(&self) -> T {
         self.refractive_index
     }
-}
\ No newline at end of file
+}
+
+impl<T> Fingerprint for Material<T>
+where
+    T: Scalar
+{
+    fn fingerprint(&self, state: &mut Fingerprinter) {
+        state.add(&self.colour);
+        for value in [self.absorptivity, self.specularity, self.diffusivity, self.transmissibility, self.refractive_index] {
+            state.scalar(value);
+        }
+        state.bool(self.checkerboard);
+    }
+}
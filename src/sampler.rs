@@ -0,0 +1,435 @@
+//! sources of the random numbers used while rendering
+//!
+//! every random decision made for a sample, from where in the pixel it lands to which way
+//! it bounces, draws the next dimension from a [`Sampler`]. samplers which spread their
+//! points evenly over all those dimensions converge much faster than independent numbers.
+//! all of them are built from hashes of the pixel, sample index and dimension, so the same
+//! seed always gives the same numbers whichever thread asks for them.
+
+use nalgebra::{point, Point2};
+
+use crate::Scalar;
+
+/// hands out the dimensions of each sample in turn, uniform in [0, 1)
+pub trait Sampler<T>: Send
+where
+    T: Scalar
+{
+    /// moves on to sample `index` of the pixel at (column, row), starting again from the first dimension
+    fn start_pixel_sample(&mut self, pixel: (usize, usize), index: usize);
+
+    fn next_1d(&mut self) -> T;
+
+    /// two dimensions that are meant to be used together, like a point on the lens
+    fn next_2d(&mut self) -> Point2<T> {
+        let x = self.next_1d();
+        point![x, self.next_1d()]
+    }
+}
+
+/// which sampler a render should use
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum SamplerKind {
+    /// unrelated random numbers, the slowest to converge
+    Independent,
+    /// a jittered grid in every dimension, shuffled so dimensions don't line up
+    Stratified,
+    /// the halton sequence with owen scrambled digits
+    Halton,
+    /// the first two sobol dimensions with owen scrambling, padded out with a fresh
+    /// shuffle for each pair of dimensions
+    #[default]
+    Sobol,
+}
+
+impl SamplerKind {
+    /// a sampler for renders taking `samples_per_pixel` samples, seeded with `seed`
+    pub fn build<T: Scalar>(&self, samples_per_pixel: usize, seed: u64) -> Box<dyn Sampler<T>> {
+        match self {
+            SamplerKind::Independent => Box::new(IndependentSampler::new(seed)),
+            SamplerKind::Stratified => Box::new(StratifiedSampler::new(samples_per_pixel, seed)),
+            SamplerKind::Halton => Box::new(HaltonSampler::new(seed)),
+            SamplerKind::Sobol => Box::new(SobolSampler::new(seed)),
+        }
+    }
+}
+
+/// the largest double below one, samples are clamped to it so they never round up to one
+const ONE_MINUS_EPSILON: f64 = 1.0 - f64::EPSILON / 2.0;
+
+/// the splitmix64 finaliser, a cheap and well distributed 64 bit mix
+fn mix(mut x: u64) -> u64 {
+    x = (x ^ (x >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    x = (x ^ (x >> 27)).wrapping_mul(0x94d049bb133111eb);
+    x ^ (x >> 31)
+}
+
+/// combines several values into one well mixed hash
+pub(crate) fn hash(values: &[u64]) -> u64 {
+    values.iter().fold(0x9e3779b97f4a7c15, |acc, value| mix(acc ^ mix(*value)))
+}
+
+/// a uniform number in [0, 1) from the top 53 bits of a hash
+fn unit_from_bits(bits: u64) -> f64 {
+    (bits >> 11) as f64 * (1.0 / (1u64 << 53) as f64)
+}
+
+/// where the sampler currently is, shared by all the implementations
+#[derive(Debug, Clone, Copy, Default)]
+struct SampleState {
+    pixel: (usize, usize),
+    index: usize,
+    dimension: usize,
+}
+
+impl SampleState {
+    fn start(&mut self, pixel: (usize, usize), index: usize) {
+        *self = Self { pixel, index, dimension: 0 };
+    }
+
+    /// a hash unique to this pixel and dimension but shared by all samples of the pixel,
+    /// moving on to the next dimension
+    fn dimension_hash(&mut self, seed: u64) -> u64 {
+        let hash = hash(&[seed, self.pixel.0 as u64, self.pixel.1 as u64, self.dimension as u64]);
+        self.dimension += 1;
+        hash
+    }
+}
+
+/// every dimension is an unrelated random number
+#[derive(Debug, Clone)]
+pub struct IndependentSampler {
+    seed: u64,
+    state: SampleState,
+}
+
+impl IndependentSampler {
+    pub fn new(seed: u64) -> Self {
+        Self { seed, state: SampleState::default() }
+    }
+}
+
+impl<T> Sampler<T> for IndependentSampler
+where
+    T: Scalar
+{
+    fn start_pixel_sample(&mut self, pixel: (usize, usize), index: usize) {
+        self.state.start(pixel, index);
+    }
+
+    fn next_1d(&mut self) -> T {
+        let dimension = self.state.dimension_hash(self.seed);
+        T::from_float(unit_from_bits(hash(&[dimension, self.state.index as u64])))
+    }
+}
+
+/// splits each dimension into as many strata as there are samples and puts one sample
+/// in each, with the strata visited in a different order for every dimension
+#[derive(Debug, Clone)]
+pub struct StratifiedSampler {
+    seed: u64,
+    samples_per_pixel: usize,
+    /// columns and rows of the grid used for pairs of dimensions
+    strata: (usize, usize),
+    state: SampleState,
+}
+
+impl StratifiedSampler {
+    pub fn new(samples_per_pixel: usize, seed: u64) -> Self {
+        let samples_per_pixel = samples_per_pixel.max(1);
+        let columns = ((samples_per_pixel as f64).sqrt() as usize).max(1);
+        let rows = samples_per_pixel.div_ceil(columns);
+        Self { seed, samples_per_pixel, strata: (columns, rows), state: SampleState::default() }
+    }
+}
+
+impl<T> Sampler<T> for StratifiedSampler
+where
+    T: Scalar
+{
+    fn start_pixel_sample(&mut self, pixel: (usize, usize), index: usize) {
+        self.state.start(pixel, index);
+    }
+
+    fn next_1d(&mut self) -> T {
+        let dimension = self.state.dimension_hash(self.seed);
+        let count = self.samples_per_pixel as u32;
+        let stratum = permutation_element((self.state.index % self.samples_per_pixel) as u32, count, dimension as u32);
+        let jitter = unit_from_bits(hash(&[dimension, self.state.index as u64]));
+        T::from_float(((stratum as f64 + jitter) / count as f64).min(ONE_MINUS_EPSILON))
+    }
+
+    fn next_2d(&mut self) -> Point2<T> {
+        let dimension = self.state.dimension_hash(self.seed);
+        // keep the dimension count the same as two calls to next_1d
+        self.state.dimension += 1;
+        let (columns, rows) = self.strata;
+        let cells = (columns * rows) as u32;
+        let cell = permutation_element((self.state.index % (columns * rows)) as u32, cells, dimension as u32) as usize;
+        let jitter = hash(&[dimension, self.state.index as u64]);
+        let (jx, jy) = (unit_from_bits(jitter), unit_from_bits(mix(jitter)));
+        let x = ((cell % columns) as f64 + jx) / columns as f64;
+        let y = ((cell / columns) as f64 + jy) / rows as f64;
+        point![T::from_float(x.min(ONE_MINUS_EPSILON)), T::from_float(y.min(ONE_MINUS_EPSILON))]
+    }
+}
+
+/// element `i` of a random permutation of 0..`length` chosen by `seed`, without building the
+/// permutation. from Kensler's "Correlated Multi-Jittered Sampling"
+fn permutation_element(mut i: u32, length: u32, seed: u32) -> u32 {
+    let mut w = length - 1;
+    w |= w >> 1;
+    w |= w >> 2;
+    w |= w >> 4;
+    w |= w >> 8;
+    w |= w >> 16;
+    loop {
+        i ^= seed;
+        i = i.wrapping_mul(0xe170893d);
+        i ^= seed >> 16;
+        i ^= (i & w) >> 4;
+        i ^= seed >> 8;
+        i = i.wrapping_mul(0x0929eb3f);
+        i ^= seed >> 23;
+        i ^= (i & w) >> 1;
+        i = i.wrapping_mul(1 | seed >> 27);
+        i = i.wrapping_mul(0x6935fa69);
+        i ^= (i & w) >> 11;
+        i = i.wrapping_mul(0x74dcb303);
+        i ^= (i & w) >> 2;
+        i = i.wrapping_mul(0x9e501cc3);
+        i ^= (i & w) >> 2;
+        i = i.wrapping_mul(0xc860a3df);
+        i &= w;
+        i ^= i >> 5;
+        // walk the cycle until we land back inside the range
+        if i < length {
+            break;
+        }
+    }
+    (i.wrapping_add(seed)) % length
+}
+
+/// the first primes, one halton base for each dimension
+const PRIMES: [u64; 64] = [
+    2, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37, 41, 43, 47, 53,
+    59, 61, 67, 71, 73, 79, 83, 89, 97, 101, 103, 107, 109, 113, 127, 131,
+    137, 139, 149, 151, 157, 163, 167, 173, 179, 181, 191, 193, 197, 199, 211, 223,
+    227, 229, 233, 239, 241, 251, 257, 263, 269, 271, 277, 281, 283, 293, 307, 311,
+];
+
+/// the halton sequence, with each dimension the radical inverse of the sample index in the
+/// next prime base. dimensions past the table of primes fall back to independent numbers
+#[derive(Debug, Clone)]
+pub struct HaltonSampler {
+    seed: u64,
+    state: SampleState,
+}
+
+impl HaltonSampler {
+    pub fn new(seed: u64) -> Self {
+        Self { seed, state: SampleState::default() }
+    }
+}
+
+impl<T> Sampler<T> for HaltonSampler
+where
+    T: Scalar
+{
+    fn start_pixel_sample(&mut self, pixel: (usize, usize), index: usize) {
+        self.state.start(pixel, index);
+    }
+
+    fn next_1d(&mut self) -> T {
+        let base = PRIMES.get(self.state.dimension).copied();
+        let dimension = self.state.dimension_hash(self.seed);
+        let index = self.state.index as u64;
+        T::from_float(match base {
+            Some(base) => owen_scrambled_radical_inverse(base, index, dimension),
+            None => unit_from_bits(hash(&[dimension, index])),
+        })
+    }
+}
+
+/// reverses the digits of `index` in `base` about the radix point, shifting each digit by an
+/// amount that depends on all the digits before it. that's a nested random permutation, so
+/// the stratification of the sequence survives while different seeds decorrelate
+fn owen_scrambled_radical_inverse(base: u64, mut index: u64, seed: u64) -> f64 {
+    let inv_base = 1.0 / base as f64;
+    let mut inv_base_m = 1.0;
+    let mut result = 0.0;
+    let mut prefix = seed;
+    // keep going past the index's last digit until the digits are too small to matter,
+    // scrambled zeros aren't zero any more
+    while 1.0 - (base as f64 - 1.0) * inv_base_m < 1.0 {
+        inv_base_m *= inv_base;
+        let digit = index % base;
+        let scrambled = (digit + mix(prefix) % base) % base;
+        result += scrambled as f64 * inv_base_m;
+        prefix = hash(&[prefix, digit]);
+        index /= base;
+    }
+    result.min(ONE_MINUS_EPSILON)
+}
+
+/// direction numbers for the second sobol dimension, from the primitive polynomial x + 1
+const SOBOL_DIRECTIONS: [u32; 32] = sobol_directions();
+
+const fn sobol_directions() -> [u32; 32] {
+    let mut directions = [0; 32];
+    let mut m: u32 = 1;
+    let mut k = 0;
+    while k < 32 {
+        directions[k] = m << (31 - k);
+        m ^= m << 1;
+        k += 1;
+    }
+    directions
+}
+
+/// a sobol point as a 32 bit fixed point fraction, only the first two dimensions are needed
+fn sobol(index: u32, dimension: usize) -> u32 {
+    if dimension == 0 {
+        // the first dimension is just the van der corput sequence
+        return index.reverse_bits();
+    }
+    let mut result = 0;
+    let mut bits = index;
+    let mut k = 0;
+    while bits != 0 {
+        if bits & 1 == 1 {
+            result ^= SOBOL_DIRECTIONS[k];
+        }
+        bits >>= 1;
+        k += 1;
+    }
+    result
+}
+
+/// a fast hash that is a nested uniform permutation of bits from the bottom up, after Laine and Karras
+fn laine_karras_permutation(mut x: u32, seed: u32) -> u32 {
+    x = x.wrapping_add(seed);
+    x ^= x.wrapping_mul(0x6c50b47c);
+    x ^= x.wrapping_mul(0xb82f1e52);
+    x ^= x.wrapping_mul(0xc7afe638);
+    x ^= x.wrapping_mul(0x8d22f6e6);
+    x
+}
+
+/// owen scrambling of a fixed point fraction, see Burley's "Practical Hash-based Owen Scrambling"
+fn nested_uniform_scramble(x: u32, seed: u32) -> u32 {
+    laine_karras_permutation(x.reverse_bits(), seed).reverse_bits()
+}
+
+/// owen scrambled sobol points. each pair of dimensions shuffles the sample order afresh
+/// so only the first two sobol dimensions are ever needed
+#[derive(Debug, Clone)]
+pub struct SobolSampler {
+    seed: u64,
+    state: SampleState,
+}
+
+impl SobolSampler {
+    pub fn new(seed: u64) -> Self {
+        Self { seed, state: SampleState::default() }
+    }
+
+    fn sample(&mut self, dimensions: usize) -> [f64; 2] {
+        let hash = self.state.dimension_hash(self.seed);
+        // keep the dimension count the same whether dimensions are drawn one or two at a time
+        self.state.dimension += dimensions - 1;
+        let seed = hash as u32;
+        let index = nested_uniform_scramble(self.state.index as u32, seed);
+        [0, 1].map(|d| {
+            let scrambled = nested_uniform_scramble(sobol(index, d), (mix(hash ^ d as u64) >> 32) as u32);
+            (scrambled as f64 / (1u64 << 32) as f64).min(ONE_MINUS_EPSILON)
+        })
+    }
+}
+
+impl<T> Sampler<T> for SobolSampler
+where
+    T: Scalar
+{
+    fn start_pixel_sample(&mut self, pixel: (usize, usize), index: usize) {
+        self.state.start(pixel, index);
+    }
+
+    fn next_1d(&mut self) -> T {
+        T::from_float(self.sample(1)[0])
+    }
+
+    fn next_2d(&mut self) -> Point2<T> {
+        let [x, y] = self.sample(2);
+        point![T::from_float(x), T::from_float(y)]
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// how many of the cells of a `columns` by `rows` grid over the unit square are left empty
+    fn empty_cells(points: &[Point2<f64>], columns: usize, rows: usize) -> usize {
+        let mut filled = vec![false; columns * rows];
+        for p in points {
+            assert!((0.0..1.0).contains(&p.x) && (0.0..1.0).contains(&p.y), "{p}");
+            filled[(p.y * rows as f64) as usize * columns + (p.x * columns as f64) as usize] = true;
+        }
+        filled.iter().filter(|f| !**f).count()
+    }
+
+    #[test]
+    pub fn test_samplers_stratify_pairs_of_dimensions() {
+        for kind in [SamplerKind::Stratified, SamplerKind::Sobol] {
+            let mut sampler = kind.build::<f64>(16, 7);
+            // look at a pair of dimensions past the first so the padding gets checked too
+            let points: Vec<_> = (0..16)
+                .map(|i| {
+                    sampler.start_pixel_sample((3, 5), i);
+                    sampler.next_2d();
+                    sampler.next_1d();
+                    sampler.next_2d()
+                })
+                .collect();
+            assert_eq!(empty_cells(&points, 4, 4), 0, "{kind:?}");
+        }
+        // halton's first two dimensions are in bases 2 and 3, so every 6 points fill a 2 by 3 grid
+        let mut halton = SamplerKind::Halton.build::<f64>(6, 7);
+        let points: Vec<_> = (0..6)
+            .map(|i| {
+                halton.start_pixel_sample((3, 5), i);
+                halton.next_2d()
+            })
+            .collect();
+        assert_eq!(empty_cells(&points, 2, 3), 0);
+        // independent numbers leave gaps, which is the point of the others
+        let mut independent = SamplerKind::Independent.build::<f64>(16, 7);
+        let points: Vec<_> = (0..16)
+            .map(|i| {
+                independent.start_pixel_sample((3, 5), i);
+                independent.next_2d()
+            })
+            .collect();
+        assert!(empty_cells(&points, 4, 4) > 0);
+    }
+
+    #[test]
+    pub fn test_samples_depend_only_on_pixel_index_and_seed() {
+        for kind in [SamplerKind::Independent, SamplerKind::Stratified, SamplerKind::Halton, SamplerKind::Sobol] {
+            let mut a = kind.build::<f64>(8, 1);
+            let mut b = kind.build::<f64>(8, 1);
+            a.start_pixel_sample((1, 2), 3);
+            // b has been busy elsewhere first
+            b.start_pixel_sample((9, 9), 0);
+            b.next_2d();
+            b.start_pixel_sample((1, 2), 3);
+            assert_eq!((a.next_2d(), a.next_1d()), (b.next_2d(), b.next_1d()), "{kind:?}");
+
+            let mut c = kind.build::<f64>(8, 2);
+            c.start_pixel_sample((1, 2), 3);
+            a.start_pixel_sample((1, 2), 3);
+            assert_ne!(a.next_2d(), c.next_2d(), "{kind:?}");
+        }
+    }
+}
@@ -9,6 +9,7 @@ pub trait Scalar: RealField + ClosedAdd + ClosedSub + ClosedMul + ClosedDiv + Nu
     const TWO: Self;
 
     fn from_float(val: f64) -> Self;
+    fn to_float(self) -> f64;
     fn scale_to_u8(self) -> u8;
 }
 
@@ -21,6 +22,10 @@ impl Scalar for f64 {
         val
     }
 
+    fn to_float(self) -> f64 {
+        self
+    }
+
     fn scale_to_u8(self) -> u8 {
         self.clamp(0.0, 1.0)
             .mul(255.0) as u8
@@ -36,6 +41,10 @@ impl Scalar for f32 {
         val as f32
     }
 
+    fn to_float(self) -> f64 {
+        self as f64
+    }
+
     fn scale_to_u8(self) -> u8 {
         self.clamp(0.0, 1.0)
             .mul(255.0) as u8